@@ -59,6 +59,11 @@ android {
         kotlinCompilerExtensionVersion = "1.5.4"
     }
 
+    // Keep the model uncompressed so Rust can read it straight from the APK via its fd
+    androidResources {
+        noCompress += "onnx"
+    }
+
     packaging {
         resources {
             excludes += "/META-INF/{AL2.0,LGPL2.1}"
@@ -14,12 +14,6 @@ class FrameProcessor(private val context: Context) {
 
     companion object {
         private const val TAG = "FrameProcessor"
-        
-        // Threshold for triggering filter on unsafe content
-        private const val UNSAFE_THRESHOLD = 0.60f
-        
-        // Threshold for suggestive (sexy) content — more lenient
-        private const val SUGGESTIVE_THRESHOLD = 0.80f
     }
 
     private val overlayManager = OverlayManager(context)
@@ -40,47 +34,26 @@ class FrameProcessor(private val context: Context) {
             val width = image.width
             val height = image.height
             
-            // Call Rust ML inference
-            val result = RustMLBridge.classifyFrame(imageData, width, height)
-            
-            val inferenceTime = System.currentTimeMillis() - startTime
-            
-            // Check if content should be filtered
-            val shouldFilter = when {
-                // Adult content (porn/hentai): filter if above threshold
-                result.isAdult && result.confidence >= UNSAFE_THRESHOLD -> true
-                // Suggestive content (sexy): higher threshold required
-                result.isSuggestive && result.confidence >= SUGGESTIVE_THRESHOLD -> true
-                // Safe content (neutral/drawing): don't filter
-                result.isSafe -> false
-                // Catch-all: filter if unsafe score is high
-                !result.isSafe && result.confidence >= UNSAFE_THRESHOLD -> true
-                else -> false
-            }
-            
-            if (!shouldFilter) {
+            // Classify, apply policy and obscure in a single Rust call
+            val result = RustMLBridge.processFrame(imageData, width, height)
+            val pixels = result.pixels
+
+            if (!result.shouldFilter || pixels == null) {
                 // Hide overlay for safe content
                 overlayManager.hideOverlay()
             } else {
-                // Generate blur effect using Rust
-                val blurRadius = calculateBlurRadius(result)
-                val blurredData = RustMLBridge.generateBlur(
-                    imageData,
-                    width,
-                    height,
-                    blurRadius
-                )
-                
                 // Convert to Bitmap and show overlay
-                val blurredBitmap = byteArrayToBitmap(blurredData, width, height)
-                overlayManager.showOverlay(blurredBitmap)
-                
+                val filteredBitmap = byteArrayToBitmap(pixels, width, height)
+                overlayManager.showOverlay(filteredBitmap)
+
                 // Log filter event (privacy-preserving)
-                logFilterEvent(result.category, result.confidence)
+                val action = if (result.effect == FrameResult.EFFECT_PIXELATE) "pixelate" else "blur"
+                logFilterEvent(result.topClass, result.confidence, action)
             }
-            
+
             val totalTime = System.currentTimeMillis() - startTime
-            Log.v(TAG, "Frame processed in ${totalTime}ms (inference: ${inferenceTime}ms)")
+            Log.v(TAG, "Frame processed in ${totalTime}ms (native: ${result.totalMicros / 1000}ms, " +
+                "classify: ${result.classifyMicros / 1000}ms, effect: ${result.effectMicros / 1000}ms)")
             
         } catch (e: Exception) {
             Log.e(TAG, "Error processing frame", e)
@@ -109,39 +82,17 @@ class FrameProcessor(private val context: Context) {
         return bitmap
     }
 
-    /**
-     * Calculate blur radius based on content category and confidence
-     * Adult content gets stronger blur than suggestive content
-     */
-    private fun calculateBlurRadius(result: ClassificationResult): Float {
-        val baseRadius = when {
-            result.isAdult -> when {
-                result.confidence > 0.95f -> 25f  // Very strong blur for confident adult
-                result.confidence > 0.85f -> 20f
-                result.confidence > 0.75f -> 15f
-                else -> 12f
-            }
-            result.isSuggestive -> when {
-                result.confidence > 0.95f -> 15f  // Moderate blur for suggestive
-                result.confidence > 0.85f -> 10f
-                else -> 8f
-            }
-            else -> 5f  // Light blur as fallback
-        }
-        return baseRadius
-    }
-
     /**
      * Log filter event (privacy-preserving: no screenshots)
      */
-    private suspend fun logFilterEvent(category: String, confidence: Float) {
+    private suspend fun logFilterEvent(category: String, confidence: Float, action: String) {
         try {
             repository?.let { repo ->
                 val event = FilterEvent(
                     timestamp = System.currentTimeMillis(),
                     category = category,
                     confidence = confidence,
-                    action = "blur"
+                    action = action
                 )
                 repo.insert(event)
             }
@@ -3,7 +3,8 @@ package com.pavlova.ml
 import android.content.Context
 import android.util.Log
 import java.io.File
-import java.io.FileOutputStream
+import java.io.FileNotFoundException
+import java.io.IOException
 
 /**
  * JNI Bridge to Rust ML library
@@ -13,18 +14,14 @@ object RustMLBridge {
     private const val TAG = "RustMLBridge"
     private const val LIBRARY_NAME = "pavlova_core"
     private const val MODEL_FILE = "nsfw_mobilenet_v2_140_224.onnx"
+    /** Optional manifest asset covered by an Ed25519 model signature */
+    private const val MANIFEST_FILE = "nsfw_mobilenet_v2_140_224.manifest"
 
-    // GantMan NSFW model class indices
-    private const val CLASS_DRAWING = 0
-    private const val CLASS_HENTAI = 1
-    private const val CLASS_NEUTRAL = 2
-    private const val CLASS_PORN = 3
-    private const val CLASS_SEXY = 4
-
-    private val CLASS_NAMES = arrayOf("drawing", "hentai", "neutral", "porn", "sexy")
-
+    /** Native engine handle returned by nativeInit (0 = not initialized) */
     @Volatile
-    private var isInitialized = false
+    private var engineHandle = 0L
+
+    private val isInitialized: Boolean get() = engineHandle != 0L
 
     init {
         try {
@@ -36,38 +33,139 @@ object RustMLBridge {
     }
 
     /**
-     * Initialize ML engine with model
+     * Initialize ML engine with the model packaged in the APK assets
+     * The model is read natively straight from the uncompressed asset, without copying it
+     * to internal storage. With [integrity], the model is verified in Rust before it is
+     * parsed; a mismatch throws [ModelIntegrityException] so the caller can enter safe mode.
+     */
+    fun initialize(context: Context, integrity: ModelIntegrity? = null) {
+        initEngine("assets/$MODEL_FILE") {
+            val manifest = readAssetOrNull(context, MANIFEST_FILE)
+            try {
+                context.assets.openFd(MODEL_FILE).use { afd ->
+                    nativeInitFromFd(
+                        afd.parcelFileDescriptor.fd, afd.startOffset, afd.length, manifest,
+                        integrity?.sha256Hex, integrity?.publicKey, integrity?.signature
+                    )
+                }
+            } catch (e: FileNotFoundException) {
+                // Compressed asset: no descriptor available, read it into memory instead
+                Log.w(TAG, "Model asset is compressed, loading through memory")
+                val bytes = context.assets.open(MODEL_FILE).use { it.readBytes() }
+                nativeInitFromBytes(bytes, manifest, integrity?.sha256Hex, integrity?.publicKey, integrity?.signature)
+            }
+        }
+    }
+
+    /**
+     * Initialize ML engine from a model file, e.g. a downloaded model update
+     * The manifest, if any, is the file next to it with a `.manifest` extension.
      */
-    fun initialize(context: Context) {
+    fun initialize(modelFile: File, integrity: ModelIntegrity? = null) {
+        initEngine(modelFile.absolutePath) {
+            if (integrity == null) {
+                nativeInit(modelFile.absolutePath)
+            } else {
+                nativeInitVerified(modelFile.absolutePath, integrity.sha256Hex, integrity.publicKey, integrity.signature)
+            }
+        }
+    }
+
+    /**
+     * Initialize ML engine from model bytes already in memory, e.g. after decryption
+     */
+    fun initialize(modelBytes: ByteArray, manifest: ByteArray? = null, integrity: ModelIntegrity? = null) {
+        initEngine("memory") {
+            nativeInitFromBytes(modelBytes, manifest, integrity?.sha256Hex, integrity?.publicKey, integrity?.signature)
+        }
+    }
+
+    /**
+     * Run a native init call and keep the returned handle
+     * A failed integrity check surfaces as [ModelIntegrityException].
+     */
+    private inline fun initEngine(source: String, init: () -> Long) {
         if (isInitialized) {
             Log.w(TAG, "Already initialized")
             return
         }
 
         try {
-            // Extract model from assets to internal storage
-            val modelFile = extractModelFromAssets(context)
-            
-            // Initialize native ML engine
-            val success = nativeInit(modelFile.absolutePath)
-            
-            if (success) {
-                isInitialized = true
-                Log.d(TAG, "ML engine initialized successfully")
+            val handle = try {
+                init()
+            } catch (e: SecurityException) {
+                throw ModelIntegrityException(e.message ?: "Model integrity check failed", e)
+            }
+
+            if (handle != 0L) {
+                engineHandle = handle
+                Log.d(TAG, "ML engine initialized successfully from $source")
             } else {
                 throw RuntimeException("Native initialization failed")
             }
-            
+
         } catch (e: Exception) {
             Log.e(TAG, "Failed to initialize ML engine", e)
             throw e
         }
     }
 
+    /**
+     * Replace the model with [modelFile] (e.g. a downloaded update) without interrupting
+     * classification: the new model is loaded and self-tested natively, then swapped in
+     * while frames already in flight finish on the old one.
+     * Blocks while loading, so call it off the main thread. Returns false and keeps the
+     * current model if the new one fails to load or validate; throws
+     * [ModelIntegrityException] if it fails [integrity].
+     */
+    fun swapModel(modelFile: File, integrity: ModelIntegrity? = null): Boolean {
+        if (!isInitialized) {
+            throw IllegalStateException("RustMLBridge not initialized")
+        }
+
+        val swapped = try {
+            nativeSwapModel(
+                engineHandle, modelFile.absolutePath,
+                integrity?.sha256Hex, integrity?.publicKey, integrity?.signature
+            )
+        } catch (e: SecurityException) {
+            throw ModelIntegrityException(e.message ?: "Model integrity check failed", e)
+        }
+        if (swapped) {
+            Log.d(TAG, "Model swapped to ${modelFile.absolutePath}")
+        } else {
+            Log.w(TAG, "Model swap rejected, keeping the current model")
+        }
+        return swapped
+    }
+
+    /**
+     * Go back to the model used before the last [swapModel]
+     * Returns false if there is nothing to roll back to.
+     */
+    fun rollbackModel(): Boolean {
+        if (!isInitialized) {
+            throw IllegalStateException("RustMLBridge not initialized")
+        }
+        return nativeRollbackModel(engineHandle)
+    }
+
+    /**
+     * Describe the loaded model: input/output shapes, labels, hash and graph statistics
+     * Returns null if the engine is not initialized.
+     */
+    fun modelInfo(): ModelInfo? {
+        if (!isInitialized) {
+            return null
+        }
+        return nativeModelInfo(engineHandle)
+    }
+
     /**
      * Classify a frame
      * Returns ClassificationResult with 5-class scores from GantMan NSFW model:
      * [drawing, hentai, neutral, porn, sexy]
+     * Safety, safety group and confidence are computed in Rust.
      */
     fun classifyFrame(imageData: ByteArray, width: Int, height: Int): ClassificationResult {
         if (!isInitialized) {
@@ -75,54 +173,17 @@ object RustMLBridge {
         }
 
         return try {
-            // scores = [drawing, hentai, neutral, porn, sexy]
-            val scores = nativeClassifyFrame(imageData, width, height)
-            
-            // Find top class
-            val topIndex = scores.indices.maxByOrNull { scores[it] } ?: CLASS_NEUTRAL
-            val topClass = CLASS_NAMES[topIndex]
-            val topScore = scores[topIndex]
-            
-            // Safe classes: neutral + drawing
-            // Unsafe classes: hentai + porn + sexy
-            val safeScore = scores[CLASS_DRAWING] + scores[CLASS_NEUTRAL]
-            val unsafeScore = scores[CLASS_HENTAI] + scores[CLASS_PORN] + scores[CLASS_SEXY]
-            val isSafe = safeScore > unsafeScore
-            
-            // Determine category for filtering policy
-            val category = when (topIndex) {
-                CLASS_NEUTRAL -> "neutral"
-                CLASS_DRAWING -> "drawing"
-                CLASS_PORN -> "porn"
-                CLASS_HENTAI -> "hentai"
-                CLASS_SEXY -> "sexy"
-                else -> "unknown"
-            }
-            
-            val confidence = if (isSafe) safeScore else unsafeScore
-            
-            Log.d(TAG, "Classification: $category (${String.format("%.3f", topScore)}) " +
-                "scores: d=${String.format("%.3f", scores[0])} h=${String.format("%.3f", scores[1])} " +
-                "n=${String.format("%.3f", scores[2])} p=${String.format("%.3f", scores[3])} " +
-                "s=${String.format("%.3f", scores[4])}")
-            
-            ClassificationResult(
-                isSafe = isSafe,
-                confidence = confidence,
-                category = category,
-                scores = scores,
-                topClass = topClass
-            )
+            val result = nativeClassifyFrameResult(engineHandle, imageData, width, height)
+                ?: return ClassificationResult.failSafe("native result unavailable")
+
+            result.error?.let { Log.w(TAG, "Native classification error: $it") }
+            Log.d(TAG, "Classification: ${result.category} (${result.safetyGroup}, " +
+                "${String.format("%.3f", result.confidence)}) in ${result.inferenceMicros}us")
+
+            result
         } catch (e: Exception) {
             Log.e(TAG, "Classification failed", e)
-            // Fail-safe: assume safe
-            ClassificationResult(
-                isSafe = true,
-                confidence = 0.0f,
-                category = "error",
-                scores = floatArrayOf(0f, 0f, 1f, 0f, 0f),
-                topClass = "neutral"
-            )
+            ClassificationResult.failSafe(e.message ?: "classification failed")
         }
     }
 
@@ -158,73 +219,193 @@ object RustMLBridge {
         }
     }
 
+    /**
+     * Classify a frame, apply the filtering policy and generate the obscured
+     * frame in a single native call (one image conversion instead of two)
+     */
+    fun processFrame(imageData: ByteArray, width: Int, height: Int): FrameResult {
+        if (!isInitialized) {
+            throw IllegalStateException("RustMLBridge not initialized")
+        }
+
+        return try {
+            nativeProcessFrame(engineHandle, imageData, width, height) ?: FrameResult.ALLOW
+        } catch (e: Exception) {
+            Log.e(TAG, "Frame processing failed", e)
+            FrameResult.ALLOW // Fail-safe: assume safe
+        }
+    }
+
+    /**
+     * Load the list of always-allowed and always-blocked images consulted by [processFrame]
+     * A missing file starts an empty list, created by the first [addImageOverride].
+     * Returns the number of listed images, or -1 if the file cannot be read.
+     */
+    fun loadImageOverrides(file: File): Int = nativeLoadOverrides(file.absolutePath)
+
+    /**
+     * Always obscure ([block] true) or always allow ([block] false) frames showing this
+     * image, even after recompression or rescaling; the list is saved immediately
+     * Near-uniform frames (black or loading screens) cannot be allowed.
+     * Returns false if no list is loaded or it cannot be saved.
+     */
+    fun addImageOverride(imageData: ByteArray, width: Int, height: Int, block: Boolean): Boolean =
+        nativeAddOverride(imageData, width, height, block)
+
+    /**
+     * Forget the overrides matching this frame
+     * Returns how many were removed, or -1 if no list is loaded or it cannot be saved.
+     */
+    fun removeImageOverride(imageData: ByteArray, width: Int, height: Int): Int =
+        nativeRemoveOverride(imageData, width, height)
+
     /**
      * Cleanup native resources
      */
     fun destroy() {
-        if (isInitialized) {
-            nativeDestroy()
-            isInitialized = false
+        val handle = engineHandle
+        if (handle != 0L) {
+            // Frames still in flight on other threads finish on the native side
+            engineHandle = 0L
+            nativeDestroy(handle)
             Log.d(TAG, "ML engine destroyed")
         }
     }
 
     /**
-     * Extract model from assets to internal storage
+     * Read a small asset fully, or null if it is not packaged
      */
-    private fun extractModelFromAssets(context: Context): File {
-        val modelFile = File(context.filesDir, MODEL_FILE)
-        
-        if (modelFile.exists()) {
-            Log.d(TAG, "Model file already exists: ${modelFile.absolutePath}")
-            return modelFile
-        }
-
-        context.assets.open(MODEL_FILE).use { input ->
-            FileOutputStream(modelFile).use { output ->
-                input.copyTo(output)
-            }
+    private fun readAssetOrNull(context: Context, name: String): ByteArray? =
+        try {
+            context.assets.open(name).use { it.readBytes() }
+        } catch (e: IOException) {
+            null
         }
-        
-        Log.d(TAG, "Model extracted to: ${modelFile.absolutePath}")
-        return modelFile
-    }
 
     // Native method declarations
-    private external fun nativeInit(modelPath: String): Boolean
-    private external fun nativeClassifyFrame(imageData: ByteArray, width: Int, height: Int): FloatArray
+    private external fun nativeInit(modelPath: String): Long
+    private external fun nativeInitVerified(modelPath: String, sha256Hex: String?, publicKey: ByteArray?, signature: ByteArray?): Long
+    private external fun nativeInitFromBytes(modelBytes: ByteArray, manifest: ByteArray?, sha256Hex: String?, publicKey: ByteArray?, signature: ByteArray?): Long
+    private external fun nativeInitFromFd(fd: Int, offset: Long, length: Long, manifest: ByteArray?, sha256Hex: String?, publicKey: ByteArray?, signature: ByteArray?): Long
+    private external fun nativeSwapModel(handle: Long, modelPath: String, sha256Hex: String?, publicKey: ByteArray?, signature: ByteArray?): Boolean
+    private external fun nativeRollbackModel(handle: Long): Boolean
+    private external fun nativeModelInfo(handle: Long): ModelInfo?
+    private external fun nativeClassifyFrame(handle: Long, imageData: ByteArray, width: Int, height: Int): FloatArray
+    private external fun nativeClassifyFrameResult(handle: Long, imageData: ByteArray, width: Int, height: Int): ClassificationResult?
     private external fun nativeGenerateBlur(imageData: ByteArray, width: Int, height: Int, radius: Float): ByteArray
     private external fun nativeGeneratePixelation(imageData: ByteArray, width: Int, height: Int, blockSize: Int): ByteArray
-    private external fun nativeDestroy()
+    private external fun nativeProcessFrame(handle: Long, imageData: ByteArray, width: Int, height: Int): FrameResult?
+    private external fun nativeLoadOverrides(path: String): Int
+    private external fun nativeAddOverride(imageData: ByteArray, width: Int, height: Int, block: Boolean): Boolean
+    private external fun nativeRemoveOverride(imageData: ByteArray, width: Int, height: Int): Int
+    private external fun nativeDestroy(handle: Long)
+}
+
+/**
+ * Expected identity of the model file, checked natively before the model is parsed.
+ * Either a pinned SHA-256 digest, or an Ed25519 publisher signature over the model
+ * and its manifest.
+ */
+class ModelIntegrity private constructor(
+    val sha256Hex: String?,
+    val publicKey: ByteArray?,
+    val signature: ByteArray?
+) {
+    companion object {
+        /**
+         * Pin a SHA-256 digest (64 hex digits): of the model alone, or, when a manifest is
+         * loaded with it, of the model and manifest together (`pinned_sha256_hex` in Rust).
+         * A model-only digest is rejected alongside a manifest.
+         */
+        fun sha256(hex: String) = ModelIntegrity(hex, null, null)
+
+        /** Ed25519 public key (32 bytes) and signature (64 bytes) */
+        fun ed25519(publicKey: ByteArray, signature: ByteArray) = ModelIntegrity(null, publicKey, signature)
+    }
+}
+
+/** The model file failed its integrity check; the app should enter safe mode */
+class ModelIntegrityException(message: String, cause: Throwable? = null) : SecurityException(message, cause)
+
+/**
+ * Description of the loaded model, for diagnostics and settings screens.
+ * Constructed from Rust (`nativeModelInfo`); keep the constructor signature in sync.
+ */
+class ModelInfo(
+    /** Model file path, or the source name of a model loaded from memory */
+    val source: String,
+    /** Lowercase hex SHA-256 of the model bytes */
+    val sha256: String,
+    val fileSize: Long,
+    /** Inputs as `name: dtype[dims]`, `?` for symbolic dimensions */
+    val inputs: Array<String>,
+    /** Outputs as `name: dtype[dims]` */
+    val outputs: Array<String>,
+    /** Class names of the output scores, in order */
+    val labels: Array<String>,
+    /** Post-processing applied to the raw output: "none", "softmax" or "sigmoid" */
+    val activation: String,
+    /** Nodes in the model file, counting inputs and initializers */
+    val sourceNodeCount: Int,
+    /** Nodes in the graph that runs, after tract's optimizations */
+    val nodeCount: Int,
+    /** Operators as `name xcount`, most used first */
+    val ops: Array<String>
+) {
+    override fun toString(): String =
+        "ModelInfo(source=$source, sha256=$sha256, size=$fileSize, inputs=${inputs.joinToString()}, " +
+            "outputs=${outputs.joinToString()}, labels=${labels.joinToString()}, activation=$activation, nodes=$nodeCount)"
 }
 
 /**
  * Classification result with 5-class NSFW scores
  * Classes: drawing, hentai, neutral, porn, sexy
+ * Constructed from Rust (`nativeClassifyFrameResult`); keep the constructor signature in sync.
  */
 data class ClassificationResult(
     /** Whether content is safe (neutral + drawing > hentai + porn + sexy) */
     val isSafe: Boolean,
     /** Aggregate confidence of safety determination */
     val confidence: Float,
-    /** Top category name */
+    /** Top category name ("error" when classification failed) */
     val category: String,
     /** Raw per-class scores [drawing, hentai, neutral, porn, sexy] */
     val scores: FloatArray = floatArrayOf(0f, 0f, 1f, 0f, 0f),
     /** Name of highest-scoring class */
-    val topClass: String = "neutral"
+    val topClass: String = "neutral",
+    /** Safety group of the top class: "safe", "suggestive" or "adult" */
+    val safetyGroup: String = SAFETY_SAFE,
+    /** Native preprocessing + inference time */
+    val inferenceMicros: Long = 0,
+    /** Error message when classification failed, null on success */
+    val error: String? = null
 ) {
+    companion object {
+        const val SAFETY_SAFE = "safe"
+        const val SAFETY_SUGGESTIVE = "suggestive"
+        const val SAFETY_ADULT = "adult"
+
+        /** Fail-safe result: assume safe */
+        fun failSafe(error: String) = ClassificationResult(
+            isSafe = true,
+            confidence = 0.0f,
+            category = "error",
+            error = error
+        )
+    }
+
     /** Whether content is adult (porn or hentai) */
-    val isAdult: Boolean get() = category == "porn" || category == "hentai"
+    val isAdult: Boolean get() = safetyGroup == SAFETY_ADULT
     
     /** Whether content is suggestive (sexy) */
-    val isSuggestive: Boolean get() = category == "sexy"
+    val isSuggestive: Boolean get() = safetyGroup == SAFETY_SUGGESTIVE
     
     override fun equals(other: Any?): Boolean {
         if (this === other) return true
         if (other !is ClassificationResult) return false
         return isSafe == other.isSafe && confidence == other.confidence &&
-            category == other.category && scores.contentEquals(other.scores)
+            category == other.category && scores.contentEquals(other.scores) &&
+            safetyGroup == other.safetyGroup && error == other.error
     }
     
     override fun hashCode(): Int {
@@ -232,6 +413,58 @@ data class ClassificationResult(
         result = 31 * result + confidence.hashCode()
         result = 31 * result + category.hashCode()
         result = 31 * result + scores.contentHashCode()
+        result = 31 * result + safetyGroup.hashCode()
         return result
     }
 }
+
+/**
+ * Result of the combined classify-and-obscure native call.
+ * Constructed from Rust (`nativeProcessFrame`); keep the constructor signature in sync.
+ */
+class FrameResult(
+    /** Whether the policy decided to obscure the frame */
+    val shouldFilter: Boolean,
+    /** Effect applied: EFFECT_NONE, EFFECT_BLUR or EFFECT_PIXELATE */
+    val effect: Int,
+    /** Blur radius or pixelation block size */
+    val effectParam: Float,
+    /** Raw per-class scores [drawing, hentai, neutral, porn, sexy] */
+    val scores: FloatArray,
+    /** Name of highest-scoring class */
+    val topClass: String,
+    /** Whether content is safe (neutral + drawing > hentai + porn + sexy) */
+    val isSafe: Boolean,
+    /** Aggregate confidence of safety determination */
+    val confidence: Float,
+    /** Preprocessing + inference time */
+    val classifyMicros: Long,
+    /** Blur / pixelation time */
+    val effectMicros: Long,
+    /** Total native processing time */
+    val totalMicros: Long,
+    /** Obscured RGBA frame, null when the frame is allowed */
+    val pixels: ByteArray?,
+    /** "allow" or "block" when a listed image decided the frame instead of the model, else null */
+    val overrideVerdict: String? = null
+) {
+    companion object {
+        const val EFFECT_NONE = 0
+        const val EFFECT_BLUR = 1
+        const val EFFECT_PIXELATE = 2
+
+        val ALLOW = FrameResult(
+            shouldFilter = false,
+            effect = EFFECT_NONE,
+            effectParam = 0f,
+            scores = floatArrayOf(0f, 0f, 1f, 0f, 0f),
+            topClass = "neutral",
+            isSafe = true,
+            confidence = 0f,
+            classifyMicros = 0,
+            effectMicros = 0,
+            totalMicros = 0,
+            pixels = null
+        )
+    }
+}
@@ -0,0 +1,101 @@
+//! Per-frame hot path: preprocessing, effects, YUV conversion and inference.
+//!
+//! Run with `cargo bench`. Inference uses a small ONNX model
+//! generated in code, so it measures tract's per-run overhead rather than MobileNet.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::time::Duration;
+
+use pavlova_core::config::{MODEL_INPUT_HEIGHT, MODEL_INPUT_WIDTH};
+use pavlova_core::image::{blur, pixelate, preprocess_for_model, resize_bilinear_rgba};
+use pavlova_core::utils::yuv420_to_rgba;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+/// Common screen sizes: 720p, a 20:9 phone (1080×2400) and 1440p
+const SCREEN_SIZES: [(usize, usize); 3] = [(1280, 720), (1080, 2400), (2560, 1440)];
+
+/// Phone screen used where one size is representative
+const PHONE: (usize, usize) = (1080, 2400);
+
+/// Deterministic, non-uniform RGBA test frame
+fn test_frame(width: usize, height: usize) -> Vec<u8> {
+    (0..width * height * 4).map(|i| (i * 31 % 251) as u8).collect()
+}
+
+fn bench_preprocess(c: &mut Criterion) {
+    let mut group = c.benchmark_group("preprocess_for_model");
+    for (width, height) in SCREEN_SIZES {
+        let frame = test_frame(width, height);
+        group.throughput(Throughput::Bytes(frame.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(format!("{}x{}", width, height)), &frame, |b, frame| {
+            b.iter(|| preprocess_for_model(black_box(frame), width, height).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_resize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resize_bilinear_rgba");
+    for (width, height) in SCREEN_SIZES {
+        let frame = test_frame(width, height);
+        group.bench_with_input(BenchmarkId::from_parameter(format!("{}x{}", width, height)), &frame, |b, frame| {
+            b.iter(|| resize_bilinear_rgba(black_box(frame), width, height, MODEL_INPUT_WIDTH, MODEL_INPUT_HEIGHT).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_blur(c: &mut Criterion) {
+    let (width, height) = PHONE;
+    let frame = test_frame(width, height);
+    let mut group = c.benchmark_group("blur_1080x2400");
+    // Full-screen blur takes tens to hundreds of milliseconds per run
+    group.sample_size(10).measurement_time(Duration::from_secs(10));
+    for radius in [5.0f32, 15.0, 25.0] {
+        group.bench_with_input(BenchmarkId::from_parameter(radius), &radius, |b, &radius| {
+            b.iter(|| blur(black_box(&frame), width, height, radius).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_pixelate(c: &mut Criterion) {
+    let (width, height) = PHONE;
+    let frame = test_frame(width, height);
+    let mut group = c.benchmark_group("pixelate_1080x2400");
+    group.sample_size(20);
+    for block_size in [8usize, 16, 32, 64] {
+        group.bench_with_input(BenchmarkId::from_parameter(block_size), &block_size, |b, &block_size| {
+            b.iter(|| pixelate(black_box(&frame), width, height, block_size).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_yuv(c: &mut Criterion) {
+    let mut group = c.benchmark_group("yuv420_to_rgba");
+    group.sample_size(20);
+    for (width, height) in SCREEN_SIZES {
+        let y: Vec<u8> = (0..width * height).map(|i| (i % 251) as u8).collect();
+        let u: Vec<u8> = (0..width * height / 4).map(|i| (i % 241) as u8).collect();
+        let v: Vec<u8> = (0..width * height / 4).map(|i| (i % 239) as u8).collect();
+        group.bench_function(format!("{}x{}", width, height), |b| {
+            b.iter(|| yuv420_to_rgba(black_box(&y), &u, &v, width, height).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_classify(c: &mut Criterion) {
+    let engine = common::colour_engine("bench");
+    let input = preprocess_for_model(&test_frame(PHONE.0, PHONE.1), PHONE.0, PHONE.1).unwrap();
+
+    c.bench_function("classify_preprocessed/synthetic", |b| {
+        b.iter(|| engine.classify_preprocessed(black_box(&input)).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_preprocess, bench_resize, bench_blur, bench_pixelate, bench_yuv, bench_classify);
+criterion_main!(benches);
@@ -0,0 +1,27 @@
+//! Build script: generates the C header for the `capi` feature.
+
+fn main() {
+    #[cfg(feature = "capi")]
+    generate_c_header();
+}
+
+#[cfg(feature = "capi")]
+fn generate_c_header() {
+    use std::path::PathBuf;
+
+    let crate_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    // Never write into the source tree: it may be read-only or vendored
+    let include_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("include");
+    let capi_src = crate_dir.join("src").join("capi.rs");
+    println!("cargo:rerun-if-changed={}", capi_src.display());
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).expect("invalid cbindgen.toml");
+    // Only the C API module: the JNI exports are not meant for C callers
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(capi_src)
+        .generate()
+        .expect("failed to generate C header")
+        .write_to_file(include_dir.join("pavlova.h"));
+}
@@ -0,0 +1,43 @@
+//! blur / pixelate / resize / preprocess on arbitrary buffers, dimensions and parameters.
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+use pavlova_core::image::{blur, pixelate, preprocess_for_model, resize_bilinear_rgba};
+
+#[derive(Arbitrary, Debug)]
+enum Operation {
+    Blur { radius: f32 },
+    Pixelate { block_size: usize },
+    Resize { dst_w: u8, dst_h: u8 },
+    Preprocess,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    // u8 sides keep every run fast while still covering mismatched and empty sizes
+    width: u8,
+    height: u8,
+    operation: Operation,
+    data: Vec<u8>,
+}
+
+fuzz_target!(|input: Input| {
+    let (width, height) = (input.width as usize, input.height as usize);
+    let data = &input.data;
+    match input.operation {
+        Operation::Blur { radius } => {
+            let _ = blur(data, width, height, radius);
+        }
+        Operation::Pixelate { block_size } => {
+            let _ = pixelate(data, width, height, block_size);
+        }
+        Operation::Resize { dst_w, dst_h } => {
+            let _ = resize_bilinear_rgba(data, width, height, dst_w as usize, dst_h as usize);
+        }
+        Operation::Preprocess => {
+            let _ = preprocess_for_model(data, width, height);
+        }
+    }
+});
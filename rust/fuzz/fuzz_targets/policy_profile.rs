@@ -0,0 +1,13 @@
+//! FilterPolicy::from_profile on arbitrary text.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use pavlova_core::policy::FilterPolicy;
+
+fuzz_target!(|text: &str| {
+    if let Ok(policy) = FilterPolicy::from_profile(text) {
+        assert!((0.0..=1.0).contains(&policy.unsafe_threshold));
+        assert!((0.0..=1.0).contains(&policy.suggestive_threshold));
+    }
+});
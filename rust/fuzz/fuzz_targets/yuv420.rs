@@ -0,0 +1,20 @@
+//! yuv420_to_rgba with arbitrary dimensions and plane lengths.
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+use pavlova_core::utils::yuv420_to_rgba;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    width: u8,
+    height: u8,
+    y: Vec<u8>,
+    u: Vec<u8>,
+    v: Vec<u8>,
+}
+
+fuzz_target!(|input: Input| {
+    let _ = yuv420_to_rgba(&input.y, &input.u, &input.v, input.width as usize, input.height as usize);
+});
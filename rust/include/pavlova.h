@@ -0,0 +1,114 @@
+#ifndef PAVLOVA_H
+#define PAVLOVA_H
+
+/* Generated by cbindgen from src/capi.rs - do not edit. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// Number of scores in `PavlovaClassification`
+#define PAVLOVA_NUM_CLASSES 5
+
+// Result code of every C API call
+typedef enum PavlovaStatus {
+  PAVLOVA_STATUS_OK = 0,
+  // A required pointer argument was null
+  PAVLOVA_STATUS_NULL_POINTER = 1,
+  // Bad size, dimensions, parameter or non-UTF-8 string
+  PAVLOVA_STATUS_INVALID_ARGUMENT = 2,
+  // The handle does not refer to a live engine
+  PAVLOVA_STATUS_INVALID_HANDLE = 3,
+  // The model file could not be loaded
+  PAVLOVA_STATUS_MODEL_LOAD = 4,
+  // Preprocessing or inference failed
+  PAVLOVA_STATUS_INFERENCE = 5,
+  // Blur / pixelation failed
+  PAVLOVA_STATUS_IMAGE = 6,
+  // A Rust panic was caught at the boundary
+  PAVLOVA_STATUS_PANIC = 7,
+  // The model failed its integrity check (digest or signature mismatch)
+  PAVLOVA_STATUS_INTEGRITY = 8,
+} PavlovaStatus;
+
+// Classification output, scores in `CLASSES` order (drawing, hentai, neutral, porn, sexy)
+typedef struct PavlovaClassification {
+  float scores[PAVLOVA_NUM_CLASSES];
+  uint32_t top_class_index;
+  bool is_safe;
+  float confidence;
+} PavlovaClassification;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Load an ONNX model and return its engine handle in `out_handle`
+//
+// # Safety
+// `model_path` must be a valid NUL-terminated string and `out_handle` a valid pointer.
+enum PavlovaStatus pavlova_engine_new(const char *model_path, uint64_t *out_handle);
+
+// Load an ONNX model after verifying it, returning its engine handle in `out_handle`
+// Pass either `sha256` (32 bytes, covering the manifest if there is one, see
+// `pinned_digest`), or `public_key` (32 bytes) and `signature` (64 bytes) over the model
+// and its manifest; unused arguments are NULL. Passing both returns
+// `PavlovaStatus::InvalidArgument`; a failed check returns `PavlovaStatus::Integrity`.
+//
+// # Safety
+// `model_path` must be a valid NUL-terminated string, `out_handle` a valid pointer, and each
+// non-NULL key argument must point to the number of bytes given above.
+enum PavlovaStatus pavlova_engine_new_verified(const char *model_path,
+                                               const uint8_t *sha256,
+                                               const uint8_t *public_key,
+                                               const uint8_t *signature,
+                                               uint64_t *out_handle);
+
+// Classify an RGBA frame of `width`×`height` pixels (`len` must equal `width * height * 4`)
+//
+// # Safety
+// `rgba` must point to `len` readable bytes and `out` must be a valid pointer.
+enum PavlovaStatus pavlova_engine_classify(uint64_t handle,
+                                           const uint8_t *rgba,
+                                           size_t len,
+                                           uint32_t width,
+                                           uint32_t height,
+                                           struct PavlovaClassification *out);
+
+// Blur an RGBA frame into `out` (`out_len` must be at least `len`); needs no engine
+// `radius` must be within 0 to `MAX_BLUR_RADIUS` (256).
+//
+// # Safety
+// `rgba` must point to `len` readable bytes and `out` to `out_len` writable bytes.
+enum PavlovaStatus pavlova_engine_blur(const uint8_t *rgba,
+                                       size_t len,
+                                       uint32_t width,
+                                       uint32_t height,
+                                       float radius,
+                                       uint8_t *out,
+                                       size_t out_len);
+
+// Pixelate an RGBA frame into `out` (`out_len` must be at least `len`); needs no engine
+//
+// # Safety
+// `rgba` must point to `len` readable bytes and `out` to `out_len` writable bytes.
+enum PavlovaStatus pavlova_engine_pixelate(const uint8_t *rgba,
+                                           size_t len,
+                                           uint32_t width,
+                                           uint32_t height,
+                                           uint32_t block_size,
+                                           uint8_t *out,
+                                           size_t out_len);
+
+// Release an engine; in-flight classifications on other threads finish first
+enum PavlovaStatus pavlova_engine_free(uint64_t handle);
+
+// Message of the last failed call on this thread, or NULL if it succeeded
+// The pointer stays valid until the next C API call on the same thread.
+const char *pavlova_last_error_message(void);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* PAVLOVA_H */
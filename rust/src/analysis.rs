@@ -0,0 +1,261 @@
+//! Image-statistics heuristics.
+//!
+//! Fast, explainable signals computed from RGBA frames alongside the neural model:
+//! skin-tone ratio, largest connected skin region, colour histogram entropy and edge
+//! density, from which "mostly text or UI" is derived. Frames are sampled on a grid of
+//! at most 128×128 pixels, so the cost does not grow with the screen resolution.
+
+use std::error::Error;
+
+/// Longest side of the sampling grid
+const SAMPLE_GRID: usize = 128;
+
+/// Histogram bits per channel for colour entropy (8 × 8 × 8 bins, at most 9 bits)
+const HISTOGRAM_BITS: u32 = 3;
+
+/// Luma difference to a right or lower neighbour that counts as an edge
+const EDGE_THRESHOLD: i32 = 48;
+
+/// Text and UI frames have sharp edges over a few flat colours
+const TEXT_MIN_EDGE_DENSITY: f32 = 0.05;
+const TEXT_MAX_ENTROPY: f32 = 4.0;
+const TEXT_MAX_SKIN_RATIO: f32 = 0.1;
+
+/// Statistics of a frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ImageStats {
+    /// Fraction of sampled pixels with a skin tone
+    pub skin_ratio: f32,
+    /// Fraction of sampled pixels in the largest 4-connected skin region
+    pub largest_skin_region: f32,
+    /// Shannon entropy of the colour histogram in bits (0 for one colour, at most 9)
+    pub colour_entropy: f32,
+    /// Fraction of sampled pixels on a luma edge
+    pub edge_density: f32,
+    /// Many edges, few colours and little skin: text, UI or a document
+    pub mostly_text: bool,
+}
+
+/// Compute all statistics of an RGBA frame
+pub fn analyze(rgba_data: &[u8], width: usize, height: usize) -> Result<ImageStats, Box<dyn Error>> {
+    let grid = Grid::sample(rgba_data, width, height)?;
+    let skin: Vec<bool> = grid.pixels.iter().map(|&[r, g, b]| is_skin(r, g, b)).collect();
+
+    let skin_ratio = ratio(skin.iter().filter(|&&s| s).count(), skin.len());
+    let largest_skin_region = ratio(largest_region(&skin, grid.width, grid.height), skin.len());
+    let colour_entropy = colour_entropy(&grid.pixels);
+    let edge_density = edge_density(&grid);
+
+    Ok(ImageStats {
+        skin_ratio,
+        largest_skin_region,
+        colour_entropy,
+        edge_density,
+        mostly_text: edge_density >= TEXT_MIN_EDGE_DENSITY
+            && colour_entropy <= TEXT_MAX_ENTROPY
+            && skin_ratio <= TEXT_MAX_SKIN_RATIO,
+    })
+}
+
+/// Fraction of skin-tone pixels only, for pre-filters that need nothing else
+pub fn skin_ratio(rgba_data: &[u8], width: usize, height: usize) -> Result<f32, Box<dyn Error>> {
+    let grid = Grid::sample(rgba_data, width, height)?;
+    let skin = grid.pixels.iter().filter(|&&[r, g, b]| is_skin(r, g, b)).count();
+    Ok(ratio(skin, grid.pixels.len()))
+}
+
+/// Skin tone in both YCbCr (Chai & Ngan: 77 <= Cb <= 127, 133 <= Cr <= 173) and HSV
+/// (hue up to 50°, saturation 0.2..=0.7, value at least 0.35)
+/// The chroma box ignores brightness, so it holds across lighting and skin colours; the
+/// HSV bounds reject the saturated reds and oranges it also admits.
+pub fn is_skin(r: u8, g: u8, b: u8) -> bool {
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+    let cb = 128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b;
+    let cr = 128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b;
+    if !((77.0..=127.0).contains(&cb) && (133.0..=173.0).contains(&cr)) {
+        return false;
+    }
+
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    if max != r || delta == 0.0 {
+        return false;
+    }
+    let hue = 60.0 * (g - b) / delta;
+    (0.0..=50.0).contains(&hue) && (0.2..=0.7).contains(&(delta / max)) && max >= 0.35 * 255.0
+}
+
+/// Frame sampled with nearest-neighbour on a grid of at most `SAMPLE_GRID` per side
+struct Grid {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Grid {
+    fn sample(rgba_data: &[u8], width: usize, height: usize) -> Result<Self, Box<dyn Error>> {
+        if width == 0 || height == 0 {
+            return Err(format!("Cannot analyse {}x{} image: empty", width, height).into());
+        }
+        let expected_len = width.checked_mul(height).and_then(|p| p.checked_mul(4));
+        if expected_len != Some(rgba_data.len()) {
+            return Err(format!("RGBA data size mismatch for {}x{}: got {} bytes", width, height, rgba_data.len()).into());
+        }
+
+        let step = width.max(height).div_ceil(SAMPLE_GRID);
+        let (grid_width, grid_height) = (width.div_ceil(step), height.div_ceil(step));
+        let mut pixels = Vec::with_capacity(grid_width * grid_height);
+        for y in (0..height).step_by(step) {
+            for x in (0..width).step_by(step) {
+                let i = (y * width + x) * 4;
+                pixels.push([rgba_data[i], rgba_data[i + 1], rgba_data[i + 2]]);
+            }
+        }
+        Ok(Grid {
+            width: grid_width,
+            height: grid_height,
+            pixels,
+        })
+    }
+}
+
+fn ratio(count: usize, total: usize) -> f32 {
+    count as f32 / total as f32
+}
+
+/// Size of the largest 4-connected region of `true` cells
+fn largest_region(mask: &[bool], width: usize, height: usize) -> usize {
+    let mut visited = vec![false; mask.len()];
+    let mut stack = Vec::new();
+    let mut largest = 0;
+    for start in 0..mask.len() {
+        if !mask[start] || visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        stack.push(start);
+        let mut size = 0;
+        while let Some(i) = stack.pop() {
+            size += 1;
+            let (x, y) = (i % width, i / width);
+            let neighbours = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < width).then_some(i + 1),
+                (y > 0).then(|| i - width),
+                (y + 1 < height).then_some(i + width),
+            ];
+            for n in neighbours.into_iter().flatten() {
+                if mask[n] && !visited[n] {
+                    visited[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+        largest = largest.max(size);
+    }
+    largest
+}
+
+fn colour_entropy(pixels: &[[u8; 3]]) -> f32 {
+    let shift = 8 - HISTOGRAM_BITS;
+    let mut histogram = vec![0usize; 1 << (3 * HISTOGRAM_BITS)];
+    for &[r, g, b] in pixels {
+        let bin = ((r >> shift) as usize) << (2 * HISTOGRAM_BITS) | ((g >> shift) as usize) << HISTOGRAM_BITS | (b >> shift) as usize;
+        histogram[bin] += 1;
+    }
+    let total = pixels.len() as f32;
+    histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f32 / total;
+            -p * p.log2()
+        })
+        .sum::<f32>()
+        .max(0.0)
+}
+
+/// Fraction of pixels whose luma differs from the right or lower neighbour by `EDGE_THRESHOLD`
+fn edge_density(grid: &Grid) -> f32 {
+    let luma: Vec<i32> = grid
+        .pixels
+        .iter()
+        .map(|&[r, g, b]| (299 * r as i32 + 587 * g as i32 + 114 * b as i32) / 1000)
+        .collect();
+    let mut edges = 0;
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let i = y * grid.width + x;
+            let right = x + 1 < grid.width && (luma[i] - luma[i + 1]).abs() >= EDGE_THRESHOLD;
+            let down = y + 1 < grid.height && (luma[i] - luma[i + grid.width]).abs() >= EDGE_THRESHOLD;
+            edges += (right || down) as usize;
+        }
+    }
+    ratio(edges, luma.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SKIN: [u8; 4] = [224, 172, 150, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+    fn frame(width: usize, height: usize, pixel: impl Fn(usize, usize) -> [u8; 4]) -> Vec<u8> {
+        (0..width * height).flat_map(|i| pixel(i % width, i / width)).collect()
+    }
+
+    #[test]
+    fn test_skin_tone() {
+        assert!(is_skin(224, 172, 150));
+        assert!(is_skin(120, 80, 60));
+        assert!(!is_skin(255, 255, 255));
+        assert!(!is_skin(255, 0, 0));
+        assert!(!is_skin(0, 128, 255));
+    }
+
+    #[test]
+    fn test_skin_ratio_and_regions() {
+        // Two skin squares of different size on a blue background, sampled on a coarse grid
+        let image = frame(256, 256, |x, y| {
+            if (x < 64 && y < 64) || ((128..160).contains(&x) && (128..160).contains(&y)) {
+                SKIN
+            } else {
+                [30, 60, 200, 255]
+            }
+        });
+        let stats = analyze(&image, 256, 256).unwrap();
+        assert!((stats.skin_ratio - 5.0 / 64.0).abs() < 1e-3);
+        assert!((stats.largest_skin_region - 1.0 / 16.0).abs() < 1e-3);
+        assert_eq!(skin_ratio(&image, 256, 256).unwrap(), stats.skin_ratio);
+        assert!(!stats.mostly_text);
+    }
+
+    #[test]
+    fn test_entropy_and_text_detection() {
+        let flat = analyze(&frame(64, 64, |_, _| WHITE), 64, 64).unwrap();
+        assert_eq!(flat.colour_entropy, 0.0);
+        assert_eq!(flat.edge_density, 0.0);
+        assert!(!flat.mostly_text);
+
+        // Black glyph strokes on white: two colours, many edges
+        let text = analyze(&frame(200, 100, |x, y| if y % 10 < 6 && x % 6 < 2 { BLACK } else { WHITE }), 200, 100).unwrap();
+        assert!(text.colour_entropy <= 1.0);
+        assert!(text.edge_density > 0.2);
+        assert!(text.mostly_text);
+
+        // Smooth colour gradient: many colours, no sharp edges
+        let photo = analyze(&frame(128, 128, |x, y| [(x * 2) as u8, (y * 2) as u8, ((x + y) % 256) as u8, 255]), 128, 128).unwrap();
+        assert!(photo.colour_entropy > 5.0);
+        assert!(!photo.mostly_text);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(analyze(&[], 0, 0).is_err());
+        assert!(analyze(&[0; 12], 2, 2).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! `calibrate` subcommand: fit temperature / isotonic calibration to saved predictions.
+
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use pavlova_core::calibration::{Calibration, CalibrationMethod};
+use pavlova_core::config::{CLASSES, NUM_CLASSES};
+use pavlova_core::evaluation::{class_calibration_error, expected_calibration_error, Sample};
+
+use crate::dataset::read_predictions;
+
+#[derive(clap::Args)]
+pub struct CalibrateArgs {
+    /// predictions.csv written by `evaluate --output-dir` without `--calibration`
+    predictions: PathBuf,
+
+    /// Stages to fit: temperature, isotonic or both
+    #[arg(long, default_value = "both", value_parser = parse_method)]
+    method: CalibrationMethod,
+
+    /// Equal-width confidence bins for expected calibration error
+    #[arg(long, default_value_t = 15, value_parser = clap::value_parser!(u16).range(1..))]
+    bins: u16,
+
+    /// Fraction of predictions held out from fitting and used to report calibration error
+    #[arg(long, default_value_t = 0.0, value_parser = parse_fraction)]
+    holdout: f64,
+
+    /// Calibration file to write
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+pub fn run(args: CalibrateArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let samples: Vec<Sample> = read_predictions(&args.predictions)?.into_iter().map(|p| p.sample).collect();
+
+    // Spread the holdout evenly over the file, which is sorted by class folder
+    let (mut fitting, mut held_out) = (Vec::new(), Vec::new());
+    for (i, sample) in samples.into_iter().enumerate() {
+        if ((i + 1) as f64 * args.holdout).floor() > (i as f64 * args.holdout).floor() {
+            held_out.push(sample);
+        } else {
+            fitting.push(sample);
+        }
+    }
+    if fitting.is_empty() {
+        return Err(format!("No predictions left to fit in {}", args.predictions.display()).into());
+    }
+
+    let (calibration, unfitted) = Calibration::fit(&fitting, args.method);
+    let (report_set, report_name) = if held_out.is_empty() {
+        (&fitting, "fitting set")
+    } else {
+        (&held_out, "held-out set")
+    };
+    let calibrated = calibration.apply_samples(report_set);
+    let bins = args.bins as usize;
+
+    let mut report = vec![
+        format!(
+            "Fitted on {} of the predictions in {}; calibration error on the {} ({} samples, {} bins)",
+            fitting.len(),
+            args.predictions.display(),
+            report_name,
+            report_set.len(),
+            bins
+        ),
+        format!("temperature: {:.4}", calibration.temperature),
+        format!("{:<8} {:>8} {:>8}", "ECE", "before", "after"),
+        format!(
+            "{:<8} {:>8.4} {:>8.4}",
+            "top",
+            expected_calibration_error(report_set, bins),
+            expected_calibration_error(&calibrated, bins)
+        ),
+    ];
+    for (class, name) in CLASSES.iter().enumerate().take(NUM_CLASSES) {
+        report.push(format!(
+            "{:<8} {:>8.4} {:>8.4}{}",
+            name,
+            class_calibration_error(report_set, class, bins),
+            class_calibration_error(&calibrated, class, bins),
+            unfitted[class].as_ref().map(|reason| format!("  (no isotonic map: {})", reason)).unwrap_or_default()
+        ));
+    }
+    for line in &report {
+        println!("{}", line);
+    }
+
+    let mut file = String::new();
+    for line in &report {
+        file.push_str(&format!("# {}\n", line));
+    }
+    file.push_str(&calibration.to_text());
+    fs::write(&args.output, file)?;
+    eprintln!("Calibration written to {}", args.output.display());
+    Ok(ExitCode::SUCCESS)
+}
+
+fn parse_method(value: &str) -> Result<CalibrationMethod, String> {
+    CalibrationMethod::parse(value).ok_or_else(|| format!("expected temperature, isotonic or both, got '{}'", value))
+}
+
+fn parse_fraction(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(fraction) if (0.0..1.0).contains(&fraction) => Ok(fraction),
+        _ => Err(format!("expected a fraction in [0, 1), got '{}'", value)),
+    }
+}
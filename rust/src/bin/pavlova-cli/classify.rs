@@ -0,0 +1,199 @@
+//! `classify` subcommand: per-file scores, top class and safety verdict.
+
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+
+use pavlova_core::config::CLASSES;
+use pavlova_core::inference::{ClassificationResult, MLEngine};
+use pavlova_core::policy::{FilterPolicy, PolicyDecision};
+use serde::Serialize;
+
+use crate::input::{collect_images, load_rgba};
+use crate::output::csv_field;
+use crate::{ModelArgs, OutputFormat};
+
+#[derive(clap::Args)]
+pub struct ClassifyArgs {
+    #[command(flatten)]
+    model: ModelArgs,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    /// Policy profile deciding the `filtered` column (default thresholds if omitted)
+    #[arg(short, long)]
+    policy: Option<PathBuf>,
+
+    /// Image files and/or directories (searched recursively for .png/.jpg/.jpeg)
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+}
+
+/// One classified file
+#[derive(Serialize)]
+struct FileReport {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scores: Option<[f32; 5]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_class: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    safety_group: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_safe: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confidence: Option<f32>,
+    /// Whether the filtering policy would obscure this image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filtered: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    millis: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl FileReport {
+    fn success(path: &Path, result: &ClassificationResult, filtered: bool, millis: f64) -> Self {
+        FileReport {
+            path: path.display().to_string(),
+            scores: Some(result.scores),
+            top_class: Some(result.top_class.clone()),
+            safety_group: Some(result.safety_group().as_str()),
+            is_safe: Some(result.is_safe),
+            confidence: Some(result.confidence),
+            filtered: Some(filtered),
+            millis: Some(millis),
+            error: None,
+        }
+    }
+
+    fn failure(path: &Path, error: String) -> Self {
+        FileReport {
+            path: path.display().to_string(),
+            scores: None,
+            top_class: None,
+            safety_group: None,
+            is_safe: None,
+            confidence: None,
+            filtered: None,
+            millis: None,
+            error: Some(error),
+        }
+    }
+}
+
+pub fn run(args: ClassifyArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let engine = args.model.load()?;
+    let policy = match &args.policy {
+        Some(path) => FilterPolicy::load_profile(path)?,
+        None => FilterPolicy::default(),
+    };
+    let images = collect_images(&args.paths)?;
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    write_header(&mut out, args.format)?;
+
+    let mut failures = 0;
+    for path in &images {
+        let report = match classify_file(&engine, &policy, path) {
+            Ok((result, decision, millis)) => FileReport::success(path, &result, decision.should_filter, millis),
+            Err(e) => {
+                failures += 1;
+                FileReport::failure(path, e.to_string())
+            }
+        };
+        write_report(&mut out, args.format, &report)?;
+    }
+
+    if failures > 0 {
+        eprintln!("{} of {} file(s) failed", failures, images.len());
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn classify_file(
+    engine: &MLEngine,
+    policy: &FilterPolicy,
+    path: &Path,
+) -> Result<(ClassificationResult, PolicyDecision, f64), Box<dyn Error>> {
+    let image = load_rgba(path)?;
+    let start_time = Instant::now();
+    let result = engine.classify(&image.data, image.width, image.height)?;
+    let millis = start_time.elapsed().as_secs_f64() * 1000.0;
+    let skin_ratio = policy.skin_ratio_input(&image.data, image.width, image.height)?;
+    let decision = policy.evaluate_with_skin_ratio(&result, skin_ratio);
+    Ok((result, decision, millis))
+}
+
+fn write_header(out: &mut impl Write, format: OutputFormat) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => {
+            write!(out, "{:<8}", "verdict")?;
+            for class in CLASSES {
+                write!(out, " {:>7}", class)?;
+            }
+            writeln!(out, " {:<8} {:>6} {:>8}  path", "top", "conf", "ms")
+        }
+        OutputFormat::Csv => writeln!(
+            out,
+            "path,{},top_class,safety_group,is_safe,confidence,filtered,millis,error",
+            CLASSES.join(",")
+        ),
+        OutputFormat::Jsonl => Ok(()),
+    }
+}
+
+fn write_report(out: &mut impl Write, format: OutputFormat, report: &FileReport) -> io::Result<()> {
+    match format {
+        OutputFormat::Jsonl => writeln!(out, "{}", serde_json::to_string(report)?),
+        OutputFormat::Table => match &report.error {
+            Some(error) => writeln!(out, "{:<8} {}  {}", "ERROR", error, report.path),
+            None => {
+                let verdict = if report.filtered == Some(true) {
+                    "FILTER"
+                } else if report.is_safe == Some(true) {
+                    "safe"
+                } else {
+                    "unsafe"
+                };
+                write!(out, "{:<8}", verdict)?;
+                for score in report.scores.unwrap_or_default() {
+                    write!(out, " {:>7.3}", score)?;
+                }
+                writeln!(
+                    out,
+                    " {:<8} {:>6.3} {:>8.1}  {}",
+                    report.top_class.as_deref().unwrap_or(""),
+                    report.confidence.unwrap_or_default(),
+                    report.millis.unwrap_or_default(),
+                    report.path
+                )
+            }
+        },
+        OutputFormat::Csv => {
+            let scores = report
+                .scores
+                .map(|s| s.iter().map(|v| format!("{:.6}", v)).collect::<Vec<_>>().join(","))
+                .unwrap_or_else(|| ",".repeat(CLASSES.len() - 1));
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{}",
+                csv_field(&report.path),
+                scores,
+                report.top_class.as_deref().unwrap_or(""),
+                report.safety_group.unwrap_or(""),
+                report.is_safe.map(|v| v.to_string()).unwrap_or_default(),
+                report.confidence.map(|v| format!("{:.6}", v)).unwrap_or_default(),
+                report.filtered.map(|v| v.to_string()).unwrap_or_default(),
+                report.millis.map(|v| format!("{:.3}", v)).unwrap_or_default(),
+                csv_field(report.error.as_deref().unwrap_or("")),
+            )
+        }
+    }
+}
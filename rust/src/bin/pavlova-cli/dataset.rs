@@ -0,0 +1,180 @@
+//! Labeled datasets (one folder per class) and the predictions made on them.
+
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use pavlova_core::config::{CLASSES, NUM_CLASSES};
+use pavlova_core::evaluation::Sample;
+
+use crate::input::collect_images;
+use crate::output::csv_field;
+
+/// Image file with its ground-truth class index
+pub struct LabeledImage {
+    pub path: PathBuf,
+    pub label: usize,
+}
+
+/// A classified labeled image
+pub struct Prediction {
+    pub path: String,
+    pub sample: Sample,
+}
+
+/// Collect images from `<root>/<class name>/...`, class names as in `CLASSES`
+/// Folders that do not name a class are skipped with a warning.
+pub fn load_labeled_dir(root: &Path) -> Result<Vec<LabeledImage>, Box<dyn Error>> {
+    let mut class_dirs: Vec<PathBuf> = fs::read_dir(root)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter(|p| p.is_dir())
+        .collect();
+    class_dirs.sort();
+
+    let mut images = Vec::new();
+    for dir in class_dirs {
+        let name = dir.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_ascii_lowercase();
+        let Some(label) = CLASSES.iter().position(|&c| c == name) else {
+            eprintln!("warning: skipping {} (not one of {})", dir.display(), CLASSES.join(", "));
+            continue;
+        };
+        for path in collect_images(std::slice::from_ref(&dir))? {
+            images.push(LabeledImage { path, label });
+        }
+    }
+
+    if images.is_empty() {
+        return Err(format!("No labeled images under {} (expected folders named {})", root.display(), CLASSES.join(", ")).into());
+    }
+    Ok(images)
+}
+
+/// Column of the skin-tone ratio, after the class scores (empty when not recorded)
+const SKIN_RATIO_COLUMN: &str = "skin_ratio";
+
+/// Write predictions as CSV: `path,label,<one column per class>,skin_ratio`
+pub fn write_predictions(path: &Path, predictions: &[Prediction]) -> Result<(), Box<dyn Error>> {
+    let mut out = fs::File::create(path)?;
+    writeln!(out, "path,label,{},{}", CLASSES.join(","), SKIN_RATIO_COLUMN)?;
+    for p in predictions {
+        let scores: Vec<String> = p.sample.scores.iter().map(|s| format!("{:.6}", s)).collect();
+        let skin_ratio = p.sample.skin_ratio.map(|r| format!("{:.6}", r)).unwrap_or_default();
+        writeln!(out, "{},{},{},{}", csv_field(&p.path), CLASSES[p.sample.label], scores.join(","), skin_ratio)?;
+    }
+    Ok(())
+}
+
+/// Read predictions written by `write_predictions`, with or without the skin-ratio column
+pub fn read_predictions(path: &Path) -> Result<Vec<Prediction>, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let mut predictions = Vec::new();
+    let has_skin_ratio = text
+        .lines()
+        .next()
+        .is_some_and(|header| header.trim_end().ends_with(&format!(",{}", SKIN_RATIO_COLUMN)));
+    let columns = NUM_CLASSES + 2 + has_skin_ratio as usize;
+
+    for (line_number, line) in text.lines().enumerate().skip(1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        // Label, scores and skin ratio are the last fields; the path may contain quoted commas
+        let mut fields: Vec<&str> = line.rsplitn(columns, ',').collect();
+        if fields.len() != columns {
+            return Err(format!("{}:{}: expected {} columns", path.display(), line_number + 1, columns).into());
+        }
+        let skin_ratio = match has_skin_ratio.then(|| fields.remove(0).trim()) {
+            None | Some("") => None,
+            Some(field) => Some(
+                field
+                    .parse()
+                    .map_err(|_| format!("{}:{}: invalid skin ratio '{}'", path.display(), line_number + 1, field))?,
+            ),
+        };
+        let mut scores = [0.0f32; NUM_CLASSES];
+        for (i, field) in fields[..NUM_CLASSES].iter().rev().enumerate() {
+            scores[i] = field
+                .trim()
+                .parse()
+                .map_err(|_| format!("{}:{}: invalid score '{}'", path.display(), line_number + 1, field))?;
+        }
+        let label_name = fields[NUM_CLASSES].trim();
+        let label = CLASSES
+            .iter()
+            .position(|&c| c == label_name)
+            .ok_or_else(|| format!("{}:{}: unknown label '{}'", path.display(), line_number + 1, label_name))?;
+        let raw_path = fields[NUM_CLASSES + 1];
+        let file = raw_path
+            .strip_prefix('"')
+            .and_then(|p| p.strip_suffix('"'))
+            .map(|p| p.replace("\"\"", "\""))
+            .unwrap_or_else(|| raw_path.to_string());
+
+        predictions.push(Prediction {
+            path: file,
+            sample: Sample {
+                label,
+                scores,
+                skin_ratio,
+            },
+        });
+    }
+    Ok(predictions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_predictions_round_trip() {
+        let dir = std::env::temp_dir().join(format!("pavlova-predictions-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("predictions.csv");
+
+        let predictions = vec![
+            Prediction {
+                path: "porn/a,b.png".to_string(),
+                sample: Sample {
+                    label: 3,
+                    scores: [0.0, 0.1, 0.2, 0.6, 0.1],
+                    skin_ratio: Some(0.25),
+                },
+            },
+            Prediction {
+                path: "neutral/c.jpg".to_string(),
+                sample: Sample {
+                    label: 2,
+                    scores: [0.1, 0.0, 0.9, 0.0, 0.0],
+                    skin_ratio: None,
+                },
+            },
+        ];
+        write_predictions(&file, &predictions).unwrap();
+        let read = read_predictions(&file).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].path, "porn/a,b.png");
+        assert_eq!(read[0].sample.label, 3);
+        assert_eq!(read[0].sample.scores, predictions[0].sample.scores);
+        assert_eq!(read[0].sample.skin_ratio, Some(0.25));
+        assert_eq!(read[1].sample.label, 2);
+        assert_eq!(read[1].sample.skin_ratio, None);
+    }
+
+    #[test]
+    fn test_predictions_without_skin_ratio_column() {
+        let file = std::env::temp_dir().join(format!("pavlova-predictions-old-{}.csv", std::process::id()));
+        fs::write(&file, "path,label,drawing,hentai,neutral,porn,sexy\na.png,sexy,0,0,0.1,0,0.9\n").unwrap();
+        let read = read_predictions(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(read[0].sample.label, 4);
+        assert_eq!(read[0].sample.scores[4], 0.9);
+        assert_eq!(read[0].sample.skin_ratio, None);
+    }
+}
@@ -0,0 +1,139 @@
+//! `evaluate` subcommand: accuracy metrics over a labeled dataset directory.
+
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use pavlova_core::analysis::skin_ratio;
+use pavlova_core::config::CLASSES;
+use pavlova_core::evaluation::{EvaluationReport, Sample};
+
+use crate::dataset::{load_labeled_dir, write_predictions, Prediction};
+use crate::input::load_rgba;
+use crate::output::write_json;
+use crate::ModelArgs;
+
+#[derive(clap::Args)]
+pub struct EvaluateArgs {
+    #[command(flatten)]
+    model: ModelArgs,
+
+    /// Dataset root with one folder per class (drawing, hentai, neutral, porn, sexy)
+    dataset: PathBuf,
+
+    /// Directory for report.json, predictions.csv, per_class.csv, confusion.csv, roc.csv and pr.csv
+    #[arg(short, long)]
+    output_dir: Option<PathBuf>,
+}
+
+pub fn run(args: EvaluateArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let engine = args.model.load()?;
+    let images = load_labeled_dir(&args.dataset)?;
+
+    let mut predictions = Vec::with_capacity(images.len());
+    let mut failures = 0;
+    for (i, image) in images.iter().enumerate() {
+        // The skin ratio is recorded so `tune` and `policy_rates` can replay `min_skin_ratio`
+        let classified = load_rgba(&image.path).and_then(|rgba| {
+            let result = engine.classify(&rgba.data, rgba.width, rgba.height)?;
+            Ok((result, skin_ratio(&rgba.data, rgba.width, rgba.height)?))
+        });
+        match classified {
+            Ok((result, skin_ratio)) => predictions.push(Prediction {
+                path: image.path.display().to_string(),
+                sample: Sample {
+                    label: image.label,
+                    scores: result.scores,
+                    skin_ratio: Some(skin_ratio),
+                },
+            }),
+            Err(e) => {
+                failures += 1;
+                eprintln!("warning: skipping {}: {}", image.path.display(), e);
+            }
+        }
+        if (i + 1) % 100 == 0 {
+            eprintln!("{}/{} images classified", i + 1, images.len());
+        }
+    }
+
+    let samples: Vec<Sample> = predictions.iter().map(|p| p.sample.clone()).collect();
+    let report = EvaluationReport::from_samples(&samples);
+    print_summary(&report);
+
+    if let Some(dir) = &args.output_dir {
+        fs::create_dir_all(dir)?;
+        write_json(&dir.join("report.json"), &report)?;
+        write_predictions(&dir.join("predictions.csv"), &predictions)?;
+        write_report_csvs(dir, &report)?;
+        eprintln!("Reports written to {}", dir.display());
+    }
+
+    if failures > 0 {
+        eprintln!("{} of {} image(s) could not be classified", failures, images.len());
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_summary(report: &EvaluationReport) {
+    println!("samples: {}  accuracy: {:.4}  macro F1: {:.4}", report.samples, report.accuracy, report.macro_f1);
+    println!(
+        "unsafe detection: ROC AUC {}  PR AUC {}",
+        format_optional(report.roc_auc),
+        format_optional(report.pr_auc)
+    );
+    println!("expected calibration error: {:.4}", report.expected_calibration_error);
+    println!();
+    println!("{:<8} {:>9} {:>9} {:>9} {:>8}", "class", "precision", "recall", "f1", "support");
+    for m in &report.per_class {
+        println!("{:<8} {:>9.4} {:>9.4} {:>9.4} {:>8}", m.class, m.precision, m.recall, m.f1, m.support);
+    }
+    println!();
+    print!("{:<16}", "actual\\predicted");
+    for class in CLASSES {
+        print!(" {:>8}", class);
+    }
+    println!();
+    for (actual, row) in report.confusion.counts.iter().enumerate() {
+        print!("{:<16}", CLASSES[actual]);
+        for count in row {
+            print!(" {:>8}", count);
+        }
+        println!();
+    }
+}
+
+fn format_optional(value: Option<f64>) -> String {
+    value.map(|v| format!("{:.4}", v)).unwrap_or_else(|| "n/a".to_string())
+}
+
+fn write_report_csvs(dir: &Path, report: &EvaluationReport) -> Result<(), Box<dyn Error>> {
+    let mut per_class = fs::File::create(dir.join("per_class.csv"))?;
+    writeln!(per_class, "class,precision,recall,f1,support")?;
+    for m in &report.per_class {
+        writeln!(per_class, "{},{:.6},{:.6},{:.6},{}", m.class, m.precision, m.recall, m.f1, m.support)?;
+    }
+
+    let mut confusion = fs::File::create(dir.join("confusion.csv"))?;
+    writeln!(confusion, "actual,{}", CLASSES.join(","))?;
+    for (actual, row) in report.confusion.counts.iter().enumerate() {
+        let counts: Vec<String> = row.iter().map(|c| c.to_string()).collect();
+        writeln!(confusion, "{},{}", CLASSES[actual], counts.join(","))?;
+    }
+
+    let mut roc = fs::File::create(dir.join("roc.csv"))?;
+    writeln!(roc, "threshold,false_positive_rate,true_positive_rate")?;
+    for p in &report.roc {
+        writeln!(roc, "{},{:.6},{:.6}", p.threshold, p.false_positive_rate, p.true_positive_rate)?;
+    }
+
+    let mut pr = fs::File::create(dir.join("pr.csv"))?;
+    writeln!(pr, "threshold,precision,recall")?;
+    for p in &report.pr {
+        writeln!(pr, "{},{:.6},{:.6}", p.threshold, p.precision, p.recall)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,285 @@
+//! Frame sequence readers for replay: PNG/JPEG directories, Y4M video and raw RGBA dumps.
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::vec;
+
+use pavlova_core::utils::yuv420_to_rgba;
+
+use crate::input::{collect_images, load_rgba, RgbaImage};
+
+/// Frame rate assumed for image directories when none is given
+pub const DEFAULT_FPS: f64 = 30.0;
+
+/// One decoded frame with its presentation time
+pub struct Frame {
+    pub timestamp: Duration,
+    pub image: RgbaImage,
+}
+
+/// Entry of a raw dump's timing index
+#[derive(Debug, PartialEq)]
+pub struct RawIndexEntry {
+    pub timestamp: Duration,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Streaming reader over any supported frame source
+pub enum FrameReader {
+    /// Image files, timed at a fixed frame rate
+    Images {
+        files: vec::IntoIter<PathBuf>,
+        interval: Duration,
+        index: u32,
+    },
+    /// YUV4MPEG2 video with 4:2:0 chroma
+    Y4m {
+        reader: BufReader<File>,
+        width: usize,
+        height: usize,
+        interval: Duration,
+        index: u32,
+    },
+    /// Back-to-back RGBA frames described by a timing index
+    Raw {
+        reader: BufReader<File>,
+        entries: vec::IntoIter<RawIndexEntry>,
+    },
+}
+
+impl FrameReader {
+    /// Open a frame source by kind: a directory of images, a `.y4m` file, or a raw RGBA
+    /// dump with a timing index (`index`, or the dump path with an `.idx` extension)
+    /// `fps` overrides the frame rate of directories and Y4M files.
+    pub fn open(path: &Path, fps: Option<f64>, index: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        if path.is_dir() {
+            let files = collect_images(&[path.to_path_buf()])?;
+            if files.is_empty() {
+                return Err(format!("No images in {}", path.display()).into());
+            }
+            return Ok(FrameReader::Images {
+                files: files.into_iter(),
+                interval: frame_interval(fps.unwrap_or(DEFAULT_FPS))?,
+                index: 0,
+            });
+        }
+
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+        if extension == "y4m" {
+            let mut reader = BufReader::new(File::open(path)?);
+            let mut header = String::new();
+            reader.read_line(&mut header)?;
+            let (width, height, header_interval) = parse_y4m_header(header.trim_end())?;
+            return Ok(FrameReader::Y4m {
+                reader,
+                width,
+                height,
+                interval: fps.map(frame_interval).transpose()?.unwrap_or(header_interval),
+                index: 0,
+            });
+        }
+
+        let index_path = index.map(Path::to_path_buf).unwrap_or_else(|| path.with_extension("idx"));
+        let index_text = fs::read_to_string(&index_path)
+            .map_err(|e| format!("Cannot read timing index {}: {}", index_path.display(), e))?;
+        Ok(FrameReader::Raw {
+            reader: BufReader::new(File::open(path)?),
+            entries: parse_raw_index(&index_text)?.into_iter(),
+        })
+    }
+
+    fn next_frame(&mut self) -> Result<Option<Frame>, Box<dyn Error>> {
+        match self {
+            FrameReader::Images { files, interval, index } => {
+                let Some(path) = files.next() else {
+                    return Ok(None);
+                };
+                let timestamp = interval.checked_mul(*index).ok_or("Frame timestamp overflows")?;
+                *index += 1;
+                let image = load_rgba(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+                Ok(Some(Frame { timestamp, image }))
+            }
+            FrameReader::Y4m {
+                reader,
+                width,
+                height,
+                interval,
+                index,
+            } => {
+                let mut marker = String::new();
+                if reader.read_line(&mut marker)? == 0 {
+                    return Ok(None);
+                }
+                if !marker.starts_with("FRAME") {
+                    return Err(format!("Y4M frame {}: expected FRAME marker", index).into());
+                }
+                let luma = *width * *height;
+                let chroma = width.div_ceil(2) * height.div_ceil(2);
+                let mut planes = vec![0u8; luma + 2 * chroma];
+                reader
+                    .read_exact(&mut planes)
+                    .map_err(|e| format!("Y4M frame {}: truncated ({})", index, e))?;
+
+                let (y, uv) = planes.split_at(luma);
+                let (u, v) = uv.split_at(chroma);
+                let timestamp = interval.checked_mul(*index).ok_or("Frame timestamp overflows")?;
+                *index += 1;
+                Ok(Some(Frame {
+                    timestamp,
+                    image: RgbaImage {
+                        data: yuv420_to_rgba(y, u, v, *width, *height)?,
+                        width: *width,
+                        height: *height,
+                    },
+                }))
+            }
+            FrameReader::Raw { reader, entries } => {
+                let Some(entry) = entries.next() else {
+                    return Ok(None);
+                };
+                let mut data = vec![0u8; entry.width * entry.height * 4];
+                reader
+                    .read_exact(&mut data)
+                    .map_err(|e| format!("Raw frame at {:?}: dump shorter than the index ({})", entry.timestamp, e))?;
+                Ok(Some(Frame {
+                    timestamp: entry.timestamp,
+                    image: RgbaImage {
+                        data,
+                        width: entry.width,
+                        height: entry.height,
+                    },
+                }))
+            }
+        }
+    }
+}
+
+impl Iterator for FrameReader {
+    type Item = Result<Frame, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().transpose()
+    }
+}
+
+/// Time between frames at `rate` frames per second
+pub fn frame_interval(rate: f64) -> Result<Duration, Box<dyn Error>> {
+    // A tiny rate gives an interval too long for a Duration
+    Some(rate)
+        .filter(|rate| rate.is_finite() && *rate > 0.0)
+        .and_then(|rate| Duration::try_from_secs_f64(1.0 / rate).ok())
+        .ok_or_else(|| format!("Invalid frame rate {}", rate).into())
+}
+
+/// Parse a `YUV4MPEG2 ...` stream header into width, height and frame interval
+/// Only 4:2:0 chroma is supported.
+pub fn parse_y4m_header(line: &str) -> Result<(usize, usize, Duration), Box<dyn Error>> {
+    let mut tokens = line.split(' ');
+    if tokens.next() != Some("YUV4MPEG2") {
+        return Err("Not a Y4M file (missing YUV4MPEG2 signature)".into());
+    }
+
+    let (mut width, mut height) = (0usize, 0usize);
+    let mut interval = Duration::from_secs_f64(1.0 / DEFAULT_FPS);
+    for token in tokens.filter(|t| !t.is_empty()) {
+        let (tag, value) = token.split_at(token.chars().next().map_or(0, char::len_utf8));
+        match tag {
+            "W" => width = value.parse().map_err(|_| format!("Invalid Y4M width '{}'", value))?,
+            "H" => height = value.parse().map_err(|_| format!("Invalid Y4M height '{}'", value))?,
+            "F" => {
+                let (numerator, denominator) = value
+                    .split_once(':')
+                    .and_then(|(n, d)| Some((n.parse::<u64>().ok()?, d.parse::<u64>().ok()?)))
+                    .filter(|&(n, d)| n > 0 && d > 0)
+                    .ok_or_else(|| format!("Invalid Y4M frame rate '{}'", value))?;
+                interval = Duration::try_from_secs_f64(denominator as f64 / numerator as f64)
+                    .map_err(|_| format!("Invalid Y4M frame rate '{}'", value))?;
+            }
+            "C" if !value.starts_with("420") => {
+                return Err(format!("Unsupported Y4M colorspace '{}' (only 4:2:0)", value).into())
+            }
+            _ => {}
+        }
+    }
+
+    if width == 0 || height == 0 || width.checked_mul(height).and_then(|n| n.checked_mul(4)).is_none() {
+        return Err(format!("Unsupported Y4M size {}x{}", width, height).into());
+    }
+    Ok((width, height, interval))
+}
+
+/// Parse a raw dump timing index: one `<timestamp_ms> <width> <height>` line per frame, `#` comments
+pub fn parse_raw_index(text: &str) -> Result<Vec<RawIndexEntry>, Box<dyn Error>> {
+    let mut entries = Vec::new();
+    for (line_number, raw_line) in text.lines().enumerate() {
+        let line = raw_line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let parsed = match fields.as_slice() {
+            [millis, width, height] => millis
+                .parse::<f64>()
+                .ok()
+                .and_then(|millis| Duration::try_from_secs_f64(millis / 1000.0).ok())
+                .zip(width.parse::<usize>().ok())
+                .zip(height.parse::<usize>().ok())
+                .map(|((timestamp, width), height)| RawIndexEntry { timestamp, width, height }),
+            _ => None,
+        };
+        let entry = parsed.ok_or_else(|| {
+            format!("Timing index line {}: expected '<timestamp_ms> <width> <height>', got '{}'", line_number + 1, line)
+        })?;
+        if entry.width == 0 || entry.height == 0 || entry.width.checked_mul(entry.height).and_then(|n| n.checked_mul(4)).is_none() {
+            return Err(format!("Timing index line {}: invalid frame size", line_number + 1).into());
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_y4m_header() {
+        let (width, height, interval) = parse_y4m_header("YUV4MPEG2 W640 H360 F25:1 Ip A1:1 C420jpeg").unwrap();
+        assert_eq!((width, height), (640, 360));
+        assert_eq!(interval, Duration::from_millis(40));
+
+        assert!(parse_y4m_header("YUV4MPEG2 W640 H360 C444").is_err());
+        assert_eq!(parse_y4m_header("YUV4MPEG2 W641 H361").unwrap().0, 641);
+        assert!(parse_y4m_header("YUV4MPEG2 W0 H360").is_err());
+        assert!(parse_y4m_header("YUV4MPEG2 \u{e9}1 W2 H2").is_ok());
+        assert!(parse_y4m_header("RIFF").is_err());
+        // An interval too long for a Duration is an error, not a panic
+        assert!(parse_y4m_header("YUV4MPEG2 W2 H2 F1:18446744073709551615").is_err());
+    }
+
+    #[test]
+    fn test_frame_interval() {
+        assert_eq!(frame_interval(25.0).unwrap(), Duration::from_millis(40));
+        assert!(frame_interval(0.0).is_err());
+        assert!(frame_interval(f64::INFINITY).is_err());
+        assert!(frame_interval(1e-300).is_err());
+    }
+
+    #[test]
+    fn test_parse_raw_index() {
+        let entries = parse_raw_index("# ms w h\n0 4 2\n\n33.5 4 2 # dropped frame follows\n100 8 8\n").unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].timestamp, Duration::from_micros(33_500));
+        assert_eq!((entries[2].width, entries[2].height), (8, 8));
+
+        assert!(parse_raw_index("0 4").is_err());
+        assert!(parse_raw_index("-5 4 2").is_err());
+        assert!(parse_raw_index("1e300 4 2").is_err());
+        assert!(parse_raw_index("NaN 4 2").is_err());
+        assert!(parse_raw_index("0 0 2").is_err());
+    }
+}
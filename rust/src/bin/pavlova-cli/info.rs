@@ -0,0 +1,26 @@
+//! `info` subcommand: describe a model's inputs, outputs, labels, hash and graph.
+
+use std::error::Error;
+use std::process::ExitCode;
+
+use crate::ModelArgs;
+
+#[derive(clap::Args)]
+pub struct InfoArgs {
+    #[command(flatten)]
+    model: ModelArgs,
+
+    /// Print the description as a JSON object
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: InfoArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let engine = args.model.load()?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(engine.info())?);
+    } else {
+        println!("{}", engine.info());
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,77 @@
+//! Image discovery and decoding for the CLI.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Decoded RGBA image, in the layout `pavlova_core::image` works on
+pub struct RgbaImage {
+    pub data: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+}
+
+const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// Whether a path has a supported image extension
+pub fn is_image_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Expand files and directories into a sorted list of image files
+/// Directories are walked recursively; explicitly named files are kept whatever their extension
+pub fn collect_images(paths: &[PathBuf]) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut images = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            walk_dir(path, &mut images)?;
+        } else if path.exists() {
+            images.push(path.clone());
+        } else {
+            return Err(format!("No such file or directory: {}", path.display()).into());
+        }
+    }
+    Ok(images)
+}
+
+fn walk_dir(dir: &Path, images: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            walk_dir(&path, images)?;
+        } else if is_image_file(&path) {
+            images.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Decode a PNG/JPEG file to RGBA
+pub fn load_rgba(path: &Path) -> Result<RgbaImage, Box<dyn Error>> {
+    let decoded = image::open(path)?.to_rgba8();
+    let (width, height) = decoded.dimensions();
+    Ok(RgbaImage {
+        data: decoded.into_raw(),
+        width: width as usize,
+        height: height as usize,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_image_file() {
+        assert!(is_image_file(Path::new("a/b.png")));
+        assert!(is_image_file(Path::new("shot.JPEG")));
+        assert!(!is_image_file(Path::new("notes.txt")));
+        assert!(!is_image_file(Path::new("png")));
+    }
+}
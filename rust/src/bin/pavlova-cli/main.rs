@@ -0,0 +1,104 @@
+//! pavlova-cli: run the Pavlova classifier and image effects on a desktop.
+//!
+//! Build with `cargo build --release --features cli --bin pavlova-cli`.
+
+mod calibrate;
+mod classify;
+mod dataset;
+mod evaluate;
+mod frames;
+mod info;
+mod input;
+mod output;
+mod render;
+mod replay;
+mod tune;
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use pavlova_core::calibration::Calibration;
+use pavlova_core::inference::MLEngine;
+
+#[derive(Parser)]
+#[command(name = "pavlova-cli", version, about = "Pavlova desktop tools")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Classify PNG/JPEG files or directories of images
+    Classify(classify::ClassifyArgs),
+    /// Measure precision/recall, confusion matrix and ROC/PR curves on a labeled dataset
+    Evaluate(evaluate::EvaluateArgs),
+    /// Choose policy thresholds from evaluation predictions and write a policy profile
+    Tune(tune::TuneArgs),
+    /// Fit a score calibration to evaluation predictions and report calibration error
+    Calibrate(calibrate::CalibrateArgs),
+    /// Write blurred / pixelated copies of images, optionally side by side with the original
+    Render(render::RenderArgs),
+    /// Replay a frame sequence through classification, temporal smoothing and policy
+    Replay(replay::ReplayArgs),
+    /// Show a model's input/output shapes, labels, hash and graph statistics
+    Info(info::InfoArgs),
+}
+
+/// Output format for per-file results
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned, human-readable columns
+    Table,
+    /// One JSON object per line
+    Jsonl,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+/// Model path argument shared by subcommands that run inference
+#[derive(clap::Args)]
+pub struct ModelArgs {
+    /// ONNX model file
+    #[arg(short, long, env = "PAVLOVA_MODEL")]
+    pub model: PathBuf,
+
+    /// Calibration file written by `calibrate`, applied to every classification
+    #[arg(long, env = "PAVLOVA_CALIBRATION")]
+    pub calibration: Option<PathBuf>,
+}
+
+impl ModelArgs {
+    /// Load the model, with its calibration if one was given
+    pub fn load(&self) -> Result<MLEngine, Box<dyn Error>> {
+        let engine = MLEngine::new(&self.model.to_string_lossy())?;
+        Ok(match &self.calibration {
+            Some(path) => engine.with_calibration(Calibration::load(path)?),
+            None => engine,
+        })
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Classify(args) => classify::run(args),
+        Command::Evaluate(args) => evaluate::run(args),
+        Command::Tune(args) => tune::run(args),
+        Command::Calibrate(args) => calibrate::run(args),
+        Command::Render(args) => render::run(args),
+        Command::Replay(args) => replay::run(args),
+        Command::Info(args) => info::run(args),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Shared output helpers for report and image files.
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::input::RgbaImage;
+
+/// Quote a CSV field if it contains separators, quotes or newlines
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Write a value as pretty-printed JSON
+pub fn write_json(path: &Path, value: &impl Serialize) -> Result<(), Box<dyn Error>> {
+    fs::write(path, serde_json::to_string_pretty(value)? + "\n")?;
+    Ok(())
+}
+
+/// Encode an RGBA image as PNG
+pub fn write_png(path: &Path, image: &RgbaImage) -> Result<(), Box<dyn Error>> {
+    image::save_buffer(
+        path,
+        &image.data,
+        image.width as u32,
+        image.height as u32,
+        image::ExtendedColorType::Rgba8,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("plain.png"), "plain.png");
+        assert_eq!(csv_field("a,b.png"), "\"a,b.png\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
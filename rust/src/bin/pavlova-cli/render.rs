@@ -0,0 +1,235 @@
+//! `render` subcommand: write blurred / pixelated copies of images for visual review.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use pavlova_core::inference::MLEngine;
+use pavlova_core::pipeline::{apply_action, process_frame};
+use pavlova_core::policy::{FilterAction, FilterPolicy};
+
+use crate::input::{collect_images, load_rgba, RgbaImage};
+use crate::output::write_png;
+use crate::ModelArgs;
+
+/// Gap between the halves of a side-by-side image, in pixels
+const SIDE_BY_SIDE_GAP: usize = 8;
+
+#[derive(clap::Args)]
+#[command(group(clap::ArgGroup::new("effect").args(["blur", "pixelate"])))]
+pub struct RenderArgs {
+    /// Blur with this radius
+    #[arg(long)]
+    blur: Option<f32>,
+
+    /// Pixelate with this block size
+    #[arg(long)]
+    pixelate: Option<usize>,
+
+    /// ONNX model whose policy picks the effect per image; not read from PAVLOVA_MODEL here,
+    /// which would conflict with --blur/--pixelate
+    #[arg(short, long, conflicts_with = "effect")]
+    model: Option<PathBuf>,
+
+    /// Calibration file written by `calibrate`, applied with --model; not read from
+    /// PAVLOVA_CALIBRATION here either
+    #[arg(long, requires = "model")]
+    calibration: Option<PathBuf>,
+
+    /// Policy profile used with --model (default thresholds if omitted)
+    #[arg(short, long)]
+    policy: Option<PathBuf>,
+
+    /// Write the original and the result next to each other
+    #[arg(long)]
+    side_by_side: bool,
+
+    /// Directory for the rendered PNG files, mirroring the input directories
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    /// Image files and/or directories (searched recursively for .png/.jpg/.jpeg)
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+}
+
+/// How the effect of each image is chosen
+enum EffectSource {
+    Fixed(FilterAction),
+    Policy(Box<MLEngine>, FilterPolicy),
+}
+
+pub fn run(args: RenderArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let source = match (args.blur, args.pixelate, &args.model) {
+        (Some(radius), _, _) => EffectSource::Fixed(FilterAction::Blur { radius }),
+        (_, Some(block_size), _) => EffectSource::Fixed(FilterAction::Pixelate { block_size }),
+        (None, None, Some(model)) => {
+            let policy = match &args.policy {
+                Some(path) => FilterPolicy::load_profile(path)?,
+                None => FilterPolicy::default(),
+            };
+            let model = ModelArgs {
+                model: model.clone(),
+                calibration: args.calibration.clone(),
+            };
+            EffectSource::Policy(Box::new(model.load()?), policy)
+        }
+        (None, None, None) => return Err("Pass --blur, --pixelate, or --model to let the policy decide".into()),
+    };
+
+    let mut images = Vec::new();
+    for root in &args.paths {
+        for path in collect_images(std::slice::from_ref(root))? {
+            let relative = output_name(root, &path);
+            images.push((path, relative));
+        }
+    }
+    fs::create_dir_all(&args.output_dir)?;
+
+    let mut written = HashSet::new();
+    let mut failures = 0;
+    for (path, relative) in &images {
+        match render_file(&source, path, relative, &args, &mut written) {
+            Ok((action, output)) => println!("{:<20} {} -> {}", describe(action), path.display(), output.display()),
+            Err(e) => {
+                failures += 1;
+                eprintln!("error: {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    if failures > 0 {
+        eprintln!("{} of {} file(s) failed", failures, images.len());
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn render_file(
+    source: &EffectSource,
+    path: &Path,
+    relative: &Path,
+    args: &RenderArgs,
+    written: &mut HashSet<PathBuf>,
+) -> Result<(FilterAction, PathBuf), Box<dyn Error>> {
+    let image = load_rgba(path)?;
+
+    let (action, pixels) = match source {
+        EffectSource::Fixed(action) => (*action, apply_action(&image.data, image.width, image.height, *action)?),
+        EffectSource::Policy(engine, policy) => {
+            let outcome = process_frame(engine, policy, &image.data, image.width, image.height)?;
+            (outcome.decision.action, outcome.output)
+        }
+    };
+    // Allowed frames are written unchanged so every input has a rendered counterpart
+    let after = RgbaImage {
+        data: pixels.unwrap_or_else(|| image.data.clone()),
+        width: image.width,
+        height: image.height,
+    };
+
+    let rendered = if args.side_by_side { side_by_side(&image, &after) } else { after };
+
+    let mut name = relative.as_os_str().to_owned();
+    name.push(format!(".{}.png", effect_suffix(action)));
+    let output = args.output_dir.join(name);
+    if !written.insert(output.clone()) {
+        return Err(format!("{} was already rendered from another input", output.display()).into());
+    }
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_png(&output, &rendered)?;
+    Ok((action, output))
+}
+
+/// Output path of an image relative to the output directory, without extension: its path
+/// under the input directory it was found in, below that directory's name
+fn output_name(root: &Path, path: &Path) -> PathBuf {
+    let base = match path.strip_prefix(root) {
+        Ok(below) if root.is_dir() => root.file_name().map(Path::new).unwrap_or(Path::new("")).join(below),
+        _ => PathBuf::from(path.file_name().unwrap_or_default()),
+    };
+    base.with_extension("")
+}
+
+/// Place two equally sized images left and right, separated by a black gap
+fn side_by_side(before: &RgbaImage, after: &RgbaImage) -> RgbaImage {
+    let width = before.width * 2 + SIDE_BY_SIDE_GAP;
+    let mut data = vec![0u8; width * before.height * 4];
+    for (y, row) in data.chunks_exact_mut(width * 4).enumerate() {
+        let line = before.width * 4;
+        row[..line].copy_from_slice(&before.data[y * line..(y + 1) * line]);
+        row[width * 4 - line..].copy_from_slice(&after.data[y * line..(y + 1) * line]);
+        // Opaque gap, so it shows as black rather than transparent
+        for pixel in row[line..width * 4 - line].chunks_exact_mut(4) {
+            pixel[3] = 255;
+        }
+    }
+    RgbaImage {
+        data,
+        width,
+        height: before.height,
+    }
+}
+
+fn effect_suffix(action: FilterAction) -> String {
+    match action {
+        FilterAction::Allow => "allow".to_string(),
+        FilterAction::Blur { radius } => format!("blur{}", radius),
+        FilterAction::Pixelate { block_size } => format!("pixelate{}", block_size),
+    }
+}
+
+fn describe(action: FilterAction) -> String {
+    match action {
+        FilterAction::Allow => "allow".to_string(),
+        FilterAction::Blur { radius } => format!("blur radius={}", radius),
+        FilterAction::Pixelate { block_size } => format!("pixelate block={}", block_size),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_names_keep_input_directories() {
+        let dir = std::env::temp_dir().join(format!("pavlova-render-{}", std::process::id()));
+        for sub in ["a", "b"] {
+            fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        let name = |root: &Path, path: &Path| output_name(root, path);
+
+        assert_eq!(name(&dir.join("a"), &dir.join("a/x.png")), Path::new("a/x"));
+        assert_eq!(name(&dir.join("b"), &dir.join("b/x.png")), Path::new("b/x"));
+        assert_eq!(name(&dir, &dir.join("a/x.png")), dir.file_name().map(|n| Path::new(n).join("a/x")).unwrap());
+        // A file argument keeps only its name
+        assert_eq!(name(&dir.join("a/x.png"), &dir.join("a/x.png")), Path::new("x"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_side_by_side_layout() {
+        let before = RgbaImage {
+            data: vec![10; 2 * 3 * 4],
+            width: 2,
+            height: 3,
+        };
+        let after = RgbaImage {
+            data: vec![200; 2 * 3 * 4],
+            width: 2,
+            height: 3,
+        };
+        let combined = side_by_side(&before, &after);
+
+        assert_eq!(combined.width, 4 + SIDE_BY_SIDE_GAP);
+        assert_eq!(combined.data.len(), combined.width * 3 * 4);
+        let row = &combined.data[combined.width * 4..combined.width * 8];
+        assert_eq!(row[0], 10);
+        assert_eq!(&row[8..12], &[0, 0, 0, 255]);
+        assert_eq!(row[row.len() - 1], 200);
+    }
+}
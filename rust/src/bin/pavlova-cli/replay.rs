@@ -0,0 +1,234 @@
+//! `replay` subcommand: push a recorded frame sequence through the pipeline with its timestamps.
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use pavlova_core::pipeline::apply_action;
+use pavlova_core::policy::{FilterAction, FilterPolicy};
+use pavlova_core::smoothing::{SmoothingConfig, TemporalSmoother};
+use serde::Serialize;
+
+use crate::frames::FrameReader;
+use crate::input::RgbaImage;
+use crate::output::{csv_field, write_png};
+use crate::{ModelArgs, OutputFormat};
+
+#[derive(clap::Args)]
+pub struct ReplayArgs {
+    #[command(flatten)]
+    model: ModelArgs,
+
+    /// Frame source: image directory, .y4m video, or raw RGBA dump with a timing index
+    input: PathBuf,
+
+    /// Timing index of a raw RGBA dump (default: the dump path with an .idx extension)
+    #[arg(long)]
+    index: Option<PathBuf>,
+
+    /// Frame rate of image directories (default 30) or override for Y4M files
+    #[arg(long)]
+    fps: Option<f64>,
+
+    /// Policy profile (default thresholds if omitted)
+    #[arg(short, long)]
+    policy: Option<PathBuf>,
+
+    /// Score averaging half-life in milliseconds (0 disables averaging)
+    #[arg(long, default_value_t = 300)]
+    half_life_ms: u64,
+
+    /// How long a filter stays on after the last frame needing it, in milliseconds
+    #[arg(long, default_value_t = 500)]
+    hold_ms: u64,
+
+    /// Decision log format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    /// Write each output frame (obscured or unchanged) as a PNG into this directory
+    #[arg(short, long)]
+    output_dir: Option<PathBuf>,
+}
+
+/// Decision log entry of one frame
+#[derive(Serialize)]
+struct FrameLog {
+    frame: usize,
+    timestamp_ms: f64,
+    /// Top class and confidence of this frame alone
+    raw_top_class: String,
+    raw_confidence: f32,
+    /// Whether the policy would filter this frame without smoothing
+    raw_filtered: bool,
+    /// Top class and confidence after temporal smoothing
+    top_class: String,
+    confidence: f32,
+    filtered: bool,
+    /// Filter kept on only by the hold period
+    held: bool,
+    action: &'static str,
+    parameter: f32,
+    classify_ms: f64,
+}
+
+pub fn run(args: ReplayArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let engine = args.model.load()?;
+    let policy = match &args.policy {
+        Some(path) => FilterPolicy::load_profile(path)?,
+        None => FilterPolicy::default(),
+    };
+    let mut smoother = TemporalSmoother::new(SmoothingConfig {
+        half_life: Duration::from_millis(args.half_life_ms),
+        hold: Duration::from_millis(args.hold_ms),
+    });
+    let frames = FrameReader::open(&args.input, args.fps, args.index.as_deref())?;
+    if let Some(dir) = &args.output_dir {
+        fs::create_dir_all(dir)?;
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    write_header(&mut out, args.format)?;
+
+    let mut summary = Summary::default();
+    for (index, frame) in frames.enumerate() {
+        let frame = frame?;
+        let image = &frame.image;
+
+        let start_time = Instant::now();
+        let raw = engine.classify(&image.data, image.width, image.height)?;
+        let classify_ms = start_time.elapsed().as_secs_f64() * 1000.0;
+
+        // Same policy inputs as the pipeline, so the preview matches production
+        let skin_ratio = policy.skin_ratio_input(&image.data, image.width, image.height)?;
+        let raw_filtered = policy.evaluate_with_skin_ratio(&raw, skin_ratio).should_filter;
+        let smoothed = smoother.update(frame.timestamp, &raw, skin_ratio, &policy);
+        let action = smoothed.decision.action;
+
+        let log = FrameLog {
+            frame: index,
+            timestamp_ms: frame.timestamp.as_secs_f64() * 1000.0,
+            raw_top_class: raw.top_class.clone(),
+            raw_confidence: raw.confidence,
+            raw_filtered,
+            top_class: smoothed.classification.top_class.clone(),
+            confidence: smoothed.classification.confidence,
+            filtered: smoothed.decision.should_filter,
+            held: smoothed.held,
+            action: action_name(action),
+            parameter: action.parameter(),
+            classify_ms,
+        };
+        write_log(&mut out, args.format, &log)?;
+        summary.add(&log);
+
+        if let Some(dir) = &args.output_dir {
+            let pixels = apply_action(&image.data, image.width, image.height, action)?;
+            let output = RgbaImage {
+                data: pixels.unwrap_or_else(|| image.data.clone()),
+                width: image.width,
+                height: image.height,
+            };
+            write_png(&dir.join(format!("frame_{:06}.png", index)), &output)?;
+        }
+    }
+
+    eprintln!(
+        "{} frames: {} filtered ({} held), filter toggled {} times (raw: {} filtered, {} toggles)",
+        summary.frames,
+        summary.filtered,
+        summary.held,
+        summary.toggles,
+        summary.raw_filtered,
+        summary.raw_toggles
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Frame counts and on/off transitions, to compare flicker with and without smoothing
+#[derive(Default)]
+struct Summary {
+    frames: usize,
+    filtered: usize,
+    held: usize,
+    toggles: usize,
+    raw_filtered: usize,
+    raw_toggles: usize,
+    previous: Option<(bool, bool)>,
+}
+
+impl Summary {
+    fn add(&mut self, log: &FrameLog) {
+        self.frames += 1;
+        self.filtered += log.filtered as usize;
+        self.held += log.held as usize;
+        self.raw_filtered += log.raw_filtered as usize;
+        if let Some((filtered, raw_filtered)) = self.previous {
+            self.toggles += (filtered != log.filtered) as usize;
+            self.raw_toggles += (raw_filtered != log.raw_filtered) as usize;
+        }
+        self.previous = Some((log.filtered, log.raw_filtered));
+    }
+}
+
+fn action_name(action: FilterAction) -> &'static str {
+    match action {
+        FilterAction::Allow => "allow",
+        FilterAction::Blur { .. } => "blur",
+        FilterAction::Pixelate { .. } => "pixelate",
+    }
+}
+
+fn write_header(out: &mut impl Write, format: OutputFormat) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => writeln!(
+            out,
+            "{:>6} {:>10}  {:<8} {:>6} {:<8} {:>6}  {:<8} {:>6} {:>8}",
+            "frame", "time_ms", "raw", "conf", "smoothed", "conf", "action", "param", "ms"
+        ),
+        OutputFormat::Csv => writeln!(
+            out,
+            "frame,timestamp_ms,raw_top_class,raw_confidence,raw_filtered,top_class,confidence,filtered,held,action,parameter,classify_ms"
+        ),
+        OutputFormat::Jsonl => Ok(()),
+    }
+}
+
+fn write_log(out: &mut impl Write, format: OutputFormat, log: &FrameLog) -> io::Result<()> {
+    match format {
+        OutputFormat::Jsonl => writeln!(out, "{}", serde_json::to_string(log)?),
+        OutputFormat::Table => writeln!(
+            out,
+            "{:>6} {:>10.1}  {:<8} {:>6.3} {:<8} {:>6.3}  {:<8} {:>6} {:>8.1}",
+            log.frame,
+            log.timestamp_ms,
+            log.raw_top_class,
+            log.raw_confidence,
+            log.top_class,
+            log.confidence,
+            if log.held { format!("{}*", log.action) } else { log.action.to_string() },
+            log.parameter,
+            log.classify_ms
+        ),
+        OutputFormat::Csv => writeln!(
+            out,
+            "{},{:.3},{},{:.6},{},{},{:.6},{},{},{},{},{:.3}",
+            log.frame,
+            log.timestamp_ms,
+            csv_field(&log.raw_top_class),
+            log.raw_confidence,
+            log.raw_filtered,
+            csv_field(&log.top_class),
+            log.confidence,
+            log.filtered,
+            log.held,
+            log.action,
+            log.parameter,
+            log.classify_ms
+        ),
+    }
+}
@@ -0,0 +1,121 @@
+//! `tune` subcommand: pick policy thresholds from saved predictions for a target error rate.
+
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use pavlova_core::evaluation::{policy_rates, tune_group_threshold, DetectionRates, OperatingPoint, Sample, TuningTarget};
+use pavlova_core::inference::SafetyGroup;
+use pavlova_core::policy::{FilterPolicy, ObscureMode};
+
+use crate::dataset::read_predictions;
+
+#[derive(clap::Args)]
+#[command(group(clap::ArgGroup::new("target").required(true).args(["max_fpr", "max_fnr"])))]
+pub struct TuneArgs {
+    /// predictions.csv written by `evaluate --output-dir`
+    predictions: PathBuf,
+
+    /// Highest acceptable fraction of safe images filtered, per content group
+    #[arg(long, value_parser = parse_rate)]
+    max_fpr: Option<f64>,
+
+    /// Highest acceptable fraction of a group's images left unfiltered
+    #[arg(long, value_parser = parse_rate)]
+    max_fnr: Option<f64>,
+
+    /// Effect written to the profile (blur or pixelate)
+    #[arg(long, default_value = "blur", value_parser = parse_mode)]
+    mode: ObscureMode,
+
+    /// Policy profile to write
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+pub fn run(args: TuneArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let samples: Vec<Sample> = read_predictions(&args.predictions)?.into_iter().map(|p| p.sample).collect();
+    if samples.is_empty() {
+        return Err(format!("No predictions in {}", args.predictions.display()).into());
+    }
+
+    let (target, target_name) = match (args.max_fpr, args.max_fnr) {
+        (Some(rate), _) => (TuningTarget::MaxFalsePositiveRate(rate), format!("false-positive rate <= {}", rate)),
+        (None, Some(rate)) => (TuningTarget::MaxFalseNegativeRate(rate), format!("false-negative rate <= {}", rate)),
+        (None, None) => unreachable!("clap requires one target"),
+    };
+
+    // The catch-all applies `unsafe_threshold` to suggestive frames too, so the suggestive
+    // threshold is tuned once the adult one is known
+    let adult = tune_group_threshold(&samples, &FilterPolicy::default(), SafetyGroup::Adult, target);
+    let base = FilterPolicy {
+        unsafe_threshold: adult.threshold,
+        mode: args.mode,
+        ..FilterPolicy::default()
+    };
+    let suggestive = tune_group_threshold(&samples, &base, SafetyGroup::Suggestive, target);
+    let policy = FilterPolicy {
+        suggestive_threshold: suggestive.threshold,
+        ..base
+    };
+    let overall = policy_rates(&samples, &policy);
+
+    let report = [
+        format!("Tuned on {} samples from {} for {}", samples.len(), args.predictions.display(), target_name),
+        describe_point("adult", &adult),
+        describe_point("suggestive", &suggestive),
+        format!("overall:    {}", describe_rates(&overall)),
+    ];
+    for line in &report {
+        println!("{}", line);
+    }
+
+    let mut profile = String::new();
+    for line in &report {
+        profile.push_str(&format!("# {}\n", line));
+    }
+    profile.push_str(&policy.to_profile());
+    fs::write(&args.output, profile)?;
+    eprintln!("Policy profile written to {}", args.output.display());
+
+    if !adult.target_met || !suggestive.target_met {
+        eprintln!("warning: target not reachable for every group; closest thresholds were used");
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn describe_point(group: &str, point: &OperatingPoint) -> String {
+    format!(
+        "{:<11} threshold {:.4}{}  {}",
+        format!("{}:", group),
+        point.threshold,
+        if point.target_met { "" } else { " (target not met)" },
+        describe_rates(&point.rates)
+    )
+}
+
+fn describe_rates(rates: &DetectionRates) -> String {
+    format!(
+        "FPR {:.4} ({}/{})  FNR {:.4} ({}/{})  precision {:.4}",
+        rates.false_positive_rate(),
+        rates.false_positives,
+        rates.negatives,
+        rates.false_negative_rate(),
+        rates.positives - rates.true_positives,
+        rates.positives,
+        rates.precision()
+    )
+}
+
+fn parse_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(rate),
+        _ => Err(format!("expected a rate between 0 and 1, got '{}'", value)),
+    }
+}
+
+fn parse_mode(value: &str) -> Result<ObscureMode, String> {
+    ObscureMode::parse(value).ok_or_else(|| format!("expected blur or pixelate, got '{}'", value))
+}
@@ -0,0 +1,434 @@
+//! Confidence calibration.
+//!
+//! Raw MobileNet scores are over-confident, and not equally so for every class, so the
+//! same policy threshold means different things per class. A calibration applied after
+//! inference fixes that: temperature scaling softens or sharpens the whole distribution,
+//! and per-class isotonic maps (monotone piecewise-linear curves) correct each class
+//! score separately. Both are fitted offline from a labeled validation set (see
+//! `pavlova-cli calibrate`) and stored as a calibration file:
+//!
+//! ```text
+//! # key = value, '#' comments
+//! temperature = 1.8
+//! isotonic.porn = 0:0, 0.35:0.12, 0.8:0.71, 1:1   # score:calibrated points
+//! ```
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::config::{CLASSES, NUM_CLASSES};
+use crate::evaluation::Sample;
+
+/// Smallest probability taken into a logarithm
+const MIN_PROBABILITY: f32 = 1e-7;
+
+/// Search range of the fitted temperature
+const TEMPERATURE_RANGE: (f32, f32) = (0.05, 20.0);
+
+/// Monotone piecewise-linear map of a score, flat beyond its first and last point
+#[derive(Debug, Clone, PartialEq)]
+pub struct IsotonicMap {
+    /// `(score, calibrated)` points, scores strictly increasing, calibrated non-decreasing
+    points: Vec<(f32, f32)>,
+}
+
+impl IsotonicMap {
+    /// Build a map from its points; fails unless it is monotone and within [0, 1]
+    pub fn new(points: Vec<(f32, f32)>) -> Result<Self, Box<dyn Error>> {
+        if points.is_empty() {
+            return Err("isotonic map needs at least one point".into());
+        }
+        if points.iter().any(|&(x, y)| !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y)) {
+            return Err("isotonic map points must lie within [0, 1]".into());
+        }
+        if points.windows(2).any(|pair| pair[1].0 <= pair[0].0 || pair[1].1 < pair[0].1) {
+            return Err("isotonic map must have increasing scores and non-decreasing values".into());
+        }
+        Ok(IsotonicMap { points })
+    }
+
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+
+    /// Calibrated value of `score`, interpolating between neighbouring points
+    pub fn apply(&self, score: f32) -> f32 {
+        let upper = self.points.partition_point(|&(x, _)| x < score);
+        if upper == 0 {
+            return self.points[0].1;
+        }
+        if upper == self.points.len() {
+            return self.points[upper - 1].1;
+        }
+        let (x0, y0) = self.points[upper - 1];
+        let (x1, y1) = self.points[upper];
+        y0 + (y1 - y0) * (score - x0) / (x1 - x0)
+    }
+
+    /// Fit by pool-adjacent-violators to `(score, outcome)` pairs
+    /// Fails unless both outcomes occur, since the map would be constant.
+    pub fn fit(pairs: &[(f32, bool)]) -> Result<Self, Box<dyn Error>> {
+        if !pairs.iter().any(|p| p.1) {
+            return Err("no positive samples".into());
+        }
+        if pairs.iter().all(|p| p.1) {
+            return Err("no negative samples".into());
+        }
+        let mut sorted = pairs.to_vec();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        // Blocks of (score sum, outcome sum, count), starting with one block per distinct score;
+        // a tied score joins the last block, which may then violate the order like a new one
+        let mut blocks: Vec<(f64, f64, f64)> = Vec::new();
+        for (i, &(score, outcome)) in sorted.iter().enumerate() {
+            let outcome = if outcome { 1.0 } else { 0.0 };
+            if i > 0 && sorted[i - 1].0 == score {
+                let last = blocks.last_mut().expect("block of the previous score");
+                last.0 += score as f64;
+                last.1 += outcome;
+                last.2 += 1.0;
+            } else {
+                blocks.push((score as f64, outcome, 1.0));
+            }
+            while blocks.len() > 1 {
+                let (last, previous) = (blocks[blocks.len() - 1], blocks[blocks.len() - 2]);
+                if previous.1 / previous.2 < last.1 / last.2 {
+                    break;
+                }
+                blocks.pop();
+                let merged = blocks.last_mut().expect("at least one block");
+                merged.0 += last.0;
+                merged.1 += last.1;
+                merged.2 += last.2;
+            }
+        }
+
+        // Interior points of a flat run add nothing to the interpolation
+        let points: Vec<(f32, f32)> = blocks
+            .iter()
+            .map(|&(x, y, n)| (((x / n) as f32).clamp(0.0, 1.0), ((y / n) as f32).clamp(0.0, 1.0)))
+            .collect();
+        let mut kept: Vec<(f32, f32)> = Vec::with_capacity(points.len());
+        for (i, &point) in points.iter().enumerate() {
+            let flat_before = i > 0 && points[i - 1].1 == point.1;
+            let flat_after = i + 1 < points.len() && points[i + 1].1 == point.1;
+            if !(flat_before && flat_after) {
+                kept.push(point);
+            }
+        }
+        Self::new(kept)
+    }
+}
+
+/// Which calibration stages `Calibration::fit` estimates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalibrationMethod {
+    Temperature,
+    Isotonic,
+    /// Temperature first, then isotonic maps on the tempered scores
+    Both,
+}
+
+impl CalibrationMethod {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "temperature" => Some(CalibrationMethod::Temperature),
+            "isotonic" => Some(CalibrationMethod::Isotonic),
+            "both" => Some(CalibrationMethod::Both),
+            _ => None,
+        }
+    }
+}
+
+/// Post-inference score calibration: temperature scaling, then per-class isotonic maps
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calibration {
+    /// Softmax temperature; 1 leaves scores unchanged, above 1 softens them
+    pub temperature: f32,
+    /// Map of each class score in `CLASSES` order, `None` to leave the class as is
+    pub class_maps: [Option<IsotonicMap>; NUM_CLASSES],
+}
+
+impl Default for Calibration {
+    /// Identity calibration
+    fn default() -> Self {
+        Calibration {
+            temperature: 1.0,
+            class_maps: Default::default(),
+        }
+    }
+}
+
+impl Calibration {
+    /// Calibrate scores in `CLASSES` order
+    /// Scores are renormalized to sum to 1 after the isotonic maps.
+    pub fn apply(&self, scores: [f32; NUM_CLASSES]) -> [f32; NUM_CLASSES] {
+        let mut scores = temper(scores, self.temperature);
+        if self.class_maps.iter().any(Option::is_some) {
+            for (score, map) in scores.iter_mut().zip(&self.class_maps) {
+                if let Some(map) = map {
+                    *score = map.apply(*score);
+                }
+            }
+            let total: f32 = scores.iter().sum();
+            if total > 0.0 {
+                scores = scores.map(|score| score / total);
+            }
+        }
+        scores
+    }
+
+    /// Apply to every sample of a labeled set
+    pub fn apply_samples(&self, samples: &[Sample]) -> Vec<Sample> {
+        samples
+            .iter()
+            .map(|sample| Sample {
+                scores: self.apply(sample.scores),
+                ..sample.clone()
+            })
+            .collect()
+    }
+
+    /// Fit a calibration to labeled samples
+    /// Also returns, per class, why no isotonic map could be fitted to it.
+    pub fn fit(samples: &[Sample], method: CalibrationMethod) -> (Self, [Option<String>; NUM_CLASSES]) {
+        let mut calibration = Calibration::default();
+        let mut unfitted: [Option<String>; NUM_CLASSES] = Default::default();
+        if method != CalibrationMethod::Isotonic {
+            calibration.temperature = fit_temperature(samples);
+        }
+        if method != CalibrationMethod::Temperature {
+            let tempered = calibration.apply_samples(samples);
+            for (class, (map, reason)) in calibration.class_maps.iter_mut().zip(&mut unfitted).enumerate() {
+                let pairs: Vec<(f32, bool)> = tempered.iter().map(|s| (s.scores[class], s.label == class)).collect();
+                match IsotonicMap::fit(&pairs) {
+                    Ok(fitted) => *map = Some(fitted),
+                    Err(e) => *reason = Some(e.to_string()),
+                }
+            }
+        }
+        (calibration, unfitted)
+    }
+
+    /// Parse a calibration file; missing keys keep the identity
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let mut calibration = Calibration::default();
+
+        for (index, raw_line) in text.lines().enumerate() {
+            let line = raw_line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| format!("line {}: expected 'key = value', got '{}'", index + 1, line))?;
+
+            if key == "temperature" {
+                calibration.temperature = match value.parse::<f32>() {
+                    Ok(t) if t.is_finite() && t > 0.0 => t,
+                    _ => return Err(format!("line {}: temperature must be a positive number, got '{}'", index + 1, value).into()),
+                };
+            } else if let Some(class) = key.strip_prefix("isotonic.") {
+                let class_index = CLASSES
+                    .iter()
+                    .position(|c| *c == class)
+                    .ok_or_else(|| format!("line {}: unknown class '{}'", index + 1, class))?;
+                let map = parse_points(value)
+                    .and_then(IsotonicMap::new)
+                    .map_err(|e| format!("line {}: {}", index + 1, e))?;
+                calibration.class_maps[class_index] = Some(map);
+            } else {
+                return Err(format!("line {}: unknown key '{}'", index + 1, key).into());
+            }
+        }
+
+        Ok(calibration)
+    }
+
+    /// Load a calibration file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Cannot read calibration {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    /// Serialize in the format read by `parse`
+    pub fn to_text(&self) -> String {
+        let mut text = format!("temperature = {}\n", self.temperature);
+        for (class, map) in CLASSES.iter().zip(&self.class_maps) {
+            if let Some(map) = map {
+                let points: Vec<String> = map.points.iter().map(|(x, y)| format!("{}:{}", x, y)).collect();
+                text.push_str(&format!("isotonic.{} = {}\n", class, points.join(", ")));
+            }
+        }
+        text
+    }
+}
+
+/// Softmax of `ln(p) / temperature`, i.e. the tempered softmax of the underlying logits
+fn temper(scores: [f32; NUM_CLASSES], temperature: f32) -> [f32; NUM_CLASSES] {
+    if temperature == 1.0 {
+        return scores;
+    }
+    let logits = scores.map(|p| p.max(MIN_PROBABILITY).ln() / temperature);
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exps = logits.map(|l| (l - max).exp());
+    let total: f32 = exps.iter().sum();
+    exps.map(|e| e / total)
+}
+
+/// Mean negative log-likelihood of the labels at a temperature
+fn negative_log_likelihood(samples: &[Sample], temperature: f32) -> f64 {
+    let total: f64 = samples
+        .iter()
+        .map(|s| -(temper(s.scores, temperature)[s.label].max(MIN_PROBABILITY) as f64).ln())
+        .sum();
+    total / samples.len().max(1) as f64
+}
+
+/// Temperature minimizing the negative log-likelihood, by golden-section search on its log
+pub fn fit_temperature(samples: &[Sample]) -> f32 {
+    if samples.is_empty() {
+        return 1.0;
+    }
+    let ratio = (5.0f64.sqrt() - 1.0) / 2.0;
+    let nll = |log_t: f64| negative_log_likelihood(samples, log_t.exp() as f32);
+    let (mut low, mut high) = ((TEMPERATURE_RANGE.0 as f64).ln(), (TEMPERATURE_RANGE.1 as f64).ln());
+    let mut a = high - ratio * (high - low);
+    let mut b = low + ratio * (high - low);
+    let (mut nll_a, mut nll_b) = (nll(a), nll(b));
+    for _ in 0..60 {
+        if nll_a < nll_b {
+            high = b;
+            b = a;
+            nll_b = nll_a;
+            a = high - ratio * (high - low);
+            nll_a = nll(a);
+        } else {
+            low = a;
+            a = b;
+            nll_a = nll_b;
+            b = low + ratio * (high - low);
+            nll_b = nll(b);
+        }
+    }
+    ((low + high) / 2.0).exp() as f32
+}
+
+fn parse_points(value: &str) -> Result<Vec<(f32, f32)>, Box<dyn Error>> {
+    value
+        .split(',')
+        .map(|point| {
+            let (x, y) = point.trim().split_once(':').ok_or_else(|| format!("expected 'score:value', got '{}'", point.trim()))?;
+            match (x.trim().parse::<f32>(), y.trim().parse::<f32>()) {
+                (Ok(x), Ok(y)) => Ok((x, y)),
+                _ => Err(format!("invalid point '{}'", point.trim()).into()),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isotonic_map() {
+        let map = IsotonicMap::new(vec![(0.2, 0.0), (0.6, 0.4), (1.0, 1.0)]).unwrap();
+        assert_eq!(map.apply(0.1), 0.0);
+        assert!((map.apply(0.4) - 0.2).abs() < 1e-6);
+        assert_eq!(map.apply(1.0), 1.0);
+
+        assert!(IsotonicMap::new(vec![]).is_err());
+        assert!(IsotonicMap::new(vec![(0.5, 0.6), (0.7, 0.4)]).is_err());
+        assert!(IsotonicMap::new(vec![(0.5, 0.6), (0.5, 0.7)]).is_err());
+
+        // Violators are pooled into a monotone fit
+        let fitted = IsotonicMap::fit(&[(0.1, false), (0.3, true), (0.5, false), (0.9, true)]).unwrap();
+        assert_eq!(fitted.points(), [(0.1, 0.0), (0.4, 0.5), (0.9, 1.0)]);
+        assert!(IsotonicMap::fit(&[(0.1, true), (0.9, true)]).is_err());
+    }
+
+    #[test]
+    fn test_isotonic_fit_pools_tied_scores() {
+        // Scores tie at 0.9, and the tie pulls that block below the one before it
+        let pairs = [(0.1, false), (0.1, true), (0.9, true), (0.9, false), (0.9, false), (0.9, false), (1.0, true)];
+        let fitted = IsotonicMap::fit(&pairs).unwrap();
+        assert!(fitted.points().windows(2).all(|pair| pair[0].0 < pair[1].0 && pair[0].1 <= pair[1].1));
+        let points = fitted.points();
+        assert_eq!(points.len(), 2);
+        assert!((points[0].0 - 3.8 / 6.0).abs() < 1e-6 && (points[0].1 - 2.0 / 6.0).abs() < 1e-6);
+        assert_eq!(points[1], (1.0, 1.0));
+    }
+
+    #[test]
+    fn test_temperature_softens_scores() {
+        let scores = [0.05, 0.05, 0.1, 0.7, 0.1];
+        let soft = temper(scores, 2.0);
+        assert!(soft[3] < 0.7 && soft[3] > soft[2]);
+        assert!((soft.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        assert_eq!(temper(scores, 1.0), scores);
+    }
+
+    #[test]
+    fn test_fit_temperature_on_overconfident_scores() {
+        // Always 90% sure, right 60% of the time: the fit must soften
+        let samples: Vec<Sample> = (0..100)
+            .map(|i| Sample {
+                label: if i % 5 < 3 { 3 } else { 2 },
+                scores: [0.025, 0.025, 0.025, 0.9, 0.025],
+                skin_ratio: None,
+            })
+            .collect();
+        assert!(fit_temperature(&samples) > 1.5);
+    }
+
+    #[test]
+    fn test_fit_reduces_calibration_error() {
+        use crate::evaluation::{expected_calibration_error, CALIBRATION_BINS};
+
+        // Every class is over-confident: the top class is right 60% of the time at any score
+        let samples: Vec<Sample> = (0..500)
+            .map(|i| {
+                let top = i % NUM_CLASSES;
+                let confidence = 0.7 + 0.25 * ((i / 5) % 10) as f32 / 10.0;
+                let mut scores = [(1.0 - confidence) / 4.0; NUM_CLASSES];
+                scores[top] = confidence;
+                let k = (i / 50) % 5;
+                let label = if k < 3 { top } else { (top + k - 2) % NUM_CLASSES };
+                Sample {
+                    label,
+                    scores,
+                    skin_ratio: None,
+                }
+            })
+            .collect();
+        let before = expected_calibration_error(&samples, CALIBRATION_BINS);
+        for method in [CalibrationMethod::Temperature, CalibrationMethod::Isotonic, CalibrationMethod::Both] {
+            let (calibration, unfitted) = Calibration::fit(&samples, method);
+            // Scores take ten distinct values, so every class map is fitted over tied scores
+            assert_eq!(unfitted, [None, None, None, None, None], "{:?}", method);
+            let after = expected_calibration_error(&calibration.apply_samples(&samples), CALIBRATION_BINS);
+            // A single temperature cannot fix every confidence level; the per-class maps can
+            let bound = if method == CalibrationMethod::Temperature { before } else { before / 2.0 };
+            assert!(after < bound, "{:?}: {} -> {}", method, before, after);
+        }
+    }
+
+    #[test]
+    fn test_calibration_file_round_trip() {
+        let calibration = Calibration {
+            temperature: 1.5,
+            class_maps: [None, None, None, Some(IsotonicMap::new(vec![(0.0, 0.0), (0.5, 0.25), (1.0, 1.0)]).unwrap()), None],
+        };
+        assert_eq!(Calibration::parse(&calibration.to_text()).unwrap(), calibration);
+        assert_eq!(Calibration::parse("# none\n").unwrap(), Calibration::default());
+
+        assert!(Calibration::parse("temperature = 0").is_err());
+        assert!(Calibration::parse("isotonic.safe = 0:0").is_err());
+        assert!(Calibration::parse("isotonic.porn = 0:0.5, 1:0.2").is_err());
+        assert!(Calibration::parse("isotonic.porn = 0-0").is_err());
+        assert!(Calibration::parse("bins = 10").is_err());
+    }
+}
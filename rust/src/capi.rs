@@ -0,0 +1,312 @@
+//! Plain C ABI for non-JNI hosts (feature `capi`).
+//!
+//! Engines are addressed by opaque `u64` handles from the shared registry, so a
+//! stale or double-freed handle is reported as an error instead of corrupting memory.
+//! Every function returns a `PavlovaStatus`; on failure the message is available from
+//! `pavlova_last_error_message` on the calling thread. Panics never cross the boundary.
+//! cbindgen generates the C header into `$OUT_DIR/include/pavlova.h` at build time; the
+//! copy shipped in `include/pavlova.h` is checked against it by `tests/capi.rs`.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use crate::config::NUM_CLASSES;
+use crate::image::{blur, pixelate, MAX_BLUR_RADIUS};
+use crate::inference::MLEngine;
+use crate::integrity::{IntegrityError, ModelIntegrity};
+use crate::registry::{Registry, INVALID_HANDLE};
+use crate::utils::panic_message;
+
+// Loaded engines, addressed by the handles returned from `pavlova_engine_new`
+lazy_static::lazy_static! {
+    static ref ENGINES: Registry<MLEngine> = Registry::new();
+}
+
+/// Number of scores in `PavlovaClassification`
+pub const PAVLOVA_NUM_CLASSES: usize = 5;
+
+// cbindgen only sees this file, so the size is spelled out and checked here
+const _: () = assert!(PAVLOVA_NUM_CLASSES == NUM_CLASSES);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Result code of every C API call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PavlovaStatus {
+    Ok = 0,
+    /// A required pointer argument was null
+    NullPointer = 1,
+    /// Bad size, dimensions, parameter or non-UTF-8 string
+    InvalidArgument = 2,
+    /// The handle does not refer to a live engine
+    InvalidHandle = 3,
+    /// The model file could not be loaded
+    ModelLoad = 4,
+    /// Preprocessing or inference failed
+    Inference = 5,
+    /// Blur / pixelation failed
+    Image = 6,
+    /// A Rust panic was caught at the boundary
+    Panic = 7,
+    /// The model failed its integrity check (digest or signature mismatch)
+    Integrity = 8,
+}
+
+/// Classification output, scores in `CLASSES` order (drawing, hentai, neutral, porn, sexy)
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PavlovaClassification {
+    pub scores: [f32; PAVLOVA_NUM_CLASSES],
+    pub top_class_index: u32,
+    pub is_safe: bool,
+    pub confidence: f32,
+}
+
+/// Error carried through a C API call: status plus message for `pavlova_last_error_message`
+struct CError(PavlovaStatus, String);
+
+type CResult = Result<(), CError>;
+
+/// Run a C API body, recording failures in the thread-local last error and catching panics
+fn guarded(body: impl FnOnce() -> CResult) -> PavlovaStatus {
+    let error = match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => {
+            set_last_error(None);
+            return PavlovaStatus::Ok;
+        }
+        Ok(Err(error)) => error,
+        Err(payload) => CError(PavlovaStatus::Panic, format!("native panic: {}", panic_message(payload.as_ref()))),
+    };
+
+    log::error!("C API error {:?}: {}", error.0, error.1);
+    set_last_error(Some(&error.1));
+    error.0
+}
+
+fn set_last_error(message: Option<&str>) {
+    // Interior NULs cannot be represented in a C string; drop them
+    let message = message.map(|m| CString::new(m.replace('\0', "")).unwrap_or_default());
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+/// Borrow an RGBA input buffer and check it against the dimensions
+fn rgba_input<'a>(data: *const u8, len: usize, width: u32, height: u32) -> Result<&'a [u8], CError> {
+    if data.is_null() {
+        return Err(CError(PavlovaStatus::NullPointer, "image data is null".into()));
+    }
+    let expected = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|n| n.checked_mul(4))
+        .ok_or_else(|| CError(PavlovaStatus::InvalidArgument, "image dimensions overflow".into()))?;
+    if len != expected {
+        return Err(CError(
+            PavlovaStatus::InvalidArgument,
+            format!("RGBA data size mismatch: expected {}x{}x4={}, got {}", width, height, expected, len),
+        ));
+    }
+    // SAFETY: caller guarantees `data` points to `len` readable bytes
+    Ok(unsafe { slice::from_raw_parts(data, len) })
+}
+
+/// Copy an effect result into the caller's output buffer
+fn write_output(output: &[u8], out: *mut u8, out_len: usize) -> CResult {
+    if out.is_null() {
+        return Err(CError(PavlovaStatus::NullPointer, "output buffer is null".into()));
+    }
+    if out_len < output.len() {
+        return Err(CError(
+            PavlovaStatus::InvalidArgument,
+            format!("output buffer too small: need {}, got {}", output.len(), out_len),
+        ));
+    }
+    // SAFETY: caller guarantees `out` points to `out_len` writable bytes, checked above
+    unsafe { ptr::copy_nonoverlapping(output.as_ptr(), out, output.len()) };
+    Ok(())
+}
+
+/// Load an ONNX model and return its engine handle in `out_handle`
+///
+/// # Safety
+/// `model_path` must be a valid NUL-terminated string and `out_handle` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn pavlova_engine_new(model_path: *const c_char, out_handle: *mut u64) -> PavlovaStatus {
+    guarded(|| {
+        if model_path.is_null() || out_handle.is_null() {
+            return Err(CError(PavlovaStatus::NullPointer, "model_path or out_handle is null".into()));
+        }
+        let path = CStr::from_ptr(model_path)
+            .to_str()
+            .map_err(|_| CError(PavlovaStatus::InvalidArgument, "model path is not valid UTF-8".into()))?;
+
+        let engine = MLEngine::new(path).map_err(|e| CError(PavlovaStatus::ModelLoad, e.to_string()))?;
+        *out_handle = ENGINES.insert(engine);
+        Ok(())
+    })
+}
+
+/// Load an ONNX model after verifying it, returning its engine handle in `out_handle`
+/// Pass either `sha256` (32 bytes, covering the manifest if there is one, see
+/// `pinned_digest`), or `public_key` (32 bytes) and `signature` (64 bytes) over the model
+/// and its manifest; unused arguments are NULL. Passing both returns
+/// `PavlovaStatus::InvalidArgument`; a failed check returns `PavlovaStatus::Integrity`.
+///
+/// # Safety
+/// `model_path` must be a valid NUL-terminated string, `out_handle` a valid pointer, and each
+/// non-NULL key argument must point to the number of bytes given above.
+#[no_mangle]
+pub unsafe extern "C" fn pavlova_engine_new_verified(
+    model_path: *const c_char,
+    sha256: *const u8,
+    public_key: *const u8,
+    signature: *const u8,
+    out_handle: *mut u64,
+) -> PavlovaStatus {
+    guarded(|| {
+        if model_path.is_null() || out_handle.is_null() {
+            return Err(CError(PavlovaStatus::NullPointer, "model_path or out_handle is null".into()));
+        }
+        let path = CStr::from_ptr(model_path)
+            .to_str()
+            .map_err(|_| CError(PavlovaStatus::InvalidArgument, "model path is not valid UTF-8".into()))?;
+
+        let integrity = if !sha256.is_null() && (!public_key.is_null() || !signature.is_null()) {
+            return Err(CError(
+                PavlovaStatus::InvalidArgument,
+                "pass either sha256 or public_key and signature, not both".into(),
+            ));
+        } else if !sha256.is_null() {
+            let mut digest = [0u8; 32];
+            digest.copy_from_slice(slice::from_raw_parts(sha256, 32));
+            ModelIntegrity::Sha256(digest)
+        } else if !public_key.is_null() && !signature.is_null() {
+            ModelIntegrity::ed25519(slice::from_raw_parts(public_key, 32), slice::from_raw_parts(signature, 64))
+                .map_err(|e| CError(PavlovaStatus::InvalidArgument, e.to_string()))?
+        } else {
+            return Err(CError(PavlovaStatus::NullPointer, "sha256, or public_key and signature, is required".into()));
+        };
+
+        let engine = MLEngine::new_verified(path, &integrity).map_err(|e| {
+            let status = if e.is::<IntegrityError>() { PavlovaStatus::Integrity } else { PavlovaStatus::ModelLoad };
+            CError(status, e.to_string())
+        })?;
+        *out_handle = ENGINES.insert(engine);
+        Ok(())
+    })
+}
+
+/// Classify an RGBA frame of `width`×`height` pixels (`len` must equal `width * height * 4`)
+///
+/// # Safety
+/// `rgba` must point to `len` readable bytes and `out` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn pavlova_engine_classify(
+    handle: u64,
+    rgba: *const u8,
+    len: usize,
+    width: u32,
+    height: u32,
+    out: *mut PavlovaClassification,
+) -> PavlovaStatus {
+    guarded(|| {
+        if out.is_null() {
+            return Err(CError(PavlovaStatus::NullPointer, "output is null".into()));
+        }
+        let engine = ENGINES
+            .get(handle)
+            .ok_or_else(|| CError(PavlovaStatus::InvalidHandle, format!("no engine for handle {}", handle)))?;
+        let data = rgba_input(rgba, len, width, height)?;
+
+        let result = engine
+            .classify(data, width as usize, height as usize)
+            .map_err(|e| CError(PavlovaStatus::Inference, e.to_string()))?;
+
+        *out = PavlovaClassification {
+            scores: result.scores,
+            top_class_index: result.top_class_index as u32,
+            is_safe: result.is_safe,
+            confidence: result.confidence,
+        };
+        Ok(())
+    })
+}
+
+/// Blur an RGBA frame into `out` (`out_len` must be at least `len`); needs no engine
+/// `radius` must be within 0 to `MAX_BLUR_RADIUS` (256).
+///
+/// # Safety
+/// `rgba` must point to `len` readable bytes and `out` to `out_len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn pavlova_engine_blur(
+    rgba: *const u8,
+    len: usize,
+    width: u32,
+    height: u32,
+    radius: f32,
+    out: *mut u8,
+    out_len: usize,
+) -> PavlovaStatus {
+    guarded(|| {
+        let data = rgba_input(rgba, len, width, height)?;
+        if !(0.0..=MAX_BLUR_RADIUS).contains(&radius) {
+            return Err(CError(
+                PavlovaStatus::InvalidArgument,
+                format!("invalid blur radius {} (expected 0 to {})", radius, MAX_BLUR_RADIUS),
+            ));
+        }
+        let blurred = blur(data, width as usize, height as usize, radius)
+            .map_err(|e| CError(PavlovaStatus::Image, e.to_string()))?;
+        write_output(&blurred, out, out_len)
+    })
+}
+
+/// Pixelate an RGBA frame into `out` (`out_len` must be at least `len`); needs no engine
+///
+/// # Safety
+/// `rgba` must point to `len` readable bytes and `out` to `out_len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn pavlova_engine_pixelate(
+    rgba: *const u8,
+    len: usize,
+    width: u32,
+    height: u32,
+    block_size: u32,
+    out: *mut u8,
+    out_len: usize,
+) -> PavlovaStatus {
+    guarded(|| {
+        let data = rgba_input(rgba, len, width, height)?;
+        if block_size == 0 {
+            return Err(CError(PavlovaStatus::InvalidArgument, "block size must be positive".into()));
+        }
+        let pixelated = pixelate(data, width as usize, height as usize, block_size as usize)
+            .map_err(|e| CError(PavlovaStatus::Image, e.to_string()))?;
+        write_output(&pixelated, out, out_len)
+    })
+}
+
+/// Release an engine; in-flight classifications on other threads finish first
+#[no_mangle]
+pub extern "C" fn pavlova_engine_free(handle: u64) -> PavlovaStatus {
+    guarded(|| {
+        if handle == INVALID_HANDLE {
+            return Ok(());
+        }
+        ENGINES
+            .remove(handle)
+            .map(|_| ())
+            .ok_or_else(|| CError(PavlovaStatus::InvalidHandle, format!("no engine for handle {}", handle)))
+    })
+}
+
+/// Message of the last failed call on this thread, or NULL if it succeeded
+/// The pointer stays valid until the next C API call on the same thread.
+#[no_mangle]
+pub extern "C" fn pavlova_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
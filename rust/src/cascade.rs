@@ -0,0 +1,209 @@
+//! Cascade inference: a cheap pre-filter in front of the full classifier.
+//!
+//! Most frames (text, UI, landscapes) are clearly safe, yet the full MobileNetV2
+//! classification costs the same on every one. `CascadeEngine` first computes a cheap
+//! gate score, either from a tiny model or from the fraction of skin-tone pixels, and
+//! only runs the full `MLEngine` when that score reaches the escalation threshold.
+//! Counters record how many frames each stage decided, to tune the threshold against
+//! the saving it buys.
+//!
+//! `EnsembleStrategy::Cascade` gates an ensemble the same way and shares the threshold
+//! check and gate test below. It differs in what the gate is: there the gate is a full
+//! ensemble member whose result is averaged with the others when escalated, and any
+//! member error fails the frame. Here the pre-filter may not be a classifier at all, only
+//! the full model's result is reported, and a failing pre-filter escalates.
+
+use std::error::Error;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::analysis::skin_ratio;
+use crate::evaluation::unsafe_score;
+use crate::inference::{ClassificationResult, MLEngine};
+
+/// First stage of a cascade
+pub enum PreFilter {
+    /// Small, low-resolution model (its manifest declares the input size); the gate
+    /// score is its unsafe score (hentai + porn + sexy)
+    Model(Box<MLEngine>),
+    /// Fraction of skin-tone pixels (`analysis::skin_ratio`); no inference at all
+    SkinTone,
+}
+
+impl PreFilter {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PreFilter::Model(_) => "model",
+            PreFilter::SkinTone => "skin_tone",
+        }
+    }
+}
+
+/// Stage that produced a cascade result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CascadeStage {
+    PreFilter,
+    Full,
+}
+
+/// Classification of a frame by the cascade
+#[derive(Debug, Clone)]
+pub struct CascadeResult {
+    /// The pre-filter model's result, or the full model's result when escalated;
+    /// `None` when the skin-tone pre-filter passed the frame without any model
+    pub classification: Option<ClassificationResult>,
+    pub stage: CascadeStage,
+    /// Gate score computed by the pre-filter (`None` if the pre-filter failed)
+    pub prefilter_score: Option<f32>,
+}
+
+/// How often each stage decided
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CascadeStats {
+    pub frames: u64,
+    /// Frames the pre-filter passed as safe without the full model
+    pub prefilter_decided: u64,
+    /// Frames sent to the full model
+    pub escalated: u64,
+    /// Escalations caused by a pre-filter error rather than its score
+    pub prefilter_errors: u64,
+}
+
+impl CascadeStats {
+    /// Fraction of frames that needed the full model
+    pub fn escalation_rate(&self) -> f64 {
+        if self.frames == 0 {
+            0.0
+        } else {
+            self.escalated as f64 / self.frames as f64
+        }
+    }
+}
+
+/// Check a gate's escalation threshold
+pub(crate) fn check_escalate_at(escalate_at: f32) -> Result<(), Box<dyn Error>> {
+    if !(0.0..=1.0).contains(&escalate_at) {
+        return Err(format!("Escalation threshold must be between 0 and 1, got {}", escalate_at).into());
+    }
+    Ok(())
+}
+
+/// Whether a gate score sends the frame on to the next stage
+pub(crate) fn escalates(score: f32, escalate_at: f32) -> bool {
+    score >= escalate_at
+}
+
+/// Pre-filter plus full classifier
+/// Counters are atomic, so a shared `&CascadeEngine` can classify from several threads.
+pub struct CascadeEngine {
+    prefilter: PreFilter,
+    full: MLEngine,
+    escalate_at: f32,
+    frames: AtomicU64,
+    prefilter_decided: AtomicU64,
+    escalated: AtomicU64,
+    prefilter_errors: AtomicU64,
+}
+
+impl CascadeEngine {
+    /// Frames whose gate score reaches `escalate_at` (0..=1) go to `full`; lower means safer
+    /// but slower, 0 escalates every frame
+    pub fn new(prefilter: PreFilter, full: MLEngine, escalate_at: f32) -> Result<Self, Box<dyn Error>> {
+        check_escalate_at(escalate_at)?;
+        log::info!("Cascade: {} pre-filter, escalating at {}", prefilter.as_str(), escalate_at);
+        Ok(CascadeEngine {
+            prefilter,
+            full,
+            escalate_at,
+            frames: AtomicU64::new(0),
+            prefilter_decided: AtomicU64::new(0),
+            escalated: AtomicU64::new(0),
+            prefilter_errors: AtomicU64::new(0),
+        })
+    }
+
+    pub fn prefilter(&self) -> &PreFilter {
+        &self.prefilter
+    }
+
+    pub fn full(&self) -> &MLEngine {
+        &self.full
+    }
+
+    pub fn escalate_at(&self) -> f32 {
+        self.escalate_at
+    }
+
+    /// Classify raw RGBA image data, running the full model only if the pre-filter escalates
+    /// A failing pre-filter escalates rather than letting the frame through unchecked.
+    pub fn classify(&self, rgba_data: &[u8], width: usize, height: usize) -> Result<CascadeResult, Box<dyn Error>> {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+
+        let gate = match &self.prefilter {
+            PreFilter::Model(engine) => engine
+                .classify(rgba_data, width, height)
+                .map(|result| (unsafe_score(&result.scores), Some(result))),
+            PreFilter::SkinTone => skin_ratio(rgba_data, width, height).map(|ratio| (ratio, None)),
+        };
+
+        let prefilter_score = match gate {
+            Ok((score, result)) if !escalates(score, self.escalate_at) => {
+                self.prefilter_decided.fetch_add(1, Ordering::Relaxed);
+                return Ok(CascadeResult {
+                    classification: result,
+                    stage: CascadeStage::PreFilter,
+                    prefilter_score: Some(score),
+                });
+            }
+            Ok((score, _)) => Some(score),
+            Err(e) => {
+                self.prefilter_errors.fetch_add(1, Ordering::Relaxed);
+                log::warn!("Cascade pre-filter failed, escalating: {}", e);
+                None
+            }
+        };
+
+        self.escalated.fetch_add(1, Ordering::Relaxed);
+        Ok(CascadeResult {
+            classification: Some(self.full.classify(rgba_data, width, height)?),
+            stage: CascadeStage::Full,
+            prefilter_score,
+        })
+    }
+
+    /// Counters since creation or the last `reset_stats`
+    pub fn stats(&self) -> CascadeStats {
+        CascadeStats {
+            frames: self.frames.load(Ordering::Relaxed),
+            prefilter_decided: self.prefilter_decided.load(Ordering::Relaxed),
+            escalated: self.escalated.load(Ordering::Relaxed),
+            prefilter_errors: self.prefilter_errors.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn reset_stats(&self) {
+        for counter in [&self.frames, &self.prefilter_decided, &self.escalated, &self.prefilter_errors] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_rates() {
+        let stats = CascadeStats { frames: 8, prefilter_decided: 6, escalated: 2, prefilter_errors: 0 };
+        assert_eq!(stats.escalation_rate(), 0.25);
+        assert_eq!(CascadeStats::default().escalation_rate(), 0.0);
+    }
+
+    #[test]
+    fn test_gate_threshold() {
+        assert!(check_escalate_at(0.0).is_ok() && check_escalate_at(1.0).is_ok());
+        assert!(check_escalate_at(1.5).is_err() && check_escalate_at(f32::NAN).is_err());
+        assert!(escalates(0.3, 0.3) && !escalates(0.29, 0.3));
+        assert!(escalates(0.0, 0.0));
+    }
+}
@@ -1,4 +1,4 @@
-/// Model configuration constants shared across modules.
+//! Model configuration constants shared across modules.
 
 /// GantMan NSFW model output class names.
 /// Order matches the model's output tensor.
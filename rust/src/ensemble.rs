@@ -0,0 +1,198 @@
+//! Multi-model ensemble.
+//!
+//! `EnsembleEngine` runs several `MLEngine`s on the same frame and combines their
+//! scores. Members may use different input sizes and normalization (each model's
+//! manifest declares its `PreprocessSpec`); a frame is preprocessed once per distinct
+//! spec. The combined result is returned together with each member's own result, so
+//! disagreements between models can be logged and evaluated.
+//!
+//! The `Cascade` strategy shares its gate with `cascade::CascadeEngine`, which puts a
+//! cheaper, possibly model-free pre-filter in front of a single classifier instead.
+
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use crate::cascade::{check_escalate_at, escalates};
+use crate::config::NUM_CLASSES;
+use crate::evaluation::unsafe_score;
+use crate::image::{preprocess, PreprocessSpec};
+use crate::inference::{ClassificationResult, MLEngine};
+
+/// Model taking part in an ensemble
+pub struct EnsembleMember {
+    /// Name reported in per-model results
+    pub name: String,
+    pub engine: MLEngine,
+    /// Relative weight in averages and votes
+    pub weight: f32,
+}
+
+impl EnsembleMember {
+    /// Member with weight 1
+    pub fn new(name: &str, engine: MLEngine) -> Self {
+        EnsembleMember {
+            name: name.to_string(),
+            engine,
+            weight: 1.0,
+        }
+    }
+
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+}
+
+/// How member results are combined
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnsembleStrategy {
+    /// Weighted mean of the member scores
+    WeightedAverage,
+    /// Result of the member with the highest unsafe score (hentai + porn + sexy)
+    MaxUnsafe,
+    /// Each member votes for its top class with its weight; scores are the vote shares
+    MajorityVote,
+    /// The first member gates the others: they only run, and are averaged, when the
+    /// first member's unsafe score reaches `escalate_at`; otherwise its result stands
+    Cascade { escalate_at: f32 },
+}
+
+impl EnsembleStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnsembleStrategy::WeightedAverage => "weighted_average",
+            EnsembleStrategy::MaxUnsafe => "max_unsafe",
+            EnsembleStrategy::MajorityVote => "majority_vote",
+            EnsembleStrategy::Cascade { .. } => "cascade",
+        }
+    }
+}
+
+/// One member's classification of a frame
+#[derive(Debug, Clone)]
+pub struct MemberResult {
+    pub name: String,
+    pub result: ClassificationResult,
+    /// Inference time, plus preprocessing if this member was the first to need its spec
+    pub elapsed: Duration,
+}
+
+/// Combined classification with the results it was combined from
+#[derive(Debug, Clone)]
+pub struct EnsembleResult {
+    pub combined: ClassificationResult,
+    /// Members that ran, in ensemble order; a cascade that did not escalate lists only the gate
+    pub members: Vec<MemberResult>,
+}
+
+/// Several models classifying the same frame
+pub struct EnsembleEngine {
+    members: Vec<EnsembleMember>,
+    strategy: EnsembleStrategy,
+}
+
+impl EnsembleEngine {
+    /// Fails without members, with a non-positive weight, or with a cascade of fewer than two models
+    pub fn new(members: Vec<EnsembleMember>, strategy: EnsembleStrategy) -> Result<Self, Box<dyn Error>> {
+        if members.is_empty() {
+            return Err("Ensemble needs at least one model".into());
+        }
+        if let Some(member) = members.iter().find(|m| !(m.weight.is_finite() && m.weight > 0.0)) {
+            return Err(format!("Ensemble member {} has invalid weight {}", member.name, member.weight).into());
+        }
+        if let EnsembleStrategy::Cascade { escalate_at } = strategy {
+            if members.len() < 2 {
+                return Err("Cascade ensemble needs a gate model and at least one more".into());
+            }
+            check_escalate_at(escalate_at).map_err(|e| format!("Cascade ensemble: {}", e))?;
+        }
+        log::info!("Ensemble of {} models ({})", members.len(), strategy.as_str());
+        Ok(EnsembleEngine { members, strategy })
+    }
+
+    pub fn members(&self) -> &[EnsembleMember] {
+        &self.members
+    }
+
+    pub fn strategy(&self) -> EnsembleStrategy {
+        self.strategy
+    }
+
+    /// Classify raw RGBA image data with the members the strategy needs and combine them
+    pub fn classify(&self, rgba_data: &[u8], width: usize, height: usize) -> Result<EnsembleResult, Box<dyn Error>> {
+        // Preprocessed frame per distinct spec, filled as members need them
+        let mut inputs: Vec<(&PreprocessSpec, Vec<f32>)> = Vec::new();
+        let mut run = |member_index: usize| -> Result<MemberResult, Box<dyn Error>> {
+            let member = &self.members[member_index];
+            let start = Instant::now();
+            let spec = member.engine.preprocess_spec();
+            let index = match inputs.iter().position(|(s, _)| *s == spec) {
+                Some(index) => index,
+                None => {
+                    inputs.push((spec, preprocess(rgba_data, width, height, spec)?));
+                    inputs.len() - 1
+                }
+            };
+            let result = member
+                .engine
+                .classify_preprocessed(&inputs[index].1)
+                .map_err(|e| format!("Ensemble member {}: {}", member.name, e))?;
+            Ok(MemberResult {
+                name: member.name.clone(),
+                result,
+                elapsed: start.elapsed(),
+            })
+        };
+
+        let gate_result = run(0)?;
+
+        if let EnsembleStrategy::Cascade { escalate_at } = self.strategy {
+            if !escalates(unsafe_score(&gate_result.result.scores), escalate_at) {
+                return Ok(EnsembleResult {
+                    combined: gate_result.result.clone(),
+                    members: vec![gate_result],
+                });
+            }
+        }
+
+        let mut results = vec![gate_result];
+        for member_index in 1..self.members.len() {
+            results.push(run(member_index)?);
+        }
+
+        let combined = match self.strategy {
+            EnsembleStrategy::WeightedAverage => self.weighted_average(&results, 0),
+            EnsembleStrategy::Cascade { .. } => self.weighted_average(&results, 1),
+            // First member wins ties
+            EnsembleStrategy::MaxUnsafe => results
+                .iter()
+                .map(|r| &r.result)
+                .reduce(|best, r| if unsafe_score(&r.scores) > unsafe_score(&best.scores) { r } else { best })
+                .expect("at least one result")
+                .clone(),
+            EnsembleStrategy::MajorityVote => {
+                let mut votes = [0.0f32; NUM_CLASSES];
+                for (member, r) in self.members.iter().zip(&results) {
+                    votes[r.result.top_class_index] += member.weight;
+                }
+                let total: f32 = votes.iter().sum();
+                ClassificationResult::from_scores(votes.map(|v| v / total))
+            }
+        };
+
+        Ok(EnsembleResult { combined, members: results })
+    }
+
+    /// Weighted mean of the results from `skip` on (results are in member order)
+    fn weighted_average(&self, results: &[MemberResult], skip: usize) -> ClassificationResult {
+        let mut scores = [0.0f32; NUM_CLASSES];
+        let mut total_weight = 0.0;
+        for (member, r) in self.members.iter().zip(results).skip(skip) {
+            for (score, value) in scores.iter_mut().zip(r.result.scores) {
+                *score += member.weight * value;
+            }
+            total_weight += member.weight;
+        }
+        ClassificationResult::from_scores(scores.map(|s| s / total_weight))
+    }
+}
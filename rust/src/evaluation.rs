@@ -0,0 +1,697 @@
+//! Offline evaluation metrics over labeled classifications.
+//!
+//! Multi-class metrics use the model's five `CLASSES`; curve metrics treat the
+//! task as binary safe-vs-unsafe, scoring each sample by its unsafe mass
+//! (hentai + porn + sexy), the same split `ClassificationResult::is_safe` uses.
+//! Threshold tuning replays `FilterPolicy` rules on saved scores to pick per-group
+//! thresholds for a target error rate. Calibration error measures how far confidences
+//! are from observed accuracy, before and after `calibration`.
+
+use crate::config::{CLASSES, NUM_CLASSES};
+use crate::inference::{ClassificationResult, SafetyGroup};
+use crate::policy::FilterPolicy;
+
+/// Class indices counted as unsafe (hentai, porn, sexy)
+pub const UNSAFE_CLASSES: [usize; 3] = [1, 3, 4];
+
+/// Whether a class index is in the unsafe group
+pub fn is_unsafe_class(class_index: usize) -> bool {
+    UNSAFE_CLASSES.contains(&class_index)
+}
+
+/// Combined unsafe score of a set of class scores
+pub fn unsafe_score(scores: &[f32; NUM_CLASSES]) -> f32 {
+    UNSAFE_CLASSES.iter().map(|&i| scores[i]).sum()
+}
+
+/// One labeled classification
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sample {
+    /// Ground-truth class index
+    pub label: usize,
+    /// Model scores in `CLASSES` order
+    pub scores: [f32; NUM_CLASSES],
+    /// Skin-tone ratio of the image, if recorded (the policy's `min_skin_ratio` input)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub skin_ratio: Option<f32>,
+}
+
+impl Sample {
+    /// Predicted class index, exactly as the engine reports it
+    pub fn predicted(&self) -> usize {
+        ClassificationResult::from_scores(self.scores).top_class_index
+    }
+}
+
+/// Confusion matrix, rows = actual class, columns = predicted class
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ConfusionMatrix {
+    pub counts: [[u64; NUM_CLASSES]; NUM_CLASSES],
+}
+
+impl ConfusionMatrix {
+    pub fn add(&mut self, actual: usize, predicted: usize) {
+        self.counts[actual][predicted] += 1;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().flatten().sum()
+    }
+
+    /// Number of samples whose actual class is `class`
+    pub fn support(&self, class: usize) -> u64 {
+        self.counts[class].iter().sum()
+    }
+
+    /// Fraction of correct predictions
+    pub fn accuracy(&self) -> f64 {
+        let correct: u64 = (0..NUM_CLASSES).map(|c| self.counts[c][c]).sum();
+        ratio(correct, self.total())
+    }
+
+    pub fn precision(&self, class: usize) -> f64 {
+        let predicted: u64 = (0..NUM_CLASSES).map(|a| self.counts[a][class]).sum();
+        ratio(self.counts[class][class], predicted)
+    }
+
+    pub fn recall(&self, class: usize) -> f64 {
+        ratio(self.counts[class][class], self.support(class))
+    }
+
+    pub fn f1(&self, class: usize) -> f64 {
+        let (p, r) = (self.precision(class), self.recall(class));
+        if p + r == 0.0 {
+            0.0
+        } else {
+            2.0 * p * r / (p + r)
+        }
+    }
+}
+
+/// Precision / recall / F1 of one class
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ClassMetrics {
+    pub class: &'static str,
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+    pub support: u64,
+}
+
+/// Point on the unsafe-detection ROC curve
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RocPoint {
+    /// Unsafe-score threshold (samples scoring >= threshold are flagged unsafe)
+    pub threshold: f32,
+    pub false_positive_rate: f64,
+    pub true_positive_rate: f64,
+}
+
+/// Point on the unsafe-detection precision/recall curve
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PrPoint {
+    pub threshold: f32,
+    pub precision: f64,
+    pub recall: f64,
+}
+
+/// Full evaluation of a labeled set
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EvaluationReport {
+    pub samples: usize,
+    pub accuracy: f64,
+    pub confusion: ConfusionMatrix,
+    pub per_class: Vec<ClassMetrics>,
+    pub macro_f1: f64,
+    pub roc: Vec<RocPoint>,
+    /// Area under the ROC curve, `None` if the set lacks safe or unsafe samples
+    pub roc_auc: Option<f64>,
+    pub pr: Vec<PrPoint>,
+    /// Average precision (area under the PR curve), `None` without unsafe samples
+    pub pr_auc: Option<f64>,
+    /// Top-label expected calibration error over `CALIBRATION_BINS` bins
+    pub expected_calibration_error: f64,
+}
+
+impl EvaluationReport {
+    /// Compute all metrics from labeled samples
+    pub fn from_samples(samples: &[Sample]) -> Self {
+        let mut confusion = ConfusionMatrix::default();
+        for sample in samples {
+            confusion.add(sample.label, sample.predicted());
+        }
+
+        let per_class: Vec<ClassMetrics> = (0..NUM_CLASSES)
+            .map(|c| ClassMetrics {
+                class: CLASSES[c],
+                precision: confusion.precision(c),
+                recall: confusion.recall(c),
+                f1: confusion.f1(c),
+                support: confusion.support(c),
+            })
+            .collect();
+        let macro_f1 = per_class.iter().map(|m| m.f1).sum::<f64>() / NUM_CLASSES as f64;
+
+        let (roc, pr) = unsafe_curves(samples);
+        let positives = samples.iter().filter(|s| is_unsafe_class(s.label)).count();
+        let negatives = samples.len() - positives;
+
+        EvaluationReport {
+            samples: samples.len(),
+            accuracy: confusion.accuracy(),
+            confusion,
+            per_class,
+            macro_f1,
+            roc_auc: (positives > 0 && negatives > 0).then(|| roc_auc(&roc)),
+            pr_auc: (positives > 0).then(|| average_precision(&pr)),
+            roc,
+            pr,
+            expected_calibration_error: expected_calibration_error(samples, CALIBRATION_BINS),
+        }
+    }
+}
+
+/// ROC and PR curves for unsafe detection, one point per distinct score threshold
+/// Thresholds descend; the ROC curve starts at (0, 0) with an infinite threshold.
+pub fn unsafe_curves(samples: &[Sample]) -> (Vec<RocPoint>, Vec<PrPoint>) {
+    let mut scored: Vec<(f32, bool)> = samples
+        .iter()
+        .map(|s| (unsafe_score(&s.scores), is_unsafe_class(s.label)))
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let positives = scored.iter().filter(|(_, p)| *p).count() as u64;
+    let negatives = scored.len() as u64 - positives;
+
+    let mut roc = vec![RocPoint {
+        threshold: f32::INFINITY,
+        false_positive_rate: 0.0,
+        true_positive_rate: 0.0,
+    }];
+    let mut pr = Vec::new();
+    let (mut tp, mut fp) = (0u64, 0u64);
+
+    let mut i = 0;
+    while i < scored.len() {
+        let threshold = scored[i].0;
+        // Every sample tied at this score is flagged together
+        while i < scored.len() && scored[i].0 == threshold {
+            if scored[i].1 {
+                tp += 1;
+            } else {
+                fp += 1;
+            }
+            i += 1;
+        }
+        roc.push(RocPoint {
+            threshold,
+            false_positive_rate: ratio(fp, negatives),
+            true_positive_rate: ratio(tp, positives),
+        });
+        pr.push(PrPoint {
+            threshold,
+            precision: ratio(tp, tp + fp),
+            recall: ratio(tp, positives),
+        });
+    }
+
+    (roc, pr)
+}
+
+/// Trapezoidal area under a ROC curve
+pub fn roc_auc(roc: &[RocPoint]) -> f64 {
+    roc.windows(2)
+        .map(|w| {
+            (w[1].false_positive_rate - w[0].false_positive_rate)
+                * (w[1].true_positive_rate + w[0].true_positive_rate)
+                / 2.0
+        })
+        .sum()
+}
+
+/// Average precision: precision weighted by each recall increment
+pub fn average_precision(pr: &[PrPoint]) -> f64 {
+    let mut previous_recall = 0.0;
+    pr.iter()
+        .map(|p| {
+            let area = (p.recall - previous_recall) * p.precision;
+            previous_recall = p.recall;
+            area
+        })
+        .sum()
+}
+
+/// Number of equal-width confidence bins used for calibration error by default
+pub const CALIBRATION_BINS: usize = 15;
+
+/// Confidences falling into one bin of a reliability diagram
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ReliabilityBin {
+    /// Mean confidence of the bin's samples
+    pub confidence: f64,
+    /// Fraction of the bin's samples that were correct
+    pub accuracy: f64,
+    pub count: u64,
+}
+
+/// Reliability diagram of `(confidence, correct)` pairs over `bins` equal-width bins
+pub fn reliability_bins(pairs: impl IntoIterator<Item = (f32, bool)>, bins: usize) -> Vec<ReliabilityBin> {
+    let bins = bins.max(1);
+    let mut sums = vec![(0.0f64, 0u64, 0u64); bins];
+    for (confidence, correct) in pairs {
+        let index = ((confidence.clamp(0.0, 1.0) * bins as f32) as usize).min(bins - 1);
+        sums[index].0 += confidence as f64;
+        sums[index].1 += correct as u64;
+        sums[index].2 += 1;
+    }
+    sums.into_iter()
+        .map(|(confidence, correct, count)| ReliabilityBin {
+            confidence: if count == 0 { 0.0 } else { confidence / count as f64 },
+            accuracy: ratio(correct, count),
+            count,
+        })
+        .collect()
+}
+
+/// Count-weighted mean gap between confidence and accuracy of reliability bins
+pub fn calibration_error(bins: &[ReliabilityBin]) -> f64 {
+    let total: u64 = bins.iter().map(|b| b.count).sum();
+    if total == 0 {
+        return 0.0;
+    }
+    bins.iter()
+        .map(|b| b.count as f64 * (b.confidence - b.accuracy).abs())
+        .sum::<f64>()
+        / total as f64
+}
+
+/// Expected calibration error of the top class: its score against how often it was right
+pub fn expected_calibration_error(samples: &[Sample], bins: usize) -> f64 {
+    let pairs = samples.iter().map(|s| {
+        let predicted = s.predicted();
+        (s.scores[predicted], predicted == s.label)
+    });
+    calibration_error(&reliability_bins(pairs, bins))
+}
+
+/// Calibration error of one class score against how often that class was the label
+pub fn class_calibration_error(samples: &[Sample], class: usize, bins: usize) -> f64 {
+    let pairs = samples.iter().map(|s| (s.scores[class], s.label == class));
+    calibration_error(&reliability_bins(pairs, bins))
+}
+
+/// Counts of a binary flagging rule over labeled samples
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DetectionRates {
+    pub true_positives: u64,
+    pub false_positives: u64,
+    pub positives: u64,
+    pub negatives: u64,
+}
+
+impl DetectionRates {
+    pub fn false_positive_rate(&self) -> f64 {
+        ratio(self.false_positives, self.negatives)
+    }
+
+    pub fn false_negative_rate(&self) -> f64 {
+        ratio(self.positives - self.true_positives, self.positives)
+    }
+
+    pub fn precision(&self) -> f64 {
+        ratio(self.true_positives, self.true_positives + self.false_positives)
+    }
+
+    pub fn recall(&self) -> f64 {
+        ratio(self.true_positives, self.positives)
+    }
+}
+
+/// How often a policy filters unsafe samples (hits) and safe samples (false positives)
+/// Samples without a recorded skin-tone ratio skip the `min_skin_ratio` check.
+pub fn policy_rates(samples: &[Sample], policy: &FilterPolicy) -> DetectionRates {
+    let mut rates = DetectionRates::default();
+    for sample in samples {
+        let positive = is_unsafe_class(sample.label);
+        let result = ClassificationResult::from_scores(sample.scores);
+        let filtered = policy.evaluate_with_skin_ratio(&result, sample.skin_ratio).should_filter;
+        if positive {
+            rates.positives += 1;
+            rates.true_positives += filtered as u64;
+        } else {
+            rates.negatives += 1;
+            rates.false_positives += filtered as u64;
+        }
+    }
+    rates
+}
+
+/// Error budget a tuned threshold must respect
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TuningTarget {
+    /// Filter as much as possible while keeping safe images filtered at most this often
+    MaxFalsePositiveRate(f64),
+    /// Filter as little as possible while missing at most this fraction of the group
+    MaxFalseNegativeRate(f64),
+}
+
+/// Threshold chosen for one content group and its rates on the tuning set
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OperatingPoint {
+    pub threshold: f32,
+    pub rates: DetectionRates,
+    /// False when no threshold reaches the target; the closest one is returned instead
+    pub target_met: bool,
+}
+
+/// Search the policy threshold of one content group (`Adult` or `Suggestive`) for a target
+///
+/// Samples are flagged by `FilterPolicy::evaluate_with_skin_ratio` with the group's threshold
+/// swept and every other setting taken from `policy`. The other threshold matters: the
+/// catch-all flags suggestive frames from `unsafe_threshold` even when that sits below
+/// `suggestive_threshold`, so those frames count as flagged at every swept threshold.
+/// Positives are samples labeled in `group`; negatives are samples labeled safe, so other
+/// unsafe groups count as neither.
+pub fn tune_group_threshold(samples: &[Sample], policy: &FilterPolicy, group: SafetyGroup, target: TuningTarget) -> OperatingPoint {
+    let with_threshold = |threshold: f32| match group {
+        SafetyGroup::Suggestive => FilterPolicy {
+            suggestive_threshold: threshold,
+            ..policy.clone()
+        },
+        SafetyGroup::Safe | SafetyGroup::Adult => FilterPolicy {
+            unsafe_threshold: threshold,
+            ..policy.clone()
+        },
+    };
+    let (never, always) = (with_threshold(f32::INFINITY), with_threshold(0.0));
+
+    let mut rates = DetectionRates::default();
+    // (confidence, positive) of every sample flagged once the swept threshold is low enough, ascending
+    let mut flaggable: Vec<(f32, bool)> = Vec::new();
+
+    for sample in samples {
+        let label_group = SafetyGroup::of_class(sample.label);
+        let positive = label_group == group;
+        if positive {
+            rates.positives += 1;
+        } else if label_group == SafetyGroup::Safe {
+            rates.negatives += 1;
+        } else {
+            continue;
+        }
+        let result = ClassificationResult::from_scores(sample.scores);
+        if never.evaluate_with_skin_ratio(&result, sample.skin_ratio).should_filter {
+            // Flagged by another rule whatever the group's threshold
+            if positive {
+                rates.true_positives += 1;
+            } else {
+                rates.false_positives += 1;
+            }
+        } else if always.evaluate_with_skin_ratio(&result, sample.skin_ratio).should_filter {
+            flaggable.push((result.confidence.clamp(0.0, 1.0), positive));
+        }
+    }
+    flaggable.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // Candidate thresholds: every flaggable confidence, plus 1.0 to flag (almost) nothing
+    let mut candidates: Vec<f32> = flaggable.iter().map(|&(confidence, _)| confidence).collect();
+    candidates.push(1.0);
+    candidates.dedup();
+
+    // Rates at each candidate; flagged samples are the suffix of `flaggable` at or above it
+    let mut points = Vec::with_capacity(candidates.len());
+    let mut start = 0;
+    let mut flagged_positives = flaggable.iter().filter(|(_, p)| *p).count() as u64;
+    let mut flagged_negatives = flaggable.len() as u64 - flagged_positives;
+    for threshold in candidates {
+        while start < flaggable.len() && flaggable[start].0 < threshold {
+            if flaggable[start].1 {
+                flagged_positives -= 1;
+            } else {
+                flagged_negatives -= 1;
+            }
+            start += 1;
+        }
+        points.push(OperatingPoint {
+            threshold,
+            rates: DetectionRates {
+                true_positives: rates.true_positives + flagged_positives,
+                false_positives: rates.false_positives + flagged_negatives,
+                ..rates
+            },
+            target_met: false,
+        });
+    }
+
+    // Rates only fall as the threshold rises, so pick the boundary point meeting the target
+    let chosen = match target {
+        TuningTarget::MaxFalsePositiveRate(limit) => points
+            .iter()
+            .find(|p| p.rates.false_positive_rate() <= limit)
+            .map(|p| (*p, true))
+            .unwrap_or((points[points.len() - 1], false)),
+        TuningTarget::MaxFalseNegativeRate(limit) => points
+            .iter()
+            .rev()
+            .find(|p| p.rates.false_negative_rate() <= limit)
+            .map(|p| (*p, true))
+            .unwrap_or((points[0], false)),
+    };
+
+    OperatingPoint {
+        target_met: chosen.1,
+        ..chosen.0
+    }
+}
+
+fn ratio(numerator: u64, denominator: u64) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(label: usize, scores: [f32; 5]) -> Sample {
+        Sample {
+            label,
+            scores,
+            skin_ratio: None,
+        }
+    }
+
+    #[test]
+    fn test_confusion_metrics() {
+        let mut confusion = ConfusionMatrix::default();
+        confusion.add(2, 2);
+        confusion.add(2, 2);
+        confusion.add(2, 3);
+        confusion.add(3, 3);
+
+        assert_eq!(confusion.total(), 4);
+        assert_eq!(confusion.accuracy(), 0.75);
+        assert_eq!(confusion.precision(3), 0.5);
+        assert_eq!(confusion.recall(2), 2.0 / 3.0);
+        assert_eq!(confusion.recall(3), 1.0);
+        assert_eq!(confusion.f1(0), 0.0);
+    }
+
+    #[test]
+    fn test_prediction_matches_engine() {
+        // No signal at all is neutral, and non-finite scores count as 0
+        assert_eq!(sample(3, [0.0; 5]).predicted(), 2);
+        assert_eq!(sample(3, [f32::NAN, 0.0, 0.0, 0.4, 0.0]).predicted(), 3);
+    }
+
+    #[test]
+    fn test_perfect_separation() {
+        let samples = vec![
+            sample(2, [0.0, 0.0, 0.9, 0.1, 0.0]),
+            sample(0, [0.8, 0.0, 0.0, 0.2, 0.0]),
+            sample(3, [0.0, 0.0, 0.1, 0.9, 0.0]),
+            sample(4, [0.0, 0.0, 0.3, 0.0, 0.7]),
+        ];
+        let report = EvaluationReport::from_samples(&samples);
+
+        assert_eq!(report.accuracy, 1.0);
+        assert_eq!(report.roc_auc, Some(1.0));
+        assert_eq!(report.pr_auc, Some(1.0));
+        assert_eq!(report.per_class[3].support, 1);
+    }
+
+    #[test]
+    fn test_inverted_scores() {
+        let samples = vec![
+            sample(2, [0.0, 0.0, 0.1, 0.9, 0.0]),
+            sample(3, [0.0, 0.0, 0.9, 0.1, 0.0]),
+        ];
+        let report = EvaluationReport::from_samples(&samples);
+        assert_eq!(report.roc_auc, Some(0.0));
+        assert_eq!(report.pr_auc, Some(0.5));
+    }
+
+    #[test]
+    fn test_ties_share_a_threshold() {
+        let samples = vec![
+            sample(2, [0.0, 0.0, 0.5, 0.5, 0.0]),
+            sample(3, [0.0, 0.0, 0.5, 0.5, 0.0]),
+        ];
+        let (roc, pr) = unsafe_curves(&samples);
+        assert_eq!(roc.len(), 2);
+        assert_eq!(pr.len(), 1);
+        assert_eq!(roc_auc(&roc), 0.5);
+    }
+
+    #[test]
+    fn test_auc_undefined_without_both_groups() {
+        let samples = vec![sample(2, [0.0, 0.0, 1.0, 0.0, 0.0])];
+        let report = EvaluationReport::from_samples(&samples);
+        assert_eq!(report.roc_auc, None);
+        assert_eq!(report.pr_auc, None);
+    }
+
+    fn adult_tuning_set() -> Vec<Sample> {
+        vec![
+            // Porn at unsafe confidence 0.9, 0.8, 0.7
+            sample(3, [0.05, 0.0, 0.05, 0.9, 0.0]),
+            sample(3, [0.1, 0.0, 0.1, 0.8, 0.0]),
+            sample(3, [0.15, 0.0, 0.15, 0.7, 0.0]),
+            // Neutral images misread as porn at 0.75 and 0.65, one correct
+            sample(2, [0.125, 0.0, 0.125, 0.75, 0.0]),
+            sample(2, [0.175, 0.0, 0.175, 0.65, 0.0]),
+            sample(2, [0.0, 0.0, 1.0, 0.0, 0.0]),
+        ]
+    }
+
+    #[test]
+    fn test_tune_for_false_positive_rate() {
+        let samples = adult_tuning_set();
+
+        let strict = tune_group_threshold(&samples, &FilterPolicy::default(), SafetyGroup::Adult, TuningTarget::MaxFalsePositiveRate(0.0));
+        assert!(strict.target_met);
+        assert_eq!(strict.threshold, 0.8);
+        assert_eq!(strict.rates.true_positives, 2);
+
+        let loose = tune_group_threshold(&samples, &FilterPolicy::default(), SafetyGroup::Adult, TuningTarget::MaxFalsePositiveRate(0.34));
+        assert_eq!(loose.threshold, 0.7);
+        assert_eq!(loose.rates.false_positives, 1);
+        assert_eq!(loose.rates.recall(), 1.0);
+    }
+
+    #[test]
+    fn test_tune_for_false_negative_rate() {
+        let samples = adult_tuning_set();
+
+        let point = tune_group_threshold(&samples, &FilterPolicy::default(), SafetyGroup::Adult, TuningTarget::MaxFalseNegativeRate(0.0));
+        assert!(point.target_met);
+        assert_eq!(point.threshold, 0.7);
+
+        // Suggestive has no positives here: every threshold misses nothing
+        let point = tune_group_threshold(&samples, &FilterPolicy::default(), SafetyGroup::Suggestive, TuningTarget::MaxFalseNegativeRate(0.0));
+        assert_eq!(point.threshold, 1.0);
+        assert_eq!(point.rates.positives, 0);
+    }
+
+    #[test]
+    fn test_tuned_profile_reproduces_reported_rates() {
+        let samples = vec![
+            // Sexy at unsafe confidence 0.95, 0.85 and 0.7
+            sample(4, [0.025, 0.0, 0.025, 0.0, 0.95]),
+            sample(4, [0.075, 0.0, 0.075, 0.0, 0.85]),
+            sample(4, [0.15, 0.0, 0.15, 0.0, 0.7]),
+            // Neutral images misread as sexy at 0.9 and 0.65
+            sample(2, [0.05, 0.0, 0.05, 0.0, 0.9]),
+            sample(2, [0.175, 0.0, 0.175, 0.0, 0.65]),
+            sample(2, [0.0, 0.0, 1.0, 0.0, 0.0]),
+        ];
+        let base = FilterPolicy {
+            unsafe_threshold: 0.9,
+            ..FilterPolicy::default()
+        };
+        let point = tune_group_threshold(&samples, &base, SafetyGroup::Suggestive, TuningTarget::MaxFalsePositiveRate(0.34));
+        assert!(point.target_met);
+        assert_eq!(point.threshold, 0.7);
+        // The catch-all flags the neutral image at 0.9 whatever the suggestive threshold
+        assert_eq!(point.rates.false_positives, 1);
+
+        let policy = FilterPolicy {
+            suggestive_threshold: point.threshold,
+            ..base
+        };
+        assert_eq!(policy_rates(&samples, &policy), point.rates);
+
+        // With the default `unsafe_threshold` the catch-all flags both misread images
+        let point = tune_group_threshold(&samples, &FilterPolicy::default(), SafetyGroup::Suggestive, TuningTarget::MaxFalsePositiveRate(0.34));
+        assert!(!point.target_met);
+        assert_eq!(point.rates.false_positives, 2);
+    }
+
+    #[test]
+    fn test_unreachable_target_returns_closest() {
+        // The only porn sample is classified neutral, so no adult threshold can catch it
+        let samples = vec![sample(3, [0.0, 0.0, 0.9, 0.1, 0.0]), sample(3, [0.0, 0.0, 0.1, 0.9, 0.0])];
+        let point = tune_group_threshold(&samples, &FilterPolicy::default(), SafetyGroup::Adult, TuningTarget::MaxFalseNegativeRate(0.0));
+        assert!(!point.target_met);
+        assert_eq!(point.rates.recall(), 0.5);
+    }
+
+    #[test]
+    fn test_calibration_error() {
+        // Confident and always right: perfectly calibrated
+        let right = vec![sample(3, [0.0, 0.0, 0.0, 1.0, 0.0]); 4];
+        assert_eq!(expected_calibration_error(&right, CALIBRATION_BINS), 0.0);
+
+        // 0.9 confident but right half the time
+        let mut half = vec![sample(3, [0.0, 0.0, 0.1, 0.9, 0.0]); 2];
+        half.extend(vec![sample(2, [0.0, 0.0, 0.1, 0.9, 0.0]); 2]);
+        assert!((expected_calibration_error(&half, CALIBRATION_BINS) - 0.4).abs() < 1e-6);
+        assert!((class_calibration_error(&half, 2, 10) - 0.4).abs() < 1e-6);
+
+        let bins = reliability_bins([(0.05, false), (0.95, true), (1.0, true)], 10);
+        assert_eq!(bins.len(), 10);
+        assert_eq!(bins[9].count, 2);
+        assert_eq!(bins[0].accuracy, 0.0);
+    }
+
+    #[test]
+    fn test_policy_rates() {
+        let samples = adult_tuning_set();
+        let policy = FilterPolicy {
+            unsafe_threshold: 0.7,
+            ..FilterPolicy::default()
+        };
+        let rates = policy_rates(&samples, &policy);
+        assert_eq!(rates.positives, 3);
+        assert_eq!(rates.true_positives, 3);
+        assert_eq!(rates.false_positives, 1);
+    }
+
+    #[test]
+    fn test_policy_rates_use_recorded_skin_ratio() {
+        let sexy = |label, skin_ratio| Sample {
+            skin_ratio,
+            ..sample(label, [0.0, 0.0, 0.05, 0.0, 0.95])
+        };
+        let samples = vec![sexy(4, Some(0.4)), sexy(2, Some(0.01)), sexy(2, None)];
+        let policy = FilterPolicy {
+            min_skin_ratio: 0.1,
+            ..FilterPolicy::default()
+        };
+        let rates = policy_rates(&samples, &policy);
+        assert_eq!(rates.true_positives, 1);
+        // Only the sample without a recorded ratio is still filtered
+        assert_eq!(rates.false_positives, 1);
+    }
+}
@@ -2,33 +2,195 @@ use std::error::Error;
 
 use crate::config::{MODEL_INPUT_WIDTH, MODEL_INPUT_HEIGHT, MODEL_INPUT_CHANNELS};
 
+/// Largest accepted blur radius; the box kernel grows linearly with it
+pub const MAX_BLUR_RADIUS: f32 = 256.0;
+
+/// Byte length of a `width`×`height` RGBA buffer, or an error if it overflows `usize`
+fn rgba_len(width: usize, height: usize) -> Result<usize, Box<dyn Error>> {
+    width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(4))
+        .ok_or_else(|| format!("Image dimensions {}x{} overflow", width, height).into())
+}
+
+/// Memory layout of a model's image input tensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TensorLayout {
+    /// Channel-first `[1, C, H, W]` (PyTorch and tf2onnx-converted MobileNet exports)
+    Nchw,
+    /// Channel-last `[1, H, W, C]` (TensorFlow exports)
+    Nhwc,
+}
+
+impl TensorLayout {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TensorLayout::Nchw => "nchw",
+            TensorLayout::Nhwc => "nhwc",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "nchw" => Some(TensorLayout::Nchw),
+            "nhwc" => Some(TensorLayout::Nhwc),
+            _ => None,
+        }
+    }
+}
+
+/// How RGBA frames are turned into a model's input tensor
+/// Each RGB channel value `v` becomes `(v - mean) / std`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreprocessSpec {
+    pub width: usize,
+    pub height: usize,
+    pub layout: TensorLayout,
+    pub mean: [f32; MODEL_INPUT_CHANNELS],
+    pub std: [f32; MODEL_INPUT_CHANNELS],
+}
+
+impl Default for PreprocessSpec {
+    /// MobileNetV2: 224×224 NCHW, normalized to [-1, 1]
+    fn default() -> Self {
+        PreprocessSpec {
+            width: MODEL_INPUT_WIDTH,
+            height: MODEL_INPUT_HEIGHT,
+            layout: TensorLayout::Nchw,
+            mean: [127.5; MODEL_INPUT_CHANNELS],
+            std: [127.5; MODEL_INPUT_CHANNELS],
+        }
+    }
+}
+
+impl PreprocessSpec {
+    /// Input tensor shape, batch of one
+    pub fn shape(&self) -> [usize; 4] {
+        match self.layout {
+            TensorLayout::Nchw => [1, MODEL_INPUT_CHANNELS, self.height, self.width],
+            TensorLayout::Nhwc => [1, self.height, self.width, MODEL_INPUT_CHANNELS],
+        }
+    }
+
+    /// Number of values in the input tensor
+    pub fn tensor_len(&self) -> usize {
+        self.width * self.height * MODEL_INPUT_CHANNELS
+    }
+
+    /// Check the spec describes a usable input
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.width == 0 || self.height == 0 || self.width > 4096 || self.height > 4096 {
+            return Err(format!("Model input size {}x{} must be between 1 and 4096", self.width, self.height).into());
+        }
+        if self.std.iter().any(|s| !s.is_finite() || *s == 0.0) || self.mean.iter().any(|m| !m.is_finite()) {
+            return Err("Model input mean must be finite and std finite and non-zero".into());
+        }
+        Ok(())
+    }
+}
+
+/// Pixel rectangle within a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Rect { x, y, width, height }
+    }
+
+    /// The whole of a `width`×`height` frame
+    pub fn full(width: usize, height: usize) -> Self {
+        Rect::new(0, 0, width, height)
+    }
+
+    /// Check the rectangle is non-empty and lies within a `width`×`height` frame
+    pub fn check_within(&self, width: usize, height: usize) -> Result<(), Box<dyn Error>> {
+        let fits = |start: usize, len: usize, limit: usize| len > 0 && start.checked_add(len).is_some_and(|end| end <= limit);
+        if !fits(self.x, self.width, width) || !fits(self.y, self.height, height) {
+            return Err(format!(
+                "Region {}x{} at ({}, {}) is empty or outside the {}x{} frame",
+                self.width, self.height, self.x, self.y, width, height
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Copy a region out of an RGBA frame, e.g. to hand a text box to a recognizer
+pub fn crop(rgba_data: &[u8], width: usize, height: usize, region: Rect) -> Result<Vec<u8>, Box<dyn Error>> {
+    if rgba_data.len() != rgba_len(width, height)? {
+        return Err("Invalid image data size".into());
+    }
+    region.check_within(width, height)?;
+
+    let mut output = Vec::with_capacity(region.width * region.height * 4);
+    for y in region.y..region.y + region.height {
+        let start = (y * width + region.x) * 4;
+        output.extend_from_slice(&rgba_data[start..start + region.width * 4]);
+    }
+    Ok(output)
+}
+
+/// Write `patch`, an RGBA image the size of `region`, into the frame at `region`
+pub fn paste(rgba_data: &mut [u8], width: usize, height: usize, region: Rect, patch: &[u8]) -> Result<(), Box<dyn Error>> {
+    if rgba_data.len() != rgba_len(width, height)? {
+        return Err("Invalid image data size".into());
+    }
+    region.check_within(width, height)?;
+    if patch.len() != region.width * region.height * 4 {
+        return Err(format!("Patch of {} bytes does not fill a {}x{} region", patch.len(), region.width, region.height).into());
+    }
+
+    for (row, y) in (region.y..region.y + region.height).enumerate() {
+        let start = (y * width + region.x) * 4;
+        let patch_start = row * region.width * 4;
+        rgba_data[start..start + region.width * 4].copy_from_slice(&patch[patch_start..patch_start + region.width * 4]);
+    }
+    Ok(())
+}
+
 /// Preprocess RGBA image data for MobileNetV2 model inference
 /// Pipeline: RGBA → resize to 224×224 → drop alpha → normalize to [-1, 1] → NCHW layout
 /// Returns Vec<f32> of length 3 * 224 * 224 in NCHW (channel-first) order
 pub fn preprocess_for_model(rgba_data: &[u8], width: usize, height: usize) -> Result<Vec<f32>, Box<dyn Error>> {
-    if rgba_data.len() != width * height * 4 {
+    preprocess(rgba_data, width, height, &PreprocessSpec::default())
+}
+
+/// Preprocess RGBA image data into the input tensor described by `spec`
+/// Pipeline: RGBA → resize → drop alpha → normalize per channel → NCHW or NHWC layout
+pub fn preprocess(rgba_data: &[u8], width: usize, height: usize, spec: &PreprocessSpec) -> Result<Vec<f32>, Box<dyn Error>> {
+    let expected_len = rgba_len(width, height)?;
+    if rgba_data.len() != expected_len {
         return Err(format!(
             "RGBA data size mismatch: expected {}x{}x4={}, got {}",
-            width, height, width * height * 4, rgba_data.len()
+            width, height, expected_len, rgba_data.len()
         ).into());
     }
 
-    // Step 1: Resize to 224×224 (bilinear interpolation on RGBA)
-    let resized = resize_bilinear_rgba(rgba_data, width, height, MODEL_INPUT_WIDTH, MODEL_INPUT_HEIGHT);
+    // Step 1: Resize to the model input size (bilinear interpolation on RGBA)
+    let resized = resize_bilinear_rgba(rgba_data, width, height, spec.width, spec.height)?;
 
-    let pixel_count = MODEL_INPUT_WIDTH * MODEL_INPUT_HEIGHT;
-    let output_size = MODEL_INPUT_CHANNELS * pixel_count;
-    
-    // Step 2: Convert RGBA → RGB, normalize to [-1, 1], and reorder to NCHW
-    // NCHW = all R values, then all G values, then all B values
-    let mut normalized = vec![0.0f32; output_size];
+    let pixel_count = spec.width * spec.height;
+    let mut normalized = vec![0.0f32; spec.tensor_len()];
 
+    // Step 2: Convert RGBA → RGB, normalize, and lay out as the model expects
     for i in 0..pixel_count {
         let rgba_idx = i * 4;
-        // MobileNetV2 normalization: (pixel - 127.5) / 127.5
-        normalized[i] = (resized[rgba_idx] as f32 - 127.5) / 127.5;                         // R channel
-        normalized[pixel_count + i] = (resized[rgba_idx + 1] as f32 - 127.5) / 127.5;       // G channel
-        normalized[2 * pixel_count + i] = (resized[rgba_idx + 2] as f32 - 127.5) / 127.5;   // B channel
+        for c in 0..MODEL_INPUT_CHANNELS {
+            let value = (resized[rgba_idx + c] as f32 - spec.mean[c]) / spec.std[c];
+            match spec.layout {
+                // NCHW = all R values, then all G values, then all B values
+                TensorLayout::Nchw => normalized[c * pixel_count + i] = value,
+                TensorLayout::Nhwc => normalized[i * MODEL_INPUT_CHANNELS + c] = value,
+            }
+        }
         // Alpha channel dropped
     }
 
@@ -36,14 +198,22 @@ pub fn preprocess_for_model(rgba_data: &[u8], width: usize, height: usize) -> Re
 }
 
 /// Bilinear interpolation resize for RGBA images
-fn resize_bilinear_rgba(
+/// Both sizes must be non-empty and `src` must hold exactly `src_w * src_h` pixels
+pub fn resize_bilinear_rgba(
     src: &[u8],
     src_w: usize,
     src_h: usize,
     dst_w: usize,
     dst_h: usize,
-) -> Vec<u8> {
-    let mut dst = vec![0u8; dst_w * dst_h * 4];
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    if src_w == 0 || src_h == 0 || dst_w == 0 || dst_h == 0 {
+        return Err(format!("Cannot resize {}x{} to {}x{}: empty image", src_w, src_h, dst_w, dst_h).into());
+    }
+    if src.len() != rgba_len(src_w, src_h)? {
+        return Err("Invalid image data size".into());
+    }
+
+    let mut dst = vec![0u8; rgba_len(dst_w, dst_h)?];
     let x_ratio = src_w as f32 / dst_w as f32;
     let y_ratio = src_h as f32 / dst_h as f32;
 
@@ -82,14 +252,18 @@ fn resize_bilinear_rgba(
         }
     }
 
-    dst
+    Ok(dst)
 }
 
 /// Apply Gaussian blur to RGBA image
+/// `radius` must be finite and within `0..=MAX_BLUR_RADIUS`
 pub fn blur(image_data: &[u8], width: usize, height: usize, radius: f32) -> Result<Vec<u8>, Box<dyn Error>> {
-    if image_data.len() != width * height * 4 {
+    if image_data.len() != rgba_len(width, height)? {
         return Err("Invalid image data size".into());
     }
+    if !(0.0..=MAX_BLUR_RADIUS).contains(&radius) {
+        return Err(format!("Invalid blur radius {} (expected 0 to {})", radius, MAX_BLUR_RADIUS).into());
+    }
 
     // Simple box blur approximation for Gaussian blur
     // For better quality, use a proper Gaussian kernel
@@ -108,7 +282,7 @@ pub fn blur(image_data: &[u8], width: usize, height: usize, radius: f32) -> Resu
             let mut count = 0u32;
 
             for kx in 0..kernel_size {
-                let sample_x = (x as i32 + kx as i32 - half_kernel as i32).max(0).min(width as i32 - 1) as usize;
+                let sample_x = (x + kx).saturating_sub(half_kernel).min(width - 1);
                 let idx = (y * width + sample_x) * 4;
                 
                 r_sum += image_data[idx] as u32;
@@ -134,7 +308,7 @@ pub fn blur(image_data: &[u8], width: usize, height: usize, radius: f32) -> Resu
             let mut count = 0u32;
 
             for ky in 0..kernel_size {
-                let sample_y = (y as i32 + ky as i32 - half_kernel as i32).max(0).min(height as i32 - 1) as usize;
+                let sample_y = (y + ky).saturating_sub(half_kernel).min(height - 1);
                 let idx = (sample_y * width + x) * 4;
                 
                 r_sum += temp[idx] as u32;
@@ -155,30 +329,34 @@ pub fn blur(image_data: &[u8], width: usize, height: usize, radius: f32) -> Resu
 }
 
 /// Apply pixelation effect to RGBA image
+/// `block_size` must be positive
 pub fn pixelate(image_data: &[u8], width: usize, height: usize, block_size: usize) -> Result<Vec<u8>, Box<dyn Error>> {
-    if image_data.len() != width * height * 4 {
+    if image_data.len() != rgba_len(width, height)? {
         return Err("Invalid image data size".into());
     }
+    if block_size == 0 {
+        return Err("Pixelation block size must be positive".into());
+    }
 
     let mut output = image_data.to_vec();
 
     for block_y in (0..height).step_by(block_size) {
         for block_x in (0..width).step_by(block_size) {
-            // Calculate average color for this block
-            let mut r_sum = 0u32;
-            let mut g_sum = 0u32;
-            let mut b_sum = 0u32;
-            let mut count = 0u32;
+            // Calculate average color for this block (u64: a block may cover the whole frame)
+            let mut r_sum = 0u64;
+            let mut g_sum = 0u64;
+            let mut b_sum = 0u64;
+            let mut count = 0u64;
 
-            let block_end_y = (block_y + block_size).min(height);
-            let block_end_x = (block_x + block_size).min(width);
+            let block_end_y = block_y.saturating_add(block_size).min(height);
+            let block_end_x = block_x.saturating_add(block_size).min(width);
 
             for y in block_y..block_end_y {
                 for x in block_x..block_end_x {
                     let idx = (y * width + x) * 4;
-                    r_sum += image_data[idx] as u32;
-                    g_sum += image_data[idx + 1] as u32;
-                    b_sum += image_data[idx + 2] as u32;
+                    r_sum += image_data[idx] as u64;
+                    g_sum += image_data[idx + 1] as u64;
+                    b_sum += image_data[idx + 2] as u64;
                     count += 1;
                 }
             }
@@ -232,4 +410,18 @@ mod tests {
         let pixelated = result.unwrap();
         assert_eq!(pixelated.len(), image_data.len());
     }
+
+    #[test]
+    fn test_invalid_parameters_are_errors() {
+        let image_data = vec![128u8; 4 * 4 * 4];
+
+        assert!(pixelate(&image_data, 4, 4, 0).is_err());
+        assert!(blur(&image_data, 4, 4, -1.0).is_err());
+        assert!(blur(&image_data, 4, 4, f32::NAN).is_err());
+        assert!(blur(&image_data, 4, 4, f32::INFINITY).is_err());
+        assert!(resize_bilinear_rgba(&image_data, 4, 4, 0, 2).is_err());
+        assert!(resize_bilinear_rgba(&[], 0, 0, 2, 2).is_err());
+        assert!(preprocess_for_model(&[], 0, 0).is_err());
+        assert!(preprocess_for_model(&image_data, usize::MAX, 2).is_err());
+    }
 }
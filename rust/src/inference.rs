@@ -1,8 +1,15 @@
 use std::error::Error;
+use std::fs;
+use std::io::Read;
 use std::path::Path;
 use tract_onnx::prelude::*;
 
-use crate::config::{CLASSES, MODEL_INPUT_WIDTH, MODEL_INPUT_HEIGHT, MODEL_INPUT_CHANNELS};
+use crate::calibration::Calibration;
+use crate::config::{CLASSES, NUM_CLASSES};
+use crate::image::PreprocessSpec;
+use crate::integrity::{manifest_path, ModelIntegrity};
+use crate::manifest::{ModelManifest, OutputActivation};
+use crate::metadata::ModelInfo;
 
 /// Classification result with per-class scores
 #[derive(Debug, Clone)]
@@ -19,61 +26,234 @@ pub struct ClassificationResult {
     pub confidence: f32,
 }
 
+/// Coarse content group of a classification, used by the filtering policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SafetyGroup {
+    /// drawing, neutral
+    Safe,
+    /// sexy
+    Suggestive,
+    /// hentai, porn
+    Adult,
+}
+
+impl SafetyGroup {
+    /// Group of a class index in `CLASSES` order
+    pub fn of_class(class_index: usize) -> Self {
+        match class_index {
+            1 | 3 => SafetyGroup::Adult,
+            4 => SafetyGroup::Suggestive,
+            _ => SafetyGroup::Safe,
+        }
+    }
+
+    /// Group name shared with the Kotlin side
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SafetyGroup::Safe => "safe",
+            SafetyGroup::Suggestive => "suggestive",
+            SafetyGroup::Adult => "adult",
+        }
+    }
+}
+
+impl ClassificationResult {
+    /// Neutral-dominant result used as a fail-safe when classification is unavailable
+    pub fn neutral() -> Self {
+        ClassificationResult {
+            scores: [0.0, 0.0, 1.0, 0.0, 0.0],
+            top_class_index: 2,
+            top_class: CLASSES[2].to_string(),
+            is_safe: true,
+            confidence: 0.0,
+        }
+    }
+
+    /// Build a result from raw model scores in `CLASSES` order
+    /// Non-finite scores (NaN/inf) are treated as 0
+    pub fn from_scores(raw_scores: [f32; 5]) -> Self {
+        let scores = raw_scores.map(|score| if score.is_finite() { score } else { 0.0 });
+        let top_class_index = top_class_index(&scores);
+
+        // Safe classes: neutral (2) and drawing (0)
+        // Unsafe classes: hentai (1), porn (3), sexy (4)
+        let safe_score = scores[0] + scores[2]; // drawing + neutral
+        let unsafe_score = scores[1] + scores[3] + scores[4]; // hentai + porn + sexy
+        let is_safe = safe_score > unsafe_score;
+
+        let confidence = if is_safe { safe_score } else { unsafe_score };
+
+        ClassificationResult {
+            scores,
+            top_class_index,
+            top_class: CLASSES[top_class_index].to_string(),
+            is_safe,
+            confidence,
+        }
+    }
+
+    /// Safety group of the top class
+    pub fn safety_group(&self) -> SafetyGroup {
+        SafetyGroup::of_class(self.top_class_index)
+    }
+}
+
+/// Optimized, runnable tract model plan
+pub(crate) type RunnableModel = SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>;
+
 /// ML inference engine using tract (pure Rust)
+/// The optimized plan is immutable and every run allocates its own execution state,
+/// so a shared `&MLEngine` can classify from several threads in parallel without locking
 pub struct MLEngine {
-    model: SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>,
-    model_path: String,
+    model: RunnableModel,
+    info: ModelInfo,
+    /// How frames become the input tensor
+    input: PreprocessSpec,
+    /// Output index of each class in `CLASSES` order
+    class_order: [usize; NUM_CLASSES],
+    /// Post-processing of the raw output (never `Auto` once loaded)
+    activation: OutputActivation,
+    /// Number of values in the model output
+    output_len: usize,
+    /// Applied to the scores after post-processing, if set
+    calibration: Option<Calibration>,
 }
 
 impl MLEngine {
     /// Create new ML engine by loading model from file
+    /// A manifest next to the model (`model.manifest`) declares its labels and activation.
     pub fn new(model_path: &str) -> Result<Self, Box<dyn Error>> {
         if !Path::new(model_path).exists() {
             return Err(format!("Model file not found: {}", model_path).into());
         }
 
-        log::info!("Loading ML model from: {}", model_path);
-
         // Try loading based on file extension
-        let model = if model_path.ends_with(".tflite") {
-            Self::load_tflite(model_path)?
-        } else if model_path.ends_with(".onnx") {
-            Self::load_onnx(model_path)?
-        } else {
+        if model_path.ends_with(".tflite") {
+            return Self::load_tflite(model_path);
+        } else if !model_path.ends_with(".onnx") {
             return Err(format!("Unsupported model format: {}", model_path).into());
+        }
+
+        let bytes = fs::read(model_path).map_err(|e| format!("Cannot read model file {}: {}", model_path, e))?;
+        let manifest = read_manifest_file(model_path)?;
+        Self::from_bytes_with_manifest(&bytes, manifest.as_deref(), model_path)
+    }
+
+    /// Load an ONNX model after checking it against `integrity`
+    /// The file is read once and verified before tract parses those same bytes. A failed
+    /// check returns an `IntegrityError` (`downcast_ref` it to distinguish tampering).
+    pub fn new_verified(model_path: &str, integrity: &ModelIntegrity) -> Result<Self, Box<dyn Error>> {
+        if !model_path.ends_with(".onnx") {
+            return Err(format!("Unsupported model format: {}", model_path).into());
+        }
+        let bytes = fs::read(model_path).map_err(|e| format!("Cannot read model file {}: {}", model_path, e))?;
+        let manifest = read_manifest_file(model_path)?;
+
+        Self::from_bytes_verified(&bytes, manifest.as_deref(), integrity, model_path)
+    }
+
+    /// Load an ONNX model from memory, e.g. straight from packaged assets or after
+    /// in-memory decryption; `source` names the model in logs and `model_path()`
+    pub fn from_bytes(bytes: &[u8], source: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_bytes_with_manifest(bytes, None, source)
+    }
+
+    /// Load an ONNX model from memory together with its manifest, if it has one
+    pub fn from_bytes_with_manifest(bytes: &[u8], manifest: Option<&[u8]>, source: &str) -> Result<Self, Box<dyn Error>> {
+        let manifest = match manifest {
+            Some(manifest) => ModelManifest::from_bytes(manifest)?,
+            None => ModelManifest::default(),
         };
 
-        log::info!("ML model loaded successfully");
+        log::info!("Loading ML model: {} ({} bytes)", source, bytes.len());
+        let engine = Self::load_onnx_bytes(bytes, manifest, source)?;
+        log::info!(
+            "ML model loaded successfully ({} nodes, activation {}, sha256 {})",
+            engine.info.node_count,
+            engine.activation,
+            engine.info.sha256
+        );
 
-        Ok(MLEngine {
-            model,
-            model_path: model_path.to_string(),
-        })
+        Ok(engine)
+    }
+
+    /// Load an ONNX model from memory after checking it and its manifest against `integrity`
+    pub fn from_bytes_verified(
+        bytes: &[u8],
+        manifest: Option<&[u8]>,
+        integrity: &ModelIntegrity,
+        source: &str,
+    ) -> Result<Self, Box<dyn Error>> {
+        integrity.verify(bytes, manifest)?;
+        log::info!("Model integrity verified: {}", source);
+        Self::from_bytes_with_manifest(bytes, manifest, source)
+    }
+
+    /// Load an ONNX model from a stream, read to the end before parsing
+    pub fn from_reader(mut reader: impl Read, source: &str) -> Result<Self, Box<dyn Error>> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Cannot read model {}: {}", source, e))?;
+        Self::from_bytes(&bytes, source)
     }
 
     /// Load a TFLite model using tract
-    fn load_tflite(_model_path: &str) -> Result<SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>, Box<dyn Error>> {
+    fn load_tflite(_model_path: &str) -> Result<Self, Box<dyn Error>> {
         // tract doesn't directly support tflite — we need to convert to ONNX first
         // For Phase 1, we'll use ONNX format
         Err("TFLite models must first be converted to ONNX format. Use: python -m tf2onnx.convert --tflite model.tflite --output model.onnx".into())
     }
 
-    /// Load an ONNX model using tract
-    /// Model expects NCHW input: [1, 3, 224, 224]
-    fn load_onnx(model_path: &str) -> Result<SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>, Box<dyn Error>> {
+    /// Load an ONNX model from memory, check its output against the manifest and describe it
+    /// The input fact comes from the manifest, by default NCHW [1, 3, 224, 224].
+    fn load_onnx_bytes(bytes: &[u8], manifest: ModelManifest, source: &str) -> Result<Self, Box<dyn Error>> {
+        if bytes.is_empty() {
+            return Err("Model data is empty".into());
+        }
         let model = tract_onnx::onnx()
-            .model_for_path(model_path)?
-            .with_input_fact(0, f32::fact([1, MODEL_INPUT_CHANNELS, MODEL_INPUT_HEIGHT, MODEL_INPUT_WIDTH]).into())?
-            .into_optimized()?
-            .into_runnable()?;
-        
-        Ok(model)
+            .model_for_read(&mut &bytes[..])?
+            .with_input_fact(0, f32::fact(manifest.input.shape()).into())?;
+        let source_node_count = model.nodes().len();
+        let model = model.into_optimized()?.into_runnable()?;
+
+        // Probe with a mid-grey frame, preprocessed as real frames are: the output must have one
+        // value per label, and its range tells probabilities from logits for `Auto`.
+        // Logits near zero can look like probabilities, so black and white frames probe too.
+        let probe = run_model(&model, &manifest.input, &probe_frame(&manifest.input, 128)?)?;
+        let mut probes = probe.clone();
+        for level in [0, 255] {
+            probes.extend(run_model(&model, &manifest.input, &probe_frame(&manifest.input, level)?)?);
+        }
+        if probe.len() != manifest.labels.len() {
+            return Err(format!(
+                "Model output has {} values but {} labels are declared ({}); not a compatible classifier",
+                probe.len(),
+                manifest.labels.len(),
+                manifest.labels.join(", ")
+            )
+            .into());
+        }
+        let activation = manifest.activation.resolve(&probes);
+        let class_order = manifest.class_order()?;
+
+        let info = ModelInfo::collect(source, bytes, source_node_count, model.model(), manifest.labels, activation)?;
+        Ok(MLEngine {
+            model,
+            info,
+            input: manifest.input,
+            class_order,
+            activation,
+            output_len: probe.len(),
+            calibration: None,
+        })
     }
 
     /// Classify preprocessed image data
-    /// Input: already preprocessed float32 tensor in NCHW format [1, 3, 224, 224] normalized to [-1, 1]
+    /// Input: float32 tensor as described by `preprocess_spec()`, by default NCHW [1, 3, 224, 224]
+    /// normalized to [-1, 1]
     pub fn classify_preprocessed(&self, input_tensor: &[f32]) -> Result<ClassificationResult, Box<dyn Error>> {
-        let expected_size = MODEL_INPUT_WIDTH * MODEL_INPUT_HEIGHT * MODEL_INPUT_CHANNELS;
+        let expected_size = self.input.tensor_len();
         if input_tensor.len() != expected_size {
             return Err(format!(
                 "Input tensor size mismatch: expected {}, got {}",
@@ -82,54 +262,24 @@ impl MLEngine {
             ).into());
         }
 
-        // Create tract tensor in NCHW format [1, 3, 224, 224]
-        let tensor: Tensor = tract_ndarray::Array4::from_shape_vec(
-            (1, MODEL_INPUT_CHANNELS, MODEL_INPUT_HEIGHT, MODEL_INPUT_WIDTH),
-            input_tensor.to_vec(),
-        )?.into();
-
-        // Run inference
-        let result = self.model.run(tvec!(tensor.into()))?;
-        
-        // Extract output scores
-        let output = result[0].to_array_view::<f32>()?;
-        let scores_slice = output.as_slice().unwrap();
-        
-        // Parse into 5-class scores
-        let mut scores = [0.0f32; 5];
-        for (i, &score) in scores_slice.iter().take(5).enumerate() {
-            scores[i] = score;
+        let mut output = run_model(&self.model, &self.input, input_tensor)?;
+        if output.len() != self.output_len {
+            return Err(format!("Model output size changed: expected {}, got {}", self.output_len, output.len()).into());
         }
 
-        // Find top class
-        let top_class_index = scores
-            .iter()
-            .enumerate()
-            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-            .map(|(i, _)| i)
-            .unwrap_or(2); // default to "neutral"
-
-        // Safe classes: neutral (2) and drawing (0)
-        // Unsafe classes: hentai (1), porn (3), sexy (4)
-        let safe_score = scores[0] + scores[2]; // drawing + neutral
-        let unsafe_score = scores[1] + scores[3] + scores[4]; // hentai + porn + sexy
-        let is_safe = safe_score > unsafe_score;
-        
-        let confidence = if is_safe { safe_score } else { unsafe_score };
-
-        let result = ClassificationResult {
-            scores,
-            top_class_index,
-            top_class: CLASSES[top_class_index].to_string(),
-            is_safe,
-            confidence,
-        };
+        // Post-process to probabilities, reorder into `CLASSES` order, then calibrate
+        self.activation.apply(&mut output);
+        let mut scores = self.class_order.map(|index| output[index]);
+        if let Some(calibration) = &self.calibration {
+            scores = calibration.apply(scores);
+        }
+        let result = ClassificationResult::from_scores(scores);
 
         log::debug!(
             "Classification: {} (confidence: {:.3}), scores: d={:.3} h={:.3} n={:.3} p={:.3} s={:.3}",
             result.top_class,
             result.confidence,
-            scores[0], scores[1], scores[2], scores[3], scores[4]
+            result.scores[0], result.scores[1], result.scores[2], result.scores[3], result.scores[4]
         );
 
         Ok(result)
@@ -137,16 +287,74 @@ impl MLEngine {
 
     /// Classify raw RGBA image data (handles full preprocessing pipeline)
     pub fn classify(&self, rgba_data: &[u8], width: usize, height: usize) -> Result<ClassificationResult, Box<dyn Error>> {
-        let preprocessed = crate::image::preprocess_for_model(rgba_data, width, height)?;
+        let preprocessed = crate::image::preprocess(rgba_data, width, height, &self.input)?;
         self.classify_preprocessed(&preprocessed)
     }
 
-    /// Get model path
+    /// Get model path (or the source name of a model loaded from memory)
     pub fn model_path(&self) -> &str {
-        &self.model_path
+        &self.info.source
+    }
+
+    /// Input size, layout and normalization frames are preprocessed with
+    pub fn preprocess_spec(&self) -> &PreprocessSpec {
+        &self.input
+    }
+
+    /// Shapes, labels, hash and graph statistics of the loaded model
+    pub fn info(&self) -> &ModelInfo {
+        &self.info
+    }
+
+    /// Calibrate every classification's scores (see `calibration`)
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        log::info!("Score calibration enabled (temperature {})", calibration.temperature);
+        self.calibration = Some(calibration);
+        self
+    }
+
+    pub fn calibration(&self) -> Option<&Calibration> {
+        self.calibration.as_ref()
+    }
+}
+
+/// Run the model on a tensor shaped as `input` describes and return its first output
+fn run_model(model: &RunnableModel, input: &PreprocessSpec, input_tensor: &[f32]) -> Result<Vec<f32>, Box<dyn Error>> {
+    let [n, a, b, c] = input.shape();
+    let tensor: Tensor = tract_ndarray::Array4::from_shape_vec((n, a, b, c), input_tensor.to_vec())?.into();
+
+    let result = model.run(tvec!(tensor.into()))?;
+    let output = result.first().ok_or("Model produced no outputs")?.to_array_view::<f32>()?;
+    Ok(output.iter().copied().collect())
+}
+
+/// Preprocessed solid frame of grey level `level` at the model's input size
+fn probe_frame(input: &PreprocessSpec, level: u8) -> Result<Vec<f32>, Box<dyn Error>> {
+    let frame = [level, level, level, 255].repeat(input.width * input.height);
+    crate::image::preprocess(&frame, input.width, input.height, input)
+}
+
+/// Manifest next to a model file, if there is one
+fn read_manifest_file(model_path: &str) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    match fs::read(manifest_path(Path::new(model_path))) {
+        Ok(manifest) => Ok(Some(manifest)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Cannot read model manifest: {}", e).into()),
     }
 }
 
+/// Index of the highest score, first index winning ties
+/// Falls back to "neutral" when every score is zero (e.g. all outputs were NaN)
+fn top_class_index(scores: &[f32; 5]) -> usize {
+    if scores.iter().all(|&s| s == 0.0) {
+        return 2;
+    }
+    scores
+        .iter()
+        .enumerate()
+        .fold(0, |best, (i, &s)| if s.total_cmp(&scores[best]).is_gt() { i } else { best })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -184,5 +392,38 @@ mod tests {
         };
         assert!(!result.is_safe);
         assert_eq!(result.top_class, "porn");
+        assert_eq!(result.safety_group(), SafetyGroup::Adult);
+    }
+
+    #[test]
+    fn test_engine_is_shareable() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<MLEngine>();
+    }
+
+    #[test]
+    fn test_top_class_index() {
+        assert_eq!(top_class_index(&[0.1, 0.05, 0.8, 0.03, 0.02]), 2);
+        assert_eq!(top_class_index(&[0.0, 0.0, 0.0, 0.9, 0.0]), 3);
+        // Ties resolve to the first index, all-zero scores to neutral
+        assert_eq!(top_class_index(&[0.5, 0.5, 0.0, 0.0, 0.0]), 0);
+        assert_eq!(top_class_index(&[0.0; 5]), 2);
+    }
+
+    #[test]
+    fn test_neutral_result() {
+        let result = ClassificationResult::neutral();
+        assert!(result.is_safe);
+        assert_eq!(result.top_class, "neutral");
+        assert_eq!(result.safety_group(), SafetyGroup::Safe);
+    }
+
+    #[test]
+    fn test_from_scores() {
+        let result = ClassificationResult::from_scores([0.05, f32::NAN, 0.1, 0.7, 0.15]);
+        assert_eq!(result.scores[1], 0.0);
+        assert_eq!(result.top_class, "porn");
+        assert!(!result.is_safe);
+        assert!((result.confidence - 0.85).abs() < 1e-6);
     }
 }
@@ -0,0 +1,198 @@
+//! Model integrity verification.
+//!
+//! A replaced model file could silently disable filtering, so the loader can check the
+//! model bytes before tract parses them: either against a pinned SHA-256 digest, or
+//! against an Ed25519 signature by the model publisher over the model and its manifest.
+//! The manifest decides label order, so both forms cover it whenever one is loaded.
+//! A failed check is reported as an `IntegrityError`, distinct from ordinary load
+//! errors, so the app can tell tampering apart from a missing file and enter safe mode.
+
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+/// Domain separator prefixed to the signed message, so a model signature cannot be
+/// replayed as a signature over anything else
+pub const SIGNATURE_CONTEXT: &[u8] = b"pavlova-model-v1";
+
+/// Expected identity of a model file
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModelIntegrity {
+    /// SHA-256 digest of the model file, or of the model and its manifest (see `pinned_digest`)
+    Sha256([u8; 32]),
+    /// Publisher signature over the model and its manifest (see `signed_message`)
+    Ed25519 { public_key: Box<VerifyingKey>, signature: Signature },
+}
+
+/// Model bytes did not match the expected digest or signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityError {
+    DigestMismatch { expected: String, actual: String },
+    /// A manifest was loaded but the pinned digest only covers the model
+    ManifestNotPinned,
+    SignatureInvalid,
+}
+
+impl fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityError::DigestMismatch { expected, actual } => {
+                write!(f, "Model integrity check failed: SHA-256 {} does not match expected {}", actual, expected)
+            }
+            IntegrityError::ManifestNotPinned => {
+                write!(f, "Model integrity check failed: the pinned SHA-256 digest does not cover the model manifest")
+            }
+            IntegrityError::SignatureInvalid => write!(f, "Model integrity check failed: invalid Ed25519 signature"),
+        }
+    }
+}
+
+impl Error for IntegrityError {}
+
+impl ModelIntegrity {
+    /// Pin a SHA-256 digest given as 64 hex digits
+    pub fn sha256_hex(digest: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(ModelIntegrity::Sha256(parse_hex(digest)?))
+    }
+
+    /// Publisher key and signature given as raw bytes
+    pub fn ed25519(public_key: &[u8], signature: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let public_key: [u8; 32] = public_key
+            .try_into()
+            .map_err(|_| format!("Ed25519 public key must be 32 bytes, got {}", public_key.len()))?;
+        let signature: [u8; 64] = signature
+            .try_into()
+            .map_err(|_| format!("Ed25519 signature must be 64 bytes, got {}", signature.len()))?;
+        Ok(ModelIntegrity::Ed25519 {
+            public_key: Box::new(
+                VerifyingKey::from_bytes(&public_key).map_err(|e| format!("Invalid Ed25519 public key: {}", e))?,
+            ),
+            signature: Signature::from_bytes(&signature),
+        })
+    }
+
+    /// Publisher key and signature given as hex digits
+    pub fn ed25519_hex(public_key: &str, signature: &str) -> Result<Self, Box<dyn Error>> {
+        let public_key: [u8; 32] = parse_hex(public_key)?;
+        let signature: [u8; 64] = parse_hex(signature)?;
+        Self::ed25519(&public_key, &signature)
+    }
+
+    /// Check model bytes and manifest against the expectation
+    /// A missing manifest is signed as empty. A model-only digest is rejected alongside a
+    /// manifest, which could otherwise reorder the labels under a passing pin.
+    pub fn verify(&self, model: &[u8], manifest: Option<&[u8]>) -> Result<(), IntegrityError> {
+        match self {
+            ModelIntegrity::Sha256(expected) => {
+                let actual = pinned_digest(model, manifest);
+                if actual == *expected {
+                    Ok(())
+                } else if manifest.is_some() && Sha256::digest(model).as_slice() == expected {
+                    Err(IntegrityError::ManifestNotPinned)
+                } else {
+                    Err(IntegrityError::DigestMismatch {
+                        expected: to_hex(expected),
+                        actual: to_hex(&actual),
+                    })
+                }
+            }
+            ModelIntegrity::Ed25519 { public_key, signature } => public_key
+                .verify(&signed_message(model, manifest), signature)
+                .map_err(|_| IntegrityError::SignatureInvalid),
+        }
+    }
+}
+
+/// Message the publisher signs: `SIGNATURE_CONTEXT || sha256(model) || sha256(manifest)`
+pub fn signed_message(model: &[u8], manifest: Option<&[u8]>) -> Vec<u8> {
+    let mut message = SIGNATURE_CONTEXT.to_vec();
+    message.extend_from_slice(&Sha256::digest(model));
+    message.extend_from_slice(&Sha256::digest(manifest.unwrap_or_default()));
+    message
+}
+
+/// Digest a SHA-256 pin must match: `sha256(model)` without a manifest, otherwise
+/// `sha256(signed_message(model, manifest))` so the pin covers the manifest too
+pub fn pinned_digest(model: &[u8], manifest: Option<&[u8]>) -> [u8; 32] {
+    match manifest {
+        Some(_) => Sha256::digest(signed_message(model, manifest)).into(),
+        None => Sha256::digest(model).into(),
+    }
+}
+
+/// Lowercase hex of `pinned_digest`, the value to pin for a model and its manifest
+pub fn pinned_sha256_hex(model: &[u8], manifest: Option<&[u8]>) -> String {
+    to_hex(&pinned_digest(model, manifest))
+}
+
+/// Manifest file that accompanies a model: the model path with a `.manifest` extension
+pub fn manifest_path(model_path: &Path) -> PathBuf {
+    model_path.with_extension("manifest")
+}
+
+/// Lowercase hex SHA-256 of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn parse_hex<const N: usize>(text: &str) -> Result<[u8; N], Box<dyn Error>> {
+    let text = text.trim();
+    if text.len() != N * 2 || !text.is_ascii() {
+        return Err(format!("Expected {} hex digits, got '{}'", N * 2, text).into());
+    }
+    let mut bytes = [0u8; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).map_err(|_| format!("Invalid hex digits in '{}'", text))?;
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    #[test]
+    fn test_sha256_pin() {
+        // SHA-256 of "abc"
+        let pinned = ModelIntegrity::sha256_hex("BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD").unwrap();
+        assert!(pinned.verify(b"abc", None).is_ok());
+        assert!(matches!(pinned.verify(b"abd", None), Err(IntegrityError::DigestMismatch { .. })));
+
+        assert!(ModelIntegrity::sha256_hex("ba7816bf").is_err());
+        assert!(ModelIntegrity::sha256_hex(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_sha256_pin_covers_manifest() {
+        let model_only = ModelIntegrity::sha256_hex(&sha256_hex(b"model")).unwrap();
+        assert_eq!(model_only.verify(b"model", Some(b"labels")), Err(IntegrityError::ManifestNotPinned));
+
+        let bundle = ModelIntegrity::sha256_hex(&pinned_sha256_hex(b"model", Some(b"labels"))).unwrap();
+        assert!(bundle.verify(b"model", Some(b"labels")).is_ok());
+        assert!(matches!(bundle.verify(b"model", Some(b"other")), Err(IntegrityError::DigestMismatch { .. })));
+        // Dropping the manifest falls back to default labels, which the pin did not cover
+        assert!(matches!(bundle.verify(b"model", None), Err(IntegrityError::DigestMismatch { .. })));
+    }
+
+    #[test]
+    fn test_ed25519_signature_covers_model_and_manifest() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let signature = key.sign(&signed_message(b"model", Some(b"labels")));
+        let integrity = ModelIntegrity::ed25519(key.verifying_key().as_bytes(), &signature.to_bytes()).unwrap();
+
+        assert!(integrity.verify(b"model", Some(b"labels")).is_ok());
+        assert_eq!(integrity.verify(b"modex", Some(b"labels")), Err(IntegrityError::SignatureInvalid));
+        assert_eq!(integrity.verify(b"model", Some(b"other")), Err(IntegrityError::SignatureInvalid));
+        assert_eq!(integrity.verify(b"model", None), Err(IntegrityError::SignatureInvalid));
+
+        assert!(ModelIntegrity::ed25519(&[0u8; 31], &[0u8; 64]).is_err());
+    }
+}
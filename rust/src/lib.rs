@@ -1,206 +1,856 @@
-mod config;
-mod image;
+pub mod analysis;
+pub mod calibration;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod cascade;
+pub mod config;
+pub mod ensemble;
+pub mod evaluation;
+pub mod image;
 pub mod inference;
-mod utils;
+pub mod integrity;
+pub mod manifest;
+pub mod metadata;
+pub mod phash;
+pub mod pipeline;
+pub mod policy;
+pub mod registry;
+pub mod smoothing;
+pub mod swap;
+pub mod text;
+pub mod utils;
 
 // --- Android / JNI entry points (feature-gated) ---
 #[cfg(feature = "android")]
 mod android {
     use jni::JNIEnv;
-    use jni::objects::{JClass, JString, JByteArray};
-    use jni::sys::{jboolean, jbyteArray, jfloatArray, jint};
+    use jni::objects::{JClass, JString, JByteArray, JObject, JObjectArray, JValue};
+    use jni::sys::{jboolean, jbyteArray, jfloatArray, jint, jlong, jobject};
     use log::{info, error};
-    use std::sync::Mutex;
+    use std::error::Error;
+    use std::fs::File;
+    use std::mem::ManuallyDrop;
+    use std::os::fd::FromRawFd;
+    use std::os::unix::fs::FileExt;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, RwLock};
 
     use crate::image::{blur, pixelate};
-    use crate::inference::MLEngine;
+    use crate::inference::{ClassificationResult, MLEngine};
+    use crate::integrity::{IntegrityError, ModelIntegrity};
+    use crate::metadata::ModelInfo;
+    use crate::phash::{image_hash, OverrideList, OverrideVerdict};
+    use crate::pipeline::{process_frame_with_overrides, FrameOutcome};
+    use crate::policy::{FilterAction, FilterPolicy, PolicyDecision};
+    use crate::registry::{Registry, INVALID_HANDLE};
+    use crate::swap::SwappableEngine;
+    use crate::utils::panic_message;
 
-    // Global ML engine instance
+    // Loaded engines, addressed from Kotlin by the handle returned from nativeInit
     lazy_static::lazy_static! {
-        static ref ML_ENGINE: Mutex<Option<MLEngine>> = Mutex::new(None);
+        static ref ENGINES: Registry<SwappableEngine> = Registry::new();
     }
 
-/// Initialize the ML engine with a model file
-#[no_mangle]
-pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeInit(
-    mut env: JNIEnv,
-    _class: JClass,
-    model_path: JString,
-) -> jboolean {
-    // Initialize Android logger
+    // Allow/block list of known images, shared by all engines, and the file it is saved to
+    lazy_static::lazy_static! {
+        static ref OVERRIDES: RwLock<(Option<PathBuf>, Arc<OverrideList>)> = RwLock::new((None, Arc::default()));
+    }
+
+/// Look up the live engine behind a Kotlin handle
+/// The returned reference stays valid across a model swap until the caller drops it.
+fn engine(handle: jlong) -> Result<Arc<MLEngine>, String> {
+    swappable(handle).map(|engine| engine.current())
+}
+
+fn swappable(handle: jlong) -> Result<Arc<SwappableEngine>, String> {
+    ENGINES
+        .get(handle as u64)
+        .ok_or_else(|| format!("ML engine not initialized (handle {})", handle))
+}
+
+/// Run the body of an exported function, converting panics and JNI errors into a Java
+/// exception and returning `fallback` instead of unwinding into the JVM
+fn guarded<'local, T>(
+    env: &mut JNIEnv<'local>,
+    name: &str,
+    fallback: T,
+    body: impl FnOnce(&mut JNIEnv<'local>) -> jni::errors::Result<T>,
+) -> T {
+    let message = match panic::catch_unwind(AssertUnwindSafe(|| body(env))) {
+        Ok(Ok(value)) => return value,
+        // A Java exception is already pending and will be raised on return
+        Ok(Err(jni::errors::Error::JavaException)) => return fallback,
+        Ok(Err(e)) => format!("{}: JNI error: {}", name, e),
+        Err(payload) => format!("{}: native panic: {}", name, panic_message(payload.as_ref())),
+    };
+
+    error!("{}", message);
+    if !env.exception_check().unwrap_or(true) {
+        let _ = env.throw_new("java/lang/RuntimeException", &message);
+    }
+    fallback
+}
+
+/// Initialize the Android logger (idempotent)
+fn init_logging() {
     android_logger::init_once(
         android_logger::Config::default()
             .with_max_level(log::LevelFilter::Debug)
             .with_tag("PavlovaRust")
     );
+}
+
+/// Initialize an ML engine with a model file
+/// Returns the engine handle, or 0 on failure
+#[no_mangle]
+pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeInit(
+    mut env: JNIEnv,
+    _class: JClass,
+    model_path: JString,
+) -> jlong {
+    guarded(&mut env, "nativeInit", INVALID_HANDLE as jlong, |env| {
+        init_logging();
+        info!("Initializing ML engine...");
+
+        let model_path_str: String = match env.get_string(&model_path) {
+            Ok(path) => path.into(),
+            Err(e) => {
+                error!("Failed to get model path: {:?}", e);
+                return Ok(INVALID_HANDLE as jlong);
+            }
+        };
+
+        let loaded = MLEngine::new(&model_path_str);
+        register_engine(env, loaded)
+    })
+}
 
-    info!("Initializing ML engine...");
+/// Integrity expectation from nullable Java arguments: either `sha256_hex` (of the model, or
+/// of model and manifest when there is one), or `public_key` and `signature` (Ed25519 over
+/// model and manifest); `None` when all are null
+/// Malformed values, or a digest together with a key or signature, throw `IllegalArgumentException`.
+fn integrity_arg(
+    env: &mut JNIEnv,
+    sha256_hex: &JString,
+    public_key: &JByteArray,
+    signature: &JByteArray,
+) -> jni::errors::Result<Option<ModelIntegrity>> {
+    let integrity = if !sha256_hex.is_null() && (!public_key.is_null() || !signature.is_null()) {
+        Err("Pass either a SHA-256 digest or an Ed25519 public key and signature, not both".into())
+    } else if !sha256_hex.is_null() {
+        let digest: String = env.get_string(sha256_hex)?.into();
+        ModelIntegrity::sha256_hex(&digest)
+    } else if !public_key.is_null() && !signature.is_null() {
+        let key = env.convert_byte_array(public_key)?;
+        let signature = env.convert_byte_array(signature)?;
+        ModelIntegrity::ed25519(&key, &signature)
+    } else if public_key.is_null() && signature.is_null() {
+        return Ok(None);
+    } else {
+        Err("An Ed25519 public key needs a signature and vice versa".into())
+    };
 
-    let model_path_str: String = match env.get_string(&model_path) {
-        Ok(path) => path.into(),
+    match integrity {
+        Ok(integrity) => Ok(Some(integrity)),
         Err(e) => {
-            error!("Failed to get model path: {:?}", e);
-            return false as jboolean;
+            env.throw_new("java/lang/IllegalArgumentException", e.to_string())?;
+            Err(jni::errors::Error::JavaException)
         }
-    };
+    }
+}
 
-    match MLEngine::new(&model_path_str) {
+/// Register a freshly loaded engine and return its handle
+/// A failed integrity check throws `SecurityException`; other load errors return 0.
+fn register_engine(env: &mut JNIEnv, loaded: Result<MLEngine, Box<dyn Error>>) -> jni::errors::Result<jlong> {
+    match loaded {
         Ok(engine) => {
-            let mut ml_engine = ML_ENGINE.lock().unwrap();
-            *ml_engine = Some(engine);
-            info!("ML engine initialized successfully");
-            true as jboolean
+            let handle = ENGINES.insert(SwappableEngine::new(engine));
+            info!("ML engine initialized successfully (handle {})", handle);
+            Ok(handle as jlong)
+        }
+        Err(e) if e.is::<IntegrityError>() => {
+            error!("{}", e);
+            env.throw_new("java/lang/SecurityException", e.to_string())?;
+            Ok(INVALID_HANDLE as jlong)
         }
         Err(e) => {
             error!("Failed to initialize ML engine: {:?}", e);
-            false as jboolean
+            Ok(INVALID_HANDLE as jlong)
         }
     }
 }
 
+/// Load from memory, verifying first when an integrity expectation is given
+fn load_bytes(bytes: &[u8], manifest: Option<&[u8]>, integrity: Option<ModelIntegrity>, source: &str) -> Result<MLEngine, Box<dyn Error>> {
+    match integrity {
+        Some(integrity) => MLEngine::from_bytes_verified(bytes, manifest, &integrity, source),
+        None => MLEngine::from_bytes_with_manifest(bytes, manifest, source),
+    }
+}
+
+/// Optional byte[] argument
+fn optional_bytes(env: &mut JNIEnv, array: &JByteArray) -> jni::errors::Result<Option<Vec<u8>>> {
+    if array.is_null() {
+        Ok(None)
+    } else {
+        env.convert_byte_array(array).map(Some)
+    }
+}
+
+/// Read `length` bytes at `offset` from a file descriptor that the caller keeps owning,
+/// e.g. the `AssetFileDescriptor` of an uncompressed APK asset
+fn read_fd_range(fd: jint, offset: jlong, length: jlong) -> Result<Vec<u8>, Box<dyn Error>> {
+    if fd < 0 || offset < 0 || length <= 0 {
+        return Err(format!("Invalid model descriptor range: fd {} offset {} length {}", fd, offset, length).into());
+    }
+    // SAFETY: the descriptor is open for the duration of the call and never closed here
+    let file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+    let end = (offset as u64).saturating_add(length as u64);
+    if end > file.metadata()?.len() {
+        return Err(format!("Model range {}+{} exceeds the file size", offset, length).into());
+    }
+    let mut bytes = vec![0u8; length as usize];
+    file.read_exact_at(&mut bytes, offset as u64)?;
+    Ok(bytes)
+}
+
+/// Initialize an ML engine after verifying the model file
+/// Pass either `sha256_hex`, or `public_key` and `signature` (Ed25519 over model and manifest).
+/// Returns the engine handle, or 0 on failure; throws `SecurityException` if the check fails.
+#[no_mangle]
+pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeInitVerified<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    model_path: JString<'local>,
+    sha256_hex: JString<'local>,
+    public_key: JByteArray<'local>,
+    signature: JByteArray<'local>,
+) -> jlong {
+    guarded(&mut env, "nativeInitVerified", INVALID_HANDLE as jlong, |env| {
+        init_logging();
+        info!("Initializing ML engine with integrity check...");
+
+        let model_path_str: String = env.get_string(&model_path)?.into();
+        let Some(integrity) = integrity_arg(env, &sha256_hex, &public_key, &signature)? else {
+            env.throw_new(
+                "java/lang/IllegalArgumentException",
+                "Either a SHA-256 digest or an Ed25519 key and signature is required",
+            )?;
+            return Err(jni::errors::Error::JavaException);
+        };
+
+        let loaded = MLEngine::new_verified(&model_path_str, &integrity);
+        register_engine(env, loaded)
+    })
+}
+
+/// Initialize an ML engine from model bytes (e.g. an asset read or decrypted in memory)
+/// Integrity arguments are optional, as in `nativeInitVerified`; `manifest` may be null.
+/// Returns the engine handle, or 0 on failure; throws `SecurityException` if the check fails.
+#[no_mangle]
+pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeInitFromBytes<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    model_bytes: JByteArray<'local>,
+    manifest: JByteArray<'local>,
+    sha256_hex: JString<'local>,
+    public_key: JByteArray<'local>,
+    signature: JByteArray<'local>,
+) -> jlong {
+    guarded(&mut env, "nativeInitFromBytes", INVALID_HANDLE as jlong, |env| {
+        init_logging();
+        info!("Initializing ML engine from memory...");
+
+        let bytes = env.convert_byte_array(&model_bytes)?;
+        let manifest = optional_bytes(env, &manifest)?;
+        let integrity = integrity_arg(env, &sha256_hex, &public_key, &signature)?;
+
+        let loaded = load_bytes(&bytes, manifest.as_deref(), integrity, "<memory>");
+        register_engine(env, loaded)
+    })
+}
+
+/// Initialize an ML engine from a byte range of a file descriptor, typically
+/// `AssetFileDescriptor` (fd, startOffset, length) of an uncompressed APK asset
+/// The descriptor is not closed. Integrity arguments are optional, as in `nativeInitFromBytes`.
+#[no_mangle]
+pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeInitFromFd<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    fd: jint,
+    offset: jlong,
+    length: jlong,
+    manifest: JByteArray<'local>,
+    sha256_hex: JString<'local>,
+    public_key: JByteArray<'local>,
+    signature: JByteArray<'local>,
+) -> jlong {
+    guarded(&mut env, "nativeInitFromFd", INVALID_HANDLE as jlong, |env| {
+        init_logging();
+        info!("Initializing ML engine from fd {} ({} bytes at {})...", fd, length, offset);
+
+        let manifest = optional_bytes(env, &manifest)?;
+        let integrity = integrity_arg(env, &sha256_hex, &public_key, &signature)?;
+
+        let loaded = read_fd_range(fd, offset, length)
+            .and_then(|bytes| load_bytes(&bytes, manifest.as_deref(), integrity, &format!("<fd {}>", fd)));
+        register_engine(env, loaded)
+    })
+}
+
+/// Replace the model of a live engine with a model file, without a gap in classification
+/// Blocks while the candidate loads and runs its self-test, so call it off the main thread.
+/// Integrity arguments are optional, as in `nativeInitFromBytes`. Returns false (keeping the
+/// current model) if loading or the self-test fails; throws `SecurityException` on an
+/// integrity mismatch.
+#[no_mangle]
+pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeSwapModel<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    model_path: JString<'local>,
+    sha256_hex: JString<'local>,
+    public_key: JByteArray<'local>,
+    signature: JByteArray<'local>,
+) -> jboolean {
+    guarded(&mut env, "nativeSwapModel", false as jboolean, |env| {
+        let model_path_str: String = env.get_string(&model_path)?.into();
+        let integrity = integrity_arg(env, &sha256_hex, &public_key, &signature)?;
+        let target = match swappable(handle) {
+            Ok(target) => target,
+            Err(message) => {
+                error!("{}", message);
+                return Ok(false as jboolean);
+            }
+        };
+
+        info!("Swapping model of handle {} to {}...", handle, model_path_str);
+        let loaded = match &integrity {
+            Some(integrity) => MLEngine::new_verified(&model_path_str, integrity),
+            None => MLEngine::new(&model_path_str),
+        };
+        match loaded.and_then(|candidate| target.swap(candidate)) {
+            Ok(_) => Ok(true as jboolean),
+            Err(e) if e.is::<IntegrityError>() => {
+                error!("{}", e);
+                env.throw_new("java/lang/SecurityException", e.to_string())?;
+                Ok(false as jboolean)
+            }
+            Err(e) => {
+                error!("Model swap failed, keeping the current model: {}", e);
+                Ok(false as jboolean)
+            }
+        }
+    })
+}
+
+/// Return an engine to the model it used before the last swap
+/// Returns false if there is nothing to roll back to.
+#[no_mangle]
+pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeRollbackModel(
+    mut env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) -> jboolean {
+    guarded(&mut env, "nativeRollbackModel", false as jboolean, |_env| {
+        match swappable(handle) {
+            Ok(target) => Ok(target.rollback() as jboolean),
+            Err(message) => {
+                error!("{}", message);
+                Ok(false as jboolean)
+            }
+        }
+    })
+}
+
+/// Describe the live model of an engine (shapes, labels, hash, graph statistics)
+/// Returns a `ModelInfo`, or null if the handle is invalid.
+#[no_mangle]
+pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeModelInfo<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) -> jobject {
+    guarded(&mut env, "nativeModelInfo", std::ptr::null_mut(), |env| {
+        match engine(handle) {
+            Ok(engine) => Ok(new_model_info(env, engine.info())?.into_raw()),
+            Err(message) => {
+                error!("{}", message);
+                Ok(std::ptr::null_mut())
+            }
+        }
+    })
+}
+
 /// Classify a frame (image data)
 /// Returns float array of 5 class scores: [drawing, hentai, neutral, porn, sexy]
 #[no_mangle]
 pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeClassifyFrame<'local>(
-    env: JNIEnv<'local>,
+    mut env: JNIEnv<'local>,
     _class: JClass<'local>,
+    handle: jlong,
     image_data: JByteArray<'local>,
     width: jint,
     height: jint,
 ) -> jfloatArray {
-    let start_time = std::time::Instant::now();
+    guarded(&mut env, "nativeClassifyFrame", std::ptr::null_mut(), |env| {
+        let start_time = std::time::Instant::now();
+        let neutral = ClassificationResult::neutral().scores;
 
-    // Convert JByteArray to Rust Vec
-    let image_bytes = match env.convert_byte_array(image_data) {
-        Ok(bytes) => bytes,
-        Err(e) => {
-            error!("Failed to convert image data: {:?}", e);
-            let output = env.new_float_array(5).unwrap();
-            // Return neutral-dominant default: [0, 0, 1, 0, 0]
-            env.set_float_array_region(&output, 0, &[0.0, 0.0, 1.0, 0.0, 0.0]).unwrap();
-            return output.into_raw();
-        }
-    };
-
-    // Classify using ML engine
-    let ml_engine = ML_ENGINE.lock().unwrap();
-    let scores = match &*ml_engine {
-        Some(engine) => {
-            match engine.classify(&image_bytes, width as usize, height as usize) {
-                Ok(result) => result.scores,
-                Err(e) => {
-                    error!("Classification failed: {:?}", e);
-                    [0.0, 0.0, 1.0, 0.0, 0.0] // Default to neutral
+        // Convert JByteArray to Rust Vec
+        let scores = match env.convert_byte_array(&image_data) {
+            Ok(image_bytes) => {
+                // Classify using ML engine
+                match engine(handle) {
+                    Ok(engine) => {
+                        match engine.classify(&image_bytes, width as usize, height as usize) {
+                            Ok(result) => result.scores,
+                            Err(e) => {
+                                error!("Classification failed: {:?}", e);
+                                neutral // Default to neutral
+                            }
+                        }
+                    }
+                    Err(message) => {
+                        error!("{}", message);
+                        neutral // Default to neutral
+                    }
                 }
             }
-        }
-        None => {
-            error!("ML engine not initialized");
-            [0.0, 0.0, 1.0, 0.0, 0.0] // Default to neutral
-        }
-    };
+            Err(e) => {
+                error!("Failed to convert image data: {:?}", e);
+                neutral // Return neutral-dominant default: [0, 0, 1, 0, 0]
+            }
+        };
 
-    let elapsed = start_time.elapsed();
-    info!("Classification took {:?}", elapsed);
+        let elapsed = start_time.elapsed();
+        info!("Classification took {:?}", elapsed);
 
-    // Return [drawing, hentai, neutral, porn, sexy]
-    let output = env.new_float_array(5).unwrap();
-    env.set_float_array_region(&output, 0, &scores).unwrap();
-    output.into_raw()
+        // Return [drawing, hentai, neutral, porn, sexy]
+        let output = env.new_float_array(scores.len() as i32)?;
+        env.set_float_array_region(&output, 0, &scores)?;
+        Ok(output.into_raw())
+    })
 }
 
 /// Generate blurred version of image
 #[no_mangle]
 pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeGenerateBlur<'local>(
-    env: JNIEnv<'local>,
+    mut env: JNIEnv<'local>,
     _class: JClass<'local>,
     image_data: JByteArray<'local>,
     width: jint,
     height: jint,
     radius: f32,
 ) -> jbyteArray {
-    let start_time = std::time::Instant::now();
+    guarded(&mut env, "nativeGenerateBlur", std::ptr::null_mut(), |env| {
+        let start_time = std::time::Instant::now();
 
-    // Convert JByteArray to Rust Vec
-    let image_bytes = match env.convert_byte_array(image_data) {
-        Ok(bytes) => bytes,
-        Err(e) => {
-            error!("Failed to convert image data: {:?}", e);
-            // Return empty array on error
-            return env.new_byte_array(0).unwrap().into_raw();
-        }
-    };
+        // Convert JByteArray to Rust Vec
+        let image_bytes = match env.convert_byte_array(&image_data) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                error!("Failed to convert image data: {:?}", e);
+                // Return empty array on error
+                return Ok(env.new_byte_array(0)?.into_raw());
+            }
+        };
 
-    // Apply blur
-    let blurred = match blur(&image_bytes, width as usize, height as usize, radius) {
-        Ok(data) => data,
-        Err(e) => {
-            error!("Blur failed: {:?}", e);
-            // Return original data on error
-            image_bytes
-        }
-    };
+        // Apply blur
+        let blurred = match blur(&image_bytes, width as usize, height as usize, radius) {
+            Ok(data) => data,
+            Err(e) => {
+                error!("Blur failed: {:?}", e);
+                // Return original data on error
+                image_bytes
+            }
+        };
 
-    let elapsed = start_time.elapsed();
-    info!("Blur took {:?}", elapsed);
+        let elapsed = start_time.elapsed();
+        info!("Blur took {:?}", elapsed);
 
-    // Convert back to JByteArray
-    let output = env.new_byte_array(blurred.len() as i32).unwrap();
-    env.set_byte_array_region(&output, 0, &blurred.iter().map(|&b| b as i8).collect::<Vec<_>>()).unwrap();
-    output.into_raw()
+        // Convert back to JByteArray
+        Ok(env.byte_array_from_slice(&blurred)?.into_raw())
+    })
 }
 
 /// Generate pixelated version of image
 #[no_mangle]
 pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeGeneratePixelation<'local>(
-    env: JNIEnv<'local>,
+    mut env: JNIEnv<'local>,
     _class: JClass<'local>,
     image_data: JByteArray<'local>,
     width: jint,
     height: jint,
     block_size: jint,
 ) -> jbyteArray {
-    let start_time = std::time::Instant::now();
+    guarded(&mut env, "nativeGeneratePixelation", std::ptr::null_mut(), |env| {
+        let start_time = std::time::Instant::now();
 
-    // Convert JByteArray to Rust Vec
-    let image_bytes = match env.convert_byte_array(image_data) {
-        Ok(bytes) => bytes,
-        Err(e) => {
-            error!("Failed to convert image data: {:?}", e);
-            // Return empty array on error
-            return env.new_byte_array(0).unwrap().into_raw();
-        }
+        // Convert JByteArray to Rust Vec
+        let image_bytes = match env.convert_byte_array(&image_data) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                error!("Failed to convert image data: {:?}", e);
+                // Return empty array on error
+                return Ok(env.new_byte_array(0)?.into_raw());
+            }
+        };
+
+        // Apply pixelation
+        let pixelated = match pixelate(&image_bytes, width as usize, height as usize, block_size as usize) {
+            Ok(data) => data,
+            Err(e) => {
+                error!("Pixelation failed: {:?}", e);
+                // Return original data on error
+                image_bytes
+            }
+        };
+
+        let elapsed = start_time.elapsed();
+        info!("Pixelation took {:?}", elapsed);
+
+        // Convert back to JByteArray
+        Ok(env.byte_array_from_slice(&pixelated)?.into_raw())
+    })
+}
+
+/// Classify a frame and return a `com.pavlova.ml.ClassificationResult`
+/// Safety group and confidence come from the Rust definition; on failure the
+/// result is neutral and `error` carries the reason
+#[no_mangle]
+pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeClassifyFrameResult<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    image_data: JByteArray<'local>,
+    width: jint,
+    height: jint,
+) -> jobject {
+    guarded(&mut env, "nativeClassifyFrameResult", std::ptr::null_mut(), |env| {
+        let start_time = std::time::Instant::now();
+
+        let classified = match env.convert_byte_array(&image_data) {
+            Ok(image_bytes) => engine(handle).and_then(|engine| {
+                engine
+                    .classify(&image_bytes, width as usize, height as usize)
+                    .map_err(|e| format!("Classification failed: {}", e))
+            }),
+            Err(e) => Err(format!("Failed to convert image data: {}", e)),
+        };
+
+        let elapsed = start_time.elapsed();
+        info!("Classification took {:?}", elapsed);
+
+        let (result, error_message) = match classified {
+            Ok(result) => (result, None),
+            Err(message) => {
+                error!("{}", message);
+                (ClassificationResult::neutral(), Some(message))
+            }
+        };
+
+        let object = new_classification_result(env, &result, elapsed.as_micros() as i64, error_message.as_deref())?;
+        Ok(object.into_raw())
+    })
+}
+
+/// Build a `com.pavlova.ml.ClassificationResult` from a Rust classification
+fn new_classification_result<'local>(
+    env: &mut JNIEnv<'local>,
+    result: &ClassificationResult,
+    inference_micros: i64,
+    error_message: Option<&str>,
+) -> jni::errors::Result<JObject<'local>> {
+    let scores = env.new_float_array(result.scores.len() as i32)?;
+    env.set_float_array_region(&scores, 0, &result.scores)?;
+    let category = env.new_string(if error_message.is_some() { "error" } else { &result.top_class })?;
+    let top_class = env.new_string(&result.top_class)?;
+    let safety_group = env.new_string(result.safety_group().as_str())?;
+    let error_string = match error_message {
+        Some(message) => JObject::from(env.new_string(message)?),
+        None => JObject::null(),
     };
 
-    // Apply pixelation
-    let pixelated = match pixelate(&image_bytes, width as usize, height as usize, block_size as usize) {
-        Ok(data) => data,
-        Err(e) => {
-            error!("Pixelation failed: {:?}", e);
-            // Return original data on error
-            image_bytes
-        }
+    env.new_object(
+        "com/pavlova/ml/ClassificationResult",
+        "(ZFLjava/lang/String;[FLjava/lang/String;Ljava/lang/String;JLjava/lang/String;)V",
+        &[
+            JValue::Bool(result.is_safe as jboolean),
+            JValue::Float(result.confidence),
+            JValue::Object(&category),
+            JValue::Object(&scores),
+            JValue::Object(&top_class),
+            JValue::Object(&safety_group),
+            JValue::Long(inference_micros),
+            JValue::Object(&error_string),
+        ],
+    )
+}
+
+/// Classify a frame, apply the filtering policy and obscure it if needed, in a single call
+/// Returns a `com.pavlova.ml.FrameResult`; `pixels` is null when the frame is allowed
+#[no_mangle]
+pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeProcessFrame<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    image_data: JByteArray<'local>,
+    width: jint,
+    height: jint,
+) -> jobject {
+    guarded(&mut env, "nativeProcessFrame", std::ptr::null_mut(), |env| {
+        let image_bytes = match env.convert_byte_array(&image_data) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                error!("Failed to convert image data: {:?}", e);
+                return new_allow_result(env);
+            }
+        };
+
+        let overrides = current_overrides();
+        let processed = engine(handle).and_then(|engine| {
+            process_frame_with_overrides(
+                &engine,
+                &FilterPolicy::default(),
+                &overrides,
+                &image_bytes,
+                width as usize,
+                height as usize,
+            )
+            .map_err(|e| format!("Frame processing failed: {}", e))
+        });
+
+        let outcome = match processed {
+            Ok(outcome) => outcome,
+            Err(message) => {
+                error!("{}", message);
+                return new_allow_result(env);
+            }
+        };
+
+        info!(
+            "Frame processed in {:?} (classify: {:?}, effect: {:?})",
+            outcome.timings.total, outcome.timings.classify, outcome.timings.effect
+        );
+
+        Ok(new_frame_result(env, &outcome)?.into_raw())
+    })
+}
+
+/// Build a `com.pavlova.ml.FrameResult` from a pipeline outcome
+fn new_frame_result<'local>(
+    env: &mut JNIEnv<'local>,
+    outcome: &FrameOutcome,
+) -> jni::errors::Result<JObject<'local>> {
+    let classification = &outcome.classification;
+    let scores = env.new_float_array(classification.scores.len() as i32)?;
+    env.set_float_array_region(&scores, 0, &classification.scores)?;
+    let top_class = env.new_string(&classification.top_class)?;
+    let pixels = match &outcome.output {
+        Some(data) => JObject::from(env.byte_array_from_slice(data)?),
+        None => JObject::null(),
     };
+    let override_verdict = match &outcome.override_match {
+        Some(m) => JObject::from(env.new_string(m.verdict.as_str())?),
+        None => JObject::null(),
+    };
+
+    env.new_object(
+        "com/pavlova/ml/FrameResult",
+        "(ZIF[FLjava/lang/String;ZFJJJ[BLjava/lang/String;)V",
+        &[
+            JValue::Bool(outcome.decision.should_filter as jboolean),
+            JValue::Int(outcome.decision.action.code()),
+            JValue::Float(outcome.decision.action.parameter()),
+            JValue::Object(&scores),
+            JValue::Object(&top_class),
+            JValue::Bool(classification.is_safe as jboolean),
+            JValue::Float(classification.confidence),
+            JValue::Long(outcome.timings.classify.as_micros() as i64),
+            JValue::Long(outcome.timings.effect.as_micros() as i64),
+            JValue::Long(outcome.timings.total.as_micros() as i64),
+            JValue::Object(&pixels),
+            JValue::Object(&override_verdict),
+        ],
+    )
+}
+
+/// Java `String[]` from Rust strings
+fn new_string_array<'local>(
+    env: &mut JNIEnv<'local>,
+    values: impl ExactSizeIterator<Item = String>,
+) -> jni::errors::Result<JObjectArray<'local>> {
+    let array = env.new_object_array(values.len() as i32, "java/lang/String", JObject::null())?;
+    for (index, value) in values.enumerate() {
+        let value = env.new_string(value)?;
+        env.set_object_array_element(&array, index as i32, value)?;
+    }
+    Ok(array)
+}
+
+/// Build a Kotlin `ModelInfo`; tensors and ops are passed pre-formatted for display
+fn new_model_info<'local>(env: &mut JNIEnv<'local>, info: &ModelInfo) -> jni::errors::Result<JObject<'local>> {
+    let source = env.new_string(&info.source)?;
+    let sha256 = env.new_string(&info.sha256)?;
+    let inputs = new_string_array(env, info.inputs.iter().map(|t| t.to_string()))?;
+    let outputs = new_string_array(env, info.outputs.iter().map(|t| t.to_string()))?;
+    let labels = new_string_array(env, info.labels.iter().cloned())?;
+    let activation = env.new_string(&info.activation)?;
+    let ops = new_string_array(env, info.op_counts.iter().map(|(op, count)| format!("{} x{}", op, count)))?;
+
+    env.new_object(
+        "com/pavlova/ml/ModelInfo",
+        "(Ljava/lang/String;Ljava/lang/String;J[Ljava/lang/String;[Ljava/lang/String;[Ljava/lang/String;Ljava/lang/String;II[Ljava/lang/String;)V",
+        &[
+            JValue::Object(&source),
+            JValue::Object(&sha256),
+            JValue::Long(info.file_size as i64),
+            JValue::Object(&inputs),
+            JValue::Object(&outputs),
+            JValue::Object(&labels),
+            JValue::Object(&activation),
+            JValue::Int(info.source_node_count as i32),
+            JValue::Int(info.node_count as i32),
+            JValue::Object(&ops),
+        ],
+    )
+}
 
-    let elapsed = start_time.elapsed();
-    info!("Pixelation took {:?}", elapsed);
+/// Neutral, unfiltered `FrameResult` used when the frame could not be processed
+fn new_allow_result(env: &mut JNIEnv) -> jni::errors::Result<jobject> {
+    let outcome = FrameOutcome {
+        decision: PolicyDecision {
+            should_filter: false,
+            action: FilterAction::Allow,
+        },
+        classification: ClassificationResult::neutral(),
+        override_match: None,
+        output: None,
+        timings: Default::default(),
+    };
+    Ok(new_frame_result(env, &outcome)?.into_raw())
+}
+
+/// Current override list; frames in flight keep the list they started with
+fn current_overrides() -> Arc<OverrideList> {
+    let overrides = OVERRIDES.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    Arc::clone(&overrides.1)
+}
+
+/// Load the allow/block list of known images that `nativeProcessFrame` consults
+/// A missing file gives an empty list, created by the first `nativeAddOverride`.
+/// Returns the number of listed images, or -1 (keeping the current list) on failure.
+#[no_mangle]
+pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeLoadOverrides<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    path: JString<'local>,
+) -> jint {
+    guarded(&mut env, "nativeLoadOverrides", -1, |env| {
+        let path = PathBuf::from(String::from(env.get_string(&path)?));
+        let loaded = if path.exists() {
+            OverrideList::load(&path)
+        } else {
+            Ok(OverrideList::default())
+        };
+        match loaded {
+            Ok(list) => {
+                let count = list.len() as jint;
+                info!("Loaded {} image override(s) from {}", count, path.display());
+                *OVERRIDES.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = (Some(path), Arc::new(list));
+                Ok(count)
+            }
+            Err(e) => {
+                error!("Failed to load image overrides: {}", e);
+                Ok(-1)
+            }
+        }
+    })
+}
 
-    // Convert back to JByteArray
-    let output = env.new_byte_array(pixelated.len() as i32).unwrap();
-    env.set_byte_array_region(&output, 0, &pixelated.iter().map(|&b| b as i8).collect::<Vec<_>>()).unwrap();
-    output.into_raw()
+/// Edit the loaded override list and save it; the list in use only changes once saved
+fn update_overrides<T>(edit: impl FnOnce(&mut OverrideList) -> Result<T, Box<dyn Error>>) -> Result<T, Box<dyn Error>> {
+    let mut overrides = OVERRIDES.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let path: &Path = overrides.0.as_deref().ok_or("No override list loaded")?;
+    let mut list = OverrideList::clone(&overrides.1);
+    let result = edit(&mut list)?;
+    list.save(path)?;
+    overrides.1 = Arc::new(list);
+    Ok(result)
 }
 
-/// Cleanup and destroy ML engine
+/// Always allow (`block` false) or always obscure (`block` true) frames like this one,
+/// surviving recompression and rescaling
+/// Returns false if no list is loaded or it cannot be saved.
+#[no_mangle]
+pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeAddOverride<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    image_data: JByteArray<'local>,
+    width: jint,
+    height: jint,
+    block: jboolean,
+) -> jboolean {
+    guarded(&mut env, "nativeAddOverride", false as jboolean, |env| {
+        let image_bytes = env.convert_byte_array(&image_data)?;
+        let verdict = if block != 0 { OverrideVerdict::Block } else { OverrideVerdict::Allow };
+        match update_overrides(|list| list.add_image(&image_bytes, width as usize, height as usize, verdict)) {
+            Ok(hash) => {
+                info!("Image {} added to the override list ({})", hash, verdict.as_str());
+                Ok(true as jboolean)
+            }
+            Err(e) => {
+                error!("Failed to add image override: {}", e);
+                Ok(false as jboolean)
+            }
+        }
+    })
+}
+
+/// Forget the overrides matching this frame
+/// Returns how many were removed, or -1 if no list is loaded or it cannot be saved.
+#[no_mangle]
+pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeRemoveOverride<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    image_data: JByteArray<'local>,
+    width: jint,
+    height: jint,
+) -> jint {
+    guarded(&mut env, "nativeRemoveOverride", -1, |env| {
+        let image_bytes = env.convert_byte_array(&image_data)?;
+        let removed = update_overrides(|list| {
+            let hash = image_hash(&image_bytes, width as usize, height as usize, list.algorithm())?;
+            Ok(list.remove_matching(hash))
+        });
+        match removed {
+            Ok(count) => Ok(count as jint),
+            Err(e) => {
+                error!("Failed to remove image override: {}", e);
+                Ok(-1)
+            }
+        }
+    })
+}
+
+/// Cleanup and destroy an ML engine
+/// In-flight classifications on this handle finish on their own reference; this never blocks on them
 #[no_mangle]
 pub extern "C" fn Java_com_pavlova_ml_RustMLBridge_nativeDestroy(
-    _env: JNIEnv,
+    mut env: JNIEnv,
     _class: JClass,
+    handle: jlong,
 ) {
-    info!("Destroying ML engine...");
-    let mut ml_engine = ML_ENGINE.lock().unwrap();
-    *ml_engine = None;
-    info!("ML engine destroyed");
+    guarded(&mut env, "nativeDestroy", (), |_env| {
+        info!("Destroying ML engine (handle {})...", handle);
+        match ENGINES.remove(handle as u64) {
+            Some(_) => info!("ML engine destroyed"),
+            None => error!("No ML engine for handle {}", handle),
+        }
+        Ok(())
+    })
 }
 
 } // mod android
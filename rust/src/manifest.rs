@@ -0,0 +1,280 @@
+//! Model manifest: what a model's input and output mean.
+//!
+//! The classifier assumes a 224×224 NCHW input normalized to [-1, 1] and five
+//! probabilities in `CLASSES` order. A manifest next to the model
+//! (`integrity::manifest_path`, e.g. `model.manifest`) declares the actual input
+//! preprocessing, output labels and whether the raw outputs still need softmax or
+//! sigmoid, so a logit-emitting export or one with a different class order or input
+//! size works unchanged:
+//!
+//! ```text
+//! # key = value, '#' comments
+//! labels = porn, neutral, sexy, drawing, hentai
+//! activation = softmax   # auto (default), none, softmax or sigmoid
+//! input_size = 299x299   # width x height, default 224x224
+//! input_layout = nhwc    # nchw (default) or nhwc
+//! input_mean = 0         # one value or one per RGB channel, default 127.5
+//! input_std = 255        # pixel value v becomes (v - mean) / std, default 127.5
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::config::{CLASSES, MODEL_INPUT_CHANNELS, NUM_CLASSES};
+use crate::image::{PreprocessSpec, TensorLayout};
+
+/// Post-processing applied to the raw model output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputActivation {
+    /// Decide at load time from probe inferences: values outside [0, 1] are logits and get
+    /// softmax, anything else passes through (declare the activation when that is wrong)
+    Auto,
+    /// Outputs are already probabilities
+    None,
+    /// Outputs are logits of mutually exclusive classes
+    Softmax,
+    /// Outputs are independent per-class logits
+    Sigmoid,
+}
+
+impl OutputActivation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputActivation::Auto => "auto",
+            OutputActivation::None => "none",
+            OutputActivation::Softmax => "softmax",
+            OutputActivation::Sigmoid => "sigmoid",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(OutputActivation::Auto),
+            "none" => Some(OutputActivation::None),
+            "softmax" => Some(OutputActivation::Softmax),
+            "sigmoid" => Some(OutputActivation::Sigmoid),
+            _ => None,
+        }
+    }
+
+    /// Resolve `Auto` from probe outputs: only values outside [0, 1] prove logits
+    /// In-range outputs need not sum to 1 (a sigmoid head emits independent probabilities),
+    /// so they pass through rather than being flattened by softmax.
+    pub fn resolve(self, probe: &[f32]) -> Self {
+        if self != OutputActivation::Auto {
+            return self;
+        }
+        if probe.iter().all(|v| (0.0..=1.0).contains(v)) {
+            OutputActivation::None
+        } else {
+            OutputActivation::Softmax
+        }
+    }
+
+    /// Apply the activation in place (`Auto` must be resolved first and is left as is)
+    pub fn apply(self, values: &mut [f32]) {
+        match self {
+            OutputActivation::Auto | OutputActivation::None => {}
+            OutputActivation::Softmax => {
+                let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                let mut total = 0.0;
+                for value in values.iter_mut() {
+                    *value = (*value - max).exp();
+                    total += *value;
+                }
+                for value in values.iter_mut() {
+                    *value /= total;
+                }
+            }
+            OutputActivation::Sigmoid => {
+                for value in values.iter_mut() {
+                    *value = 1.0 / (1.0 + (-*value).exp());
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for OutputActivation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Declared meaning of a model's input and output
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelManifest {
+    /// Label of each output value, in output order; must name every class in `CLASSES` once
+    pub labels: Vec<String>,
+    pub activation: OutputActivation,
+    /// How frames become the input tensor
+    pub input: PreprocessSpec,
+}
+
+impl Default for ModelManifest {
+    /// The GantMan model: MobileNetV2 input, `CLASSES` order, activation detected at load time
+    fn default() -> Self {
+        ModelManifest {
+            labels: CLASSES.iter().map(|label| label.to_string()).collect(),
+            activation: OutputActivation::Auto,
+            input: PreprocessSpec::default(),
+        }
+    }
+}
+
+impl ModelManifest {
+    /// Parse a manifest; missing keys keep their defaults
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let mut manifest = ModelManifest::default();
+
+        for (index, raw_line) in text.lines().enumerate() {
+            let line = raw_line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| format!("line {}: expected 'key = value', got '{}'", index + 1, line))?;
+
+            match key {
+                "labels" => manifest.labels = value.split(',').map(|label| label.trim().to_string()).collect(),
+                "activation" => {
+                    manifest.activation = OutputActivation::parse(value).ok_or_else(|| {
+                        format!("line {}: unknown activation '{}' (auto, none, softmax or sigmoid)", index + 1, value)
+                    })?
+                }
+                "input_size" => {
+                    let (width, height) = value
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+                        .ok_or_else(|| format!("line {}: expected input_size = <width>x<height>, got '{}'", index + 1, value))?;
+                    manifest.input.width = width;
+                    manifest.input.height = height;
+                }
+                "input_layout" => {
+                    manifest.input.layout = TensorLayout::parse(value)
+                        .ok_or_else(|| format!("line {}: unknown input_layout '{}' (nchw or nhwc)", index + 1, value))?
+                }
+                "input_mean" => manifest.input.mean = parse_channels(index, key, value)?,
+                "input_std" => manifest.input.std = parse_channels(index, key, value)?,
+                _ => return Err(format!("line {}: unknown key '{}'", index + 1, key).into()),
+            }
+        }
+
+        manifest.class_order()?;
+        manifest.input.validate()?;
+        Ok(manifest)
+    }
+
+    /// Parse manifest bytes as shipped next to the model
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let text = std::str::from_utf8(bytes).map_err(|_| "Model manifest is not valid UTF-8")?;
+        Self::parse(text).map_err(|e| format!("Model manifest: {}", e).into())
+    }
+
+    /// Load a manifest file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let bytes = fs::read(path).map_err(|e| format!("Cannot read manifest {}: {}", path.display(), e))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Output index of each class in `CLASSES` order
+    /// Fails unless every class is labeled exactly once; extra labels are allowed and ignored.
+    pub fn class_order(&self) -> Result<[usize; NUM_CLASSES], Box<dyn Error>> {
+        let mut order = [0usize; NUM_CLASSES];
+        for (class_index, class) in CLASSES.iter().enumerate() {
+            let mut positions = self.labels.iter().enumerate().filter(|(_, label)| label.as_str() == *class);
+            match (positions.next(), positions.next()) {
+                (Some((position, _)), None) => order[class_index] = position,
+                (None, _) => return Err(format!("labels do not include class '{}'", class).into()),
+                (Some(_), Some(_)) => return Err(format!("class '{}' is labeled more than once", class).into()),
+            }
+        }
+        Ok(order)
+    }
+
+    /// Serialize in the format read by `parse`
+    pub fn to_text(&self) -> String {
+        let join = |values: &[f32]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ");
+        format!(
+            "labels = {}\nactivation = {}\ninput_size = {}x{}\ninput_layout = {}\ninput_mean = {}\ninput_std = {}\n",
+            self.labels.join(", "),
+            self.activation,
+            self.input.width,
+            self.input.height,
+            self.input.layout.as_str(),
+            join(&self.input.mean),
+            join(&self.input.std)
+        )
+    }
+}
+
+/// One value for every RGB channel, or one value per channel
+fn parse_channels(index: usize, key: &str, value: &str) -> Result<[f32; MODEL_INPUT_CHANNELS], Box<dyn Error>> {
+    let values: Vec<f32> = value
+        .split(',')
+        .map(|v| v.trim().parse::<f32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("line {}: {} must be numbers, got '{}'", index + 1, key, value))?;
+    match values[..] {
+        [all] => Ok([all; MODEL_INPUT_CHANNELS]),
+        [r, g, b] => Ok([r, g, b]),
+        _ => Err(format!("line {}: {} takes 1 or {} values, got {}", index + 1, key, MODEL_INPUT_CHANNELS, values.len()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = ModelManifest::parse("# exported with tf2onnx\nlabels = porn, neutral, sexy, drawing, hentai, other\nactivation = sigmoid\n").unwrap();
+        assert_eq!(manifest.activation, OutputActivation::Sigmoid);
+        assert_eq!(manifest.class_order().unwrap(), [3, 4, 1, 0, 2]);
+        assert_eq!(ModelManifest::parse(&manifest.to_text()).unwrap(), manifest);
+
+        assert_eq!(ModelManifest::parse("").unwrap(), ModelManifest::default());
+        assert!(ModelManifest::parse("labels = safe, unsafe").is_err());
+        assert!(ModelManifest::parse("labels = drawing, hentai, neutral, porn, sexy, porn").is_err());
+        assert!(ModelManifest::parse("activation = relu").is_err());
+        assert!(ModelManifest::parse("version = 2").is_err());
+        assert!(ModelManifest::from_bytes(&[0xff, 0xfe]).is_err());
+    }
+
+    #[test]
+    fn test_parse_input_spec() {
+        let manifest = ModelManifest::parse("input_size = 299x299\ninput_layout = nhwc\ninput_mean = 0\ninput_std = 58.4, 57.1, 57.4\n").unwrap();
+        assert_eq!(manifest.input.shape(), [1, 299, 299, 3]);
+        assert_eq!(manifest.input.mean, [0.0; 3]);
+        assert_eq!(manifest.input.std, [58.4, 57.1, 57.4]);
+        assert_eq!(ModelManifest::parse(&manifest.to_text()).unwrap(), manifest);
+
+        assert!(ModelManifest::parse("input_size = 224").is_err());
+        assert!(ModelManifest::parse("input_size = 0x224").is_err());
+        assert!(ModelManifest::parse("input_layout = chw").is_err());
+        assert!(ModelManifest::parse("input_std = 0").is_err());
+        assert!(ModelManifest::parse("input_mean = 1, 2").is_err());
+    }
+
+    #[test]
+    fn test_activations() {
+        let mut logits = [2.0, 1.0, 0.0];
+        OutputActivation::Softmax.apply(&mut logits);
+        assert!((logits.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        assert!(logits[0] > logits[1] && logits[1] > logits[2]);
+
+        let mut logits = [0.0, 100.0];
+        OutputActivation::Sigmoid.apply(&mut logits);
+        assert_eq!(logits, [0.5, 1.0]);
+
+        assert_eq!(OutputActivation::Auto.resolve(&[0.1, 0.2, 0.7]), OutputActivation::None);
+        assert_eq!(OutputActivation::Auto.resolve(&[-1.0, 3.0, 0.5]), OutputActivation::Softmax);
+        // Independent sigmoid probabilities are not flattened
+        assert_eq!(OutputActivation::Auto.resolve(&[0.9, 0.1, 0.8]), OutputActivation::None);
+        assert_eq!(OutputActivation::Sigmoid.resolve(&[0.1, 0.2, 0.7]), OutputActivation::Sigmoid);
+    }
+}
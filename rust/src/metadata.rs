@@ -0,0 +1,164 @@
+//! Model introspection: what was actually loaded.
+//!
+//! Collected once at load time from the model bytes and the optimized tract plan,
+//! so a wrong model (other input size, two outputs instead of five, ...) can be
+//! spotted from its description instead of from odd classifications.
+
+use std::fmt;
+
+use tract_onnx::prelude::*;
+
+use crate::manifest::OutputActivation;
+
+/// Shape and element type of a model input or output
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TensorInfo {
+    pub name: String,
+    /// Element type as named by tract, e.g. `f32`
+    pub dtype: String,
+    /// Dimensions; `None` for a symbolic (e.g. batch) dimension
+    pub shape: Vec<Option<usize>>,
+}
+
+impl TensorInfo {
+    /// Number of elements, if every dimension is known
+    pub fn element_count(&self) -> Option<usize> {
+        self.shape.iter().try_fold(1usize, |count, dim| count.checked_mul((*dim)?))
+    }
+}
+
+impl fmt::Display for TensorInfo {
+    /// `name: f32[1,3,224,224]`, with `?` for symbolic dimensions
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dims: Vec<String> = self
+            .shape
+            .iter()
+            .map(|dim| dim.map_or_else(|| "?".to_string(), |d| d.to_string()))
+            .collect();
+        write!(f, "{}: {}[{}]", self.name, self.dtype, dims.join(","))
+    }
+}
+
+/// Description of a loaded model
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ModelInfo {
+    /// File path, or the source name of a model loaded from memory
+    pub source: String,
+    /// Lowercase hex SHA-256 of the model bytes
+    pub sha256: String,
+    pub file_size: u64,
+    pub inputs: Vec<TensorInfo>,
+    pub outputs: Vec<TensorInfo>,
+    /// Class names assigned to the output scores, in order
+    pub labels: Vec<String>,
+    /// Post-processing applied to the raw output (`none`, `softmax` or `sigmoid`)
+    pub activation: String,
+    /// Nodes in the model file's graph, counting inputs and initializers
+    pub source_node_count: usize,
+    /// Nodes in the graph that actually runs
+    pub node_count: usize,
+    /// Operator name and number of nodes using it in the running graph, by count then name
+    pub op_counts: Vec<(String, usize)>,
+}
+
+impl ModelInfo {
+    /// Describe an optimized plan; `source_node_count` is the node count before optimization
+    pub(crate) fn collect(
+        source: &str,
+        bytes: &[u8],
+        source_node_count: usize,
+        model: &TypedModel,
+        labels: Vec<String>,
+        activation: OutputActivation,
+    ) -> TractResult<Self> {
+        let tensors = |outlets: &[OutletId]| -> TractResult<Vec<TensorInfo>> {
+            outlets
+                .iter()
+                .map(|&outlet| {
+                    let fact = model.outlet_fact(outlet)?;
+                    Ok(TensorInfo {
+                        name: model.node(outlet.node).name.clone(),
+                        dtype: format!("{:?}", fact.datum_type).to_lowercase(),
+                        shape: fact
+                            .shape
+                            .iter()
+                            .map(|dim| dim.as_i64().and_then(|d| usize::try_from(d).ok()))
+                            .collect(),
+                    })
+                })
+                .collect()
+        };
+
+        let mut op_counts: Vec<(String, usize)> = Vec::new();
+        for node in model.nodes() {
+            let op = node.op().name();
+            match op_counts.iter_mut().find(|(name, _)| *name == op) {
+                Some((_, count)) => *count += 1,
+                None => op_counts.push((op.into_owned(), 1)),
+            }
+        }
+        op_counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        Ok(ModelInfo {
+            source: source.to_string(),
+            sha256: crate::integrity::sha256_hex(bytes),
+            file_size: bytes.len() as u64,
+            inputs: tensors(model.input_outlets()?)?,
+            outputs: tensors(model.output_outlets()?)?,
+            labels,
+            activation: activation.to_string(),
+            source_node_count,
+            node_count: model.nodes().len(),
+            op_counts,
+        })
+    }
+}
+
+impl fmt::Display for ModelInfo {
+    /// Multi-line `key: value` summary for logs and the CLI
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "source: {}", self.source)?;
+        writeln!(f, "sha256: {}", self.sha256)?;
+        writeln!(f, "size: {} bytes", self.file_size)?;
+        for input in &self.inputs {
+            writeln!(f, "input: {}", input)?;
+        }
+        for output in &self.outputs {
+            writeln!(f, "output: {}", output)?;
+        }
+        writeln!(f, "labels: {}", self.labels.join(", "))?;
+        writeln!(f, "activation: {}", self.activation)?;
+        writeln!(
+            f,
+            "graph: {} nodes after optimization ({} in file)",
+            self.node_count, self.source_node_count
+        )?;
+        let ops: Vec<String> = self.op_counts.iter().map(|(op, count)| format!("{} x{}", op, count)).collect();
+        write!(f, "ops: {}", ops.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tensor_info() {
+        let tensor = TensorInfo {
+            name: "input".to_string(),
+            dtype: "f32".to_string(),
+            shape: vec![Some(1), Some(3), Some(224), Some(224)],
+        };
+        assert_eq!(tensor.to_string(), "input: f32[1,3,224,224]");
+        assert_eq!(tensor.element_count(), Some(3 * 224 * 224));
+
+        let symbolic = TensorInfo {
+            shape: vec![None, Some(5)],
+            ..tensor
+        };
+        assert_eq!(symbolic.to_string(), "input: f32[?,5]");
+        assert_eq!(symbolic.element_count(), None);
+    }
+}
@@ -0,0 +1,557 @@
+//! Perceptual hashing and the local allow/block list of known images.
+//!
+//! A perceptual hash summarises the coarse structure of a frame or region in 64 bits,
+//! so recompression, rescaling and small colour shifts change only a few bits.
+//! `OverrideList` keeps "always allow" and "always block" hashes in a BK-tree and
+//! finds the nearest one within a Hamming distance; the pipeline lets a match override
+//! the model's verdict. Lists persist as `key = value` text files.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::image::Rect;
+
+/// Hamming distance (of 64 bits) under which two hashes are taken as the same image
+pub const DEFAULT_MAX_DISTANCE: u32 = 8;
+
+/// Luma range (of 255) across the dHash thumbnail under which a frame is too uniform to
+/// allow-list: its hash would be near all zeros and match every blank or loading screen
+pub const MIN_ALLOW_CONTRAST: f32 = 16.0;
+
+/// Side of the greyscale thumbnail the pHash DCT runs on
+const PHASH_SIZE: usize = 32;
+
+/// Low-frequency DCT coefficients kept per axis (8 × 8 = 64 bits)
+const PHASH_FREQUENCIES: usize = 8;
+
+/// Source pixels averaged per thumbnail cell and axis, at most (larger cells are subsampled)
+const CELL_SAMPLES: usize = 16;
+
+/// 64-bit perceptual hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageHash(pub u64);
+
+impl ImageHash {
+    /// Number of differing bits
+    pub fn distance(&self, other: ImageHash) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+
+    /// Parse 16 hex digits
+    pub fn parse(value: &str) -> Option<Self> {
+        if value.len() != 16 {
+            return None;
+        }
+        u64::from_str_radix(value, 16).ok().map(ImageHash)
+    }
+}
+
+impl fmt::Display for ImageHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Perceptual hash function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    /// Difference hash: brightness gradients of a 9×8 thumbnail; fastest
+    DHash,
+    /// DCT hash: low frequencies of a 32×32 thumbnail against their median; more robust
+    /// to gamma and contrast changes
+    PHash,
+}
+
+impl HashAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashAlgorithm::DHash => "dhash",
+            HashAlgorithm::PHash => "phash",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "dhash" => Some(HashAlgorithm::DHash),
+            "phash" => Some(HashAlgorithm::PHash),
+            _ => None,
+        }
+    }
+}
+
+/// Hash a whole RGBA frame
+pub fn image_hash(rgba_data: &[u8], width: usize, height: usize, algorithm: HashAlgorithm) -> Result<ImageHash, Box<dyn Error>> {
+    region_hash(rgba_data, width, height, Rect::full(width, height), algorithm)
+}
+
+/// Hash a region of an RGBA frame
+pub fn region_hash(
+    rgba_data: &[u8],
+    width: usize,
+    height: usize,
+    region: Rect,
+    algorithm: HashAlgorithm,
+) -> Result<ImageHash, Box<dyn Error>> {
+    let expected_len = width.checked_mul(height).and_then(|p| p.checked_mul(4));
+    if expected_len != Some(rgba_data.len()) {
+        return Err(format!("RGBA data size mismatch for {}x{}: got {} bytes", width, height, rgba_data.len()).into());
+    }
+    region.check_within(width, height)?;
+
+    Ok(match algorithm {
+        HashAlgorithm::DHash => {
+            let thumbnail = grey_thumbnail(rgba_data, width, region, 9, 8);
+            let mut bits = 0u64;
+            for row in thumbnail.chunks(9) {
+                for pair in row.windows(2) {
+                    bits = bits << 1 | (pair[0] < pair[1]) as u64;
+                }
+            }
+            ImageHash(bits)
+        }
+        HashAlgorithm::PHash => {
+            let thumbnail = grey_thumbnail(rgba_data, width, region, PHASH_SIZE, PHASH_SIZE);
+            let coefficients = low_frequency_dct(&thumbnail);
+            // The DC term only measures overall brightness; leave it out of the median
+            let mut ac = coefficients[1..].to_vec();
+            ac.sort_by(f32::total_cmp);
+            let median = ac[ac.len() / 2];
+            ImageHash(coefficients.iter().fold(0u64, |bits, &c| bits << 1 | (c > median) as u64))
+        }
+    })
+}
+
+/// Mean luma of each cell of a `columns`×`rows` grid over the region
+fn grey_thumbnail(rgba_data: &[u8], width: usize, region: Rect, columns: usize, rows: usize) -> Vec<f32> {
+    let span = |index: usize, cells: usize, len: usize| {
+        let start = index * len / cells;
+        (start, ((index + 1) * len / cells).max(start + 1))
+    };
+    let mut thumbnail = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        let (y0, y1) = span(row, rows, region.height);
+        for column in 0..columns {
+            let (x0, x1) = span(column, columns, region.width);
+            let (mut sum, mut count) = (0.0f32, 0usize);
+            for y in (y0..y1).step_by((y1 - y0).div_ceil(CELL_SAMPLES)) {
+                for x in (x0..x1).step_by((x1 - x0).div_ceil(CELL_SAMPLES)) {
+                    let i = ((region.y + y) * width + region.x + x) * 4;
+                    sum += 0.299 * rgba_data[i] as f32 + 0.587 * rgba_data[i + 1] as f32 + 0.114 * rgba_data[i + 2] as f32;
+                    count += 1;
+                }
+            }
+            thumbnail.push(sum / count as f32);
+        }
+    }
+    thumbnail
+}
+
+/// Unscaled 2D DCT-II of a square thumbnail, lowest frequencies only, row-major
+fn low_frequency_dct(thumbnail: &[f32]) -> Vec<f32> {
+    let cosines: Vec<f32> = (0..PHASH_FREQUENCIES)
+        .flat_map(|u| {
+            (0..PHASH_SIZE).map(move |x| ((2 * x + 1) as f32 * u as f32 * std::f32::consts::PI / (2 * PHASH_SIZE) as f32).cos())
+        })
+        .collect();
+    let cosine = |u: usize, x: usize| cosines[u * PHASH_SIZE + x];
+
+    // Rows first, then columns
+    let mut rows = vec![0.0f32; PHASH_SIZE * PHASH_FREQUENCIES];
+    for y in 0..PHASH_SIZE {
+        for u in 0..PHASH_FREQUENCIES {
+            rows[y * PHASH_FREQUENCIES + u] = (0..PHASH_SIZE).map(|x| thumbnail[y * PHASH_SIZE + x] * cosine(u, x)).sum();
+        }
+    }
+    let mut coefficients = Vec::with_capacity(PHASH_FREQUENCIES * PHASH_FREQUENCIES);
+    for v in 0..PHASH_FREQUENCIES {
+        for u in 0..PHASH_FREQUENCIES {
+            coefficients.push((0..PHASH_SIZE).map(|y| rows[y * PHASH_FREQUENCIES + u] * cosine(v, y)).sum());
+        }
+    }
+    coefficients
+}
+
+/// User decision attached to a known image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverrideVerdict {
+    /// Never obscure, whatever the model says
+    Allow,
+    /// Always obscure
+    Block,
+}
+
+impl OverrideVerdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverrideVerdict::Allow => "allow",
+            OverrideVerdict::Block => "block",
+        }
+    }
+}
+
+/// Listed image matching a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverrideMatch {
+    pub verdict: OverrideVerdict,
+    /// Hash as listed
+    pub hash: ImageHash,
+    pub distance: u32,
+}
+
+/// Persistent set of allowed and blocked image hashes
+#[derive(Debug, Clone)]
+pub struct OverrideList {
+    algorithm: HashAlgorithm,
+    max_distance: u32,
+    tree: BkTree,
+}
+
+impl Default for OverrideList {
+    /// Empty dHash list matching within `DEFAULT_MAX_DISTANCE`
+    fn default() -> Self {
+        OverrideList::new(HashAlgorithm::DHash, DEFAULT_MAX_DISTANCE)
+    }
+}
+
+impl OverrideList {
+    pub fn new(algorithm: HashAlgorithm, max_distance: u32) -> Self {
+        OverrideList {
+            algorithm,
+            max_distance,
+            tree: BkTree::default(),
+        }
+    }
+
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    pub fn max_distance(&self) -> u32 {
+        self.max_distance
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.nodes.is_empty()
+    }
+
+    /// Listed hashes in insertion order
+    pub fn entries(&self) -> impl Iterator<Item = (ImageHash, OverrideVerdict)> + '_ {
+        self.tree.nodes.iter().map(|node| (node.hash, node.verdict))
+    }
+
+    /// List a hash, replacing the verdict of an identical one; returns whether the list changed
+    pub fn add(&mut self, hash: ImageHash, verdict: OverrideVerdict) -> bool {
+        self.tree.insert(hash, verdict)
+    }
+
+    /// Hash a frame with the list's algorithm and list it
+    /// Allowing a frame with less than `MIN_ALLOW_CONTRAST` is an error; blocking one is not.
+    pub fn add_image(&mut self, rgba_data: &[u8], width: usize, height: usize, verdict: OverrideVerdict) -> Result<ImageHash, Box<dyn Error>> {
+        let hash = image_hash(rgba_data, width, height, self.algorithm)?;
+        if verdict == OverrideVerdict::Allow {
+            let thumbnail = grey_thumbnail(rgba_data, width, Rect::full(width, height), 9, 8);
+            let contrast = thumbnail.iter().copied().fold(f32::MIN, f32::max) - thumbnail.iter().copied().fold(f32::MAX, f32::min);
+            if contrast < MIN_ALLOW_CONTRAST {
+                return Err(format!(
+                    "Image is too uniform to allow (contrast {:.1}, at least {} needed): it would match every blank frame",
+                    contrast, MIN_ALLOW_CONTRAST
+                )
+                .into());
+            }
+        }
+        self.add(hash, verdict);
+        Ok(hash)
+    }
+
+    /// Unlist every hash within `max_distance` of `hash`; returns how many were removed
+    pub fn remove_matching(&mut self, hash: ImageHash) -> usize {
+        let (before, max_distance) = (self.len(), self.max_distance);
+        let kept: Vec<BkNode> = self
+            .tree
+            .nodes
+            .drain(..)
+            .filter(|node| node.hash.distance(hash) > max_distance)
+            .collect();
+        for node in kept {
+            self.tree.insert(node.hash, node.verdict);
+        }
+        before - self.len()
+    }
+
+    /// Nearest listed hash within `max_distance`; a block wins over an allow at the same distance
+    pub fn lookup(&self, hash: ImageHash) -> Option<OverrideMatch> {
+        self.tree
+            .within(hash, self.max_distance)
+            .into_iter()
+            .map(|(node, distance)| OverrideMatch {
+                verdict: node.verdict,
+                hash: node.hash,
+                distance,
+            })
+            .min_by_key(|m| (m.distance, m.verdict != OverrideVerdict::Block))
+    }
+
+    /// Hash a frame and look it up; an empty list skips hashing
+    pub fn check(&self, rgba_data: &[u8], width: usize, height: usize) -> Result<Option<OverrideMatch>, Box<dyn Error>> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        Ok(self.lookup(image_hash(rgba_data, width, height, self.algorithm)?))
+    }
+
+    /// Parse a list: `algorithm` (`dhash` or `phash`), `max_distance` and any number of
+    /// `allow = <hash>` / `block = <hash>` lines with 16 hex digits; `#` starts a comment
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let mut list = OverrideList::default();
+
+        for (index, raw_line) in text.lines().enumerate() {
+            let line = raw_line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| format!("line {}: expected 'key = value', got '{}'", index + 1, line))?;
+
+            let verdict = match key {
+                "algorithm" => {
+                    if !list.is_empty() {
+                        return Err(format!("line {}: algorithm must come before the hashes", index + 1).into());
+                    }
+                    list.algorithm = HashAlgorithm::parse(value)
+                        .ok_or_else(|| format!("line {}: unknown algorithm '{}' (dhash or phash)", index + 1, value))?;
+                    continue;
+                }
+                "max_distance" => {
+                    list.max_distance = match value.parse::<u32>() {
+                        Ok(distance) if distance <= 64 => distance,
+                        _ => return Err(format!("line {}: max_distance must be 0 to 64, got '{}'", index + 1, value).into()),
+                    };
+                    continue;
+                }
+                "allow" => OverrideVerdict::Allow,
+                "block" => OverrideVerdict::Block,
+                _ => return Err(format!("line {}: unknown key '{}'", index + 1, key).into()),
+            };
+            let hash = ImageHash::parse(value)
+                .ok_or_else(|| format!("line {}: expected 16 hex digits, got '{}'", index + 1, value))?;
+            list.add(hash, verdict);
+        }
+
+        Ok(list)
+    }
+
+    /// Load a list from a file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Cannot read override list {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    /// Write the list, replacing the file only once the new contents are complete
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let partial = path.with_extension("partial");
+        fs::write(&partial, self.to_text())
+            .and_then(|_| fs::rename(&partial, path))
+            .map_err(|e| format!("Cannot write override list {}: {}", path.display(), e).into())
+    }
+
+    /// Serialize in the format read by `parse`
+    pub fn to_text(&self) -> String {
+        let mut text = format!("algorithm = {}\nmax_distance = {}\n", self.algorithm.as_str(), self.max_distance);
+        for (hash, verdict) in self.entries() {
+            text.push_str(&format!("{} = {}\n", verdict.as_str(), hash));
+        }
+        text
+    }
+}
+
+/// BK-tree over Hamming distance: each child edge is labelled with its distance to the
+/// parent, so a query within `d` of a node at distance `n` only follows edges `n ± d`
+#[derive(Debug, Clone, Default)]
+struct BkTree {
+    nodes: Vec<BkNode>,
+}
+
+#[derive(Debug, Clone)]
+struct BkNode {
+    hash: ImageHash,
+    verdict: OverrideVerdict,
+    children: Vec<(u32, usize)>,
+}
+
+impl BkTree {
+    fn insert(&mut self, hash: ImageHash, verdict: OverrideVerdict) -> bool {
+        let new_node = BkNode {
+            hash,
+            verdict,
+            children: Vec::new(),
+        };
+        if self.nodes.is_empty() {
+            self.nodes.push(new_node);
+            return true;
+        }
+
+        let mut index = 0;
+        loop {
+            let distance = self.nodes[index].hash.distance(hash);
+            if distance == 0 {
+                let changed = self.nodes[index].verdict != verdict;
+                self.nodes[index].verdict = verdict;
+                return changed;
+            }
+            match self.nodes[index].children.iter().find(|(d, _)| *d == distance) {
+                Some(&(_, child)) => index = child,
+                None => {
+                    self.nodes.push(new_node);
+                    let child = self.nodes.len() - 1;
+                    self.nodes[index].children.push((distance, child));
+                    return true;
+                }
+            }
+        }
+    }
+
+    /// Nodes within `max_distance` of `hash`, with their distances
+    fn within(&self, hash: ImageHash, max_distance: u32) -> Vec<(&BkNode, u32)> {
+        let mut found = Vec::new();
+        let mut pending = if self.nodes.is_empty() { vec![] } else { vec![0] };
+        while let Some(index) = pending.pop() {
+            let node = &self.nodes[index];
+            let distance = node.hash.distance(hash);
+            if distance <= max_distance {
+                found.push((node, distance));
+            }
+            let range = distance.saturating_sub(max_distance)..=distance + max_distance;
+            pending.extend(node.children.iter().filter(|(d, _)| range.contains(d)).map(|&(_, child)| child));
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Diagonal gradient with a bright square, optionally offset in brightness and noise
+    fn picture(width: usize, height: usize, shift: i32, noise: bool) -> Vec<u8> {
+        let mut data = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for x in 0..width {
+                let mut value = ((x + y) * 255 / (width + height)) as i32;
+                if (width / 4..width / 2).contains(&x) && (height / 4..height / 2).contains(&y) {
+                    value = 240;
+                }
+                let jitter = if noise { ((x * 7 + y * 13) % 7) as i32 - 3 } else { 0 };
+                let v = (value + shift + jitter).clamp(0, 255) as u8;
+                data.extend_from_slice(&[v, v / 2, 255 - v, 255]);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_hashes_survive_small_changes() {
+        for algorithm in [HashAlgorithm::DHash, HashAlgorithm::PHash] {
+            let original = image_hash(&picture(120, 90, 0, false), 120, 90, algorithm).unwrap();
+            let noisy = image_hash(&picture(120, 90, 6, true), 120, 90, algorithm).unwrap();
+            let rescaled = image_hash(&picture(60, 45, 0, false), 60, 45, algorithm).unwrap();
+            assert!(original.distance(noisy) <= 4, "{:?}: {}", algorithm, original.distance(noisy));
+            assert!(original.distance(rescaled) <= 6, "{:?}: {}", algorithm, original.distance(rescaled));
+
+            let flipped: Vec<u8> = picture(120, 90, 0, false)
+                .chunks(4)
+                .rev()
+                .flatten()
+                .copied()
+                .collect();
+            let other = image_hash(&flipped, 120, 90, algorithm).unwrap();
+            assert!(original.distance(other) > 20, "{:?}: {}", algorithm, original.distance(other));
+        }
+    }
+
+    #[test]
+    fn test_region_hash() {
+        let frame = picture(120, 90, 0, false);
+        let whole = image_hash(&frame, 120, 90, HashAlgorithm::DHash).unwrap();
+        assert_eq!(region_hash(&frame, 120, 90, Rect::full(120, 90), HashAlgorithm::DHash).unwrap(), whole);
+        assert!(region_hash(&frame, 120, 90, Rect::new(100, 0, 30, 10), HashAlgorithm::DHash).is_err());
+        assert!(region_hash(&frame, 120, 90, Rect::new(0, 0, 0, 10), HashAlgorithm::DHash).is_err());
+        // Tiny regions still hash
+        assert!(region_hash(&frame, 120, 90, Rect::new(5, 5, 2, 2), HashAlgorithm::PHash).is_ok());
+    }
+
+    #[test]
+    fn test_lookup_nearest_within_distance() {
+        let mut list = OverrideList::new(HashAlgorithm::DHash, 4);
+        for i in 0..200u64 {
+            list.add(ImageHash(i.wrapping_mul(0x9e37_79b9_7f4a_7c15)), OverrideVerdict::Allow);
+        }
+        let blocked = ImageHash(0xffff_0000_ffff_0000);
+        assert!(list.add(blocked, OverrideVerdict::Block));
+        assert!(!list.add(blocked, OverrideVerdict::Block));
+
+        let found = list.lookup(ImageHash(blocked.0 ^ 0b101)).unwrap();
+        assert_eq!((found.verdict, found.hash, found.distance), (OverrideVerdict::Block, blocked, 2));
+        assert!(list.lookup(ImageHash(blocked.0 ^ 0b11111)).is_none());
+
+        // Brute force agrees with the tree
+        for i in 0..200u64 {
+            let query = ImageHash(i.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ (1 << (i % 64)));
+            let expected = list.entries().map(|(h, _)| h.distance(query)).filter(|&d| d <= 4).min();
+            assert_eq!(list.lookup(query).map(|m| m.distance), expected);
+        }
+
+        assert_eq!(list.remove_matching(blocked), 1);
+        assert!(list.lookup(blocked).is_none());
+        assert_eq!(list.len(), 200);
+    }
+
+    #[test]
+    fn test_block_wins_ties() {
+        let mut list = OverrideList::default();
+        list.add(ImageHash(0b01), OverrideVerdict::Allow);
+        list.add(ImageHash(0b10), OverrideVerdict::Block);
+        assert_eq!(list.lookup(ImageHash(0)).unwrap().verdict, OverrideVerdict::Block);
+    }
+
+    #[test]
+    fn test_uniform_frames_cannot_be_allowed() {
+        for algorithm in [HashAlgorithm::DHash, HashAlgorithm::PHash] {
+            let mut list = OverrideList::new(algorithm, DEFAULT_MAX_DISTANCE);
+            let black = vec![0u8; 64 * 48 * 4];
+            assert!(list.add_image(&black, 64, 48, OverrideVerdict::Allow).is_err());
+            // Faint noise over a grey loading screen is still uniform
+            let grey: Vec<u8> = (0..64 * 48 * 4).map(|i| if i % 4 == 3 { 255 } else { 100 + (i % 7) as u8 }).collect();
+            assert!(list.add_image(&grey, 64, 48, OverrideVerdict::Allow).is_err());
+            assert!(list.is_empty());
+
+            // Blocking a uniform frame is harmless, and textured frames can be allowed
+            assert!(list.add_image(&black, 64, 48, OverrideVerdict::Block).is_ok());
+            assert!(list.add_image(&picture(64, 48, 0, false), 64, 48, OverrideVerdict::Allow).is_ok());
+            assert_eq!(list.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let mut list = OverrideList::new(HashAlgorithm::PHash, 6);
+        list.add(ImageHash(0x0123_4567_89ab_cdef), OverrideVerdict::Allow);
+        list.add(ImageHash(0xfedc_ba98_7654_3210), OverrideVerdict::Block);
+        let parsed = OverrideList::parse(&format!("# overrides\n{}", list.to_text())).unwrap();
+        assert_eq!(parsed.algorithm(), HashAlgorithm::PHash);
+        assert_eq!(parsed.max_distance(), 6);
+        assert_eq!(parsed.entries().collect::<Vec<_>>(), list.entries().collect::<Vec<_>>());
+
+        assert!(OverrideList::parse("allow = 12345").is_err());
+        assert!(OverrideList::parse("algorithm = md5").is_err());
+        assert!(OverrideList::parse("max_distance = 65").is_err());
+        assert!(OverrideList::parse("allow = 0123456789abcdef\nalgorithm = phash").is_err());
+    }
+}
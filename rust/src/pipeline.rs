@@ -0,0 +1,163 @@
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use crate::image::{blur, crop, paste, pixelate, Rect};
+use crate::inference::{ClassificationResult, MLEngine};
+use crate::phash::{OverrideList, OverrideMatch, OverrideVerdict};
+use crate::policy::{FilterAction, FilterPolicy, PolicyDecision};
+
+/// Per-stage timings of a processed frame
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameTimings {
+    /// Preprocessing + inference, plus the skin-tone ratio and hashing when used
+    pub classify: Duration,
+    /// Blur / pixelation (zero when the frame was allowed)
+    pub effect: Duration,
+    /// Whole pipeline
+    pub total: Duration,
+}
+
+/// Result of classifying a frame and applying the policy to it
+#[derive(Debug, Clone)]
+pub struct FrameOutcome {
+    pub classification: ClassificationResult,
+    pub decision: PolicyDecision,
+    /// Listed image that decided the frame instead of the policy
+    pub override_match: Option<OverrideMatch>,
+    /// Obscured RGBA pixels, `None` when the frame was allowed
+    pub output: Option<Vec<u8>>,
+    pub timings: FrameTimings,
+}
+
+/// Classify an RGBA frame, evaluate the policy and obscure it if needed, in one pass
+pub fn process_frame(
+    engine: &MLEngine,
+    policy: &FilterPolicy,
+    rgba_data: &[u8],
+    width: usize,
+    height: usize,
+) -> Result<FrameOutcome, Box<dyn Error>> {
+    process_frame_with_overrides(engine, policy, &OverrideList::default(), rgba_data, width, height)
+}
+
+/// `process_frame`, except that a frame matching an allowed or blocked image is allowed,
+/// or obscured with the policy's strongest effect, whatever the model says
+/// The model still runs so the outcome reports its classification.
+pub fn process_frame_with_overrides(
+    engine: &MLEngine,
+    policy: &FilterPolicy,
+    overrides: &OverrideList,
+    rgba_data: &[u8],
+    width: usize,
+    height: usize,
+) -> Result<FrameOutcome, Box<dyn Error>> {
+    let start_time = Instant::now();
+
+    let classification = engine.classify(rgba_data, width, height)?;
+    let override_match = overrides.check(rgba_data, width, height)?;
+    let decision = match override_match.map(|m| m.verdict) {
+        Some(OverrideVerdict::Allow) => PolicyDecision {
+            should_filter: false,
+            action: FilterAction::Allow,
+        },
+        Some(OverrideVerdict::Block) => policy.block_decision(),
+        None => policy.evaluate_with_skin_ratio(&classification, policy.skin_ratio_input(rgba_data, width, height)?),
+    };
+    let classify_time = start_time.elapsed();
+
+    let effect_start = Instant::now();
+    let output = apply_action(rgba_data, width, height, decision.action)?;
+    let effect_time = effect_start.elapsed();
+
+    Ok(FrameOutcome {
+        classification,
+        decision,
+        override_match,
+        output,
+        timings: FrameTimings {
+            classify: classify_time,
+            effect: effect_time,
+            total: start_time.elapsed(),
+        },
+    })
+}
+
+/// Apply a policy action to an RGBA frame
+/// Returns `None` for `FilterAction::Allow`
+pub fn apply_action(
+    rgba_data: &[u8],
+    width: usize,
+    height: usize,
+    action: FilterAction,
+) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    match action {
+        FilterAction::Allow => Ok(None),
+        FilterAction::Blur { radius } => Ok(Some(blur(rgba_data, width, height, radius)?)),
+        FilterAction::Pixelate { block_size } => Ok(Some(pixelate(rgba_data, width, height, block_size)?)),
+    }
+}
+
+/// Apply a policy action to regions of an RGBA frame only, e.g. detected text boxes
+/// Returns `None` for `FilterAction::Allow` or when there are no regions.
+pub fn apply_action_to_regions(
+    rgba_data: &[u8],
+    width: usize,
+    height: usize,
+    regions: &[Rect],
+    action: FilterAction,
+) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    if action == FilterAction::Allow || regions.is_empty() {
+        return Ok(None);
+    }
+    let mut output = rgba_data.to_vec();
+    for &region in regions {
+        let patch = crop(&output, width, height, region)?;
+        if let Some(obscured) = apply_action(&patch, region.width, region.height, action)? {
+            paste(&mut output, width, height, region, &obscured)?;
+        }
+    }
+    Ok(Some(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_action_allow() {
+        let image_data = vec![128u8; 10 * 10 * 4];
+        assert!(apply_action(&image_data, 10, 10, FilterAction::Allow).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_apply_action_effects() {
+        let image_data = vec![128u8; 10 * 10 * 4];
+
+        let blurred = apply_action(&image_data, 10, 10, FilterAction::Blur { radius: 2.0 }).unwrap();
+        assert_eq!(blurred.unwrap().len(), image_data.len());
+
+        let pixelated = apply_action(&image_data, 10, 10, FilterAction::Pixelate { block_size: 4 }).unwrap();
+        assert_eq!(pixelated.unwrap().len(), image_data.len());
+    }
+
+    #[test]
+    fn test_apply_action_to_regions() {
+        // Vertical stripes, so pixelation visibly changes a region
+        let image_data: Vec<u8> = (0..10 * 10).flat_map(|i| if i % 2 == 0 { [0, 0, 0, 255] } else { [255; 4] }).collect();
+        let region = Rect::new(2, 3, 4, 4);
+
+        let output = apply_action_to_regions(&image_data, 10, 10, &[region], FilterAction::Pixelate { block_size: 4 })
+            .unwrap()
+            .unwrap();
+        for y in 0..10 {
+            for x in 0..10 {
+                let i = (y * 10 + x) * 4;
+                let inside = (2..6).contains(&x) && (3..7).contains(&y);
+                assert_eq!(output[i..i + 4] != image_data[i..i + 4], inside, "pixel ({}, {})", x, y);
+            }
+        }
+
+        assert!(apply_action_to_regions(&image_data, 10, 10, &[], FilterAction::Blur { radius: 2.0 }).unwrap().is_none());
+        assert!(apply_action_to_regions(&image_data, 10, 10, &[Rect::new(8, 8, 4, 4)], FilterAction::Blur { radius: 2.0 }).is_err());
+    }
+}
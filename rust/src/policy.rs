@@ -0,0 +1,382 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::analysis;
+use crate::inference::{ClassificationResult, SafetyGroup};
+
+/// Obscuring action selected by the policy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterAction {
+    /// Leave the frame untouched
+    Allow,
+    /// Box-blur the frame with the given radius
+    Blur { radius: f32 },
+    /// Pixelate the frame with the given block size
+    Pixelate { block_size: usize },
+}
+
+impl FilterAction {
+    /// Numeric code shared with the Kotlin side (0 = allow, 1 = blur, 2 = pixelate)
+    pub fn code(&self) -> i32 {
+        match self {
+            FilterAction::Allow => 0,
+            FilterAction::Blur { .. } => 1,
+            FilterAction::Pixelate { .. } => 2,
+        }
+    }
+
+    /// Effect parameter (blur radius or block size), 0 for `Allow`
+    pub fn parameter(&self) -> f32 {
+        match self {
+            FilterAction::Allow => 0.0,
+            FilterAction::Blur { radius } => *radius,
+            FilterAction::Pixelate { block_size } => *block_size as f32,
+        }
+    }
+}
+
+/// Effect family used when a frame must be obscured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObscureMode {
+    Blur,
+    Pixelate,
+}
+
+impl ObscureMode {
+    /// Name used in policy profiles
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObscureMode::Blur => "blur",
+            ObscureMode::Pixelate => "pixelate",
+        }
+    }
+
+    /// Parse a profile name (`blur` or `pixelate`)
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "blur" => Some(ObscureMode::Blur),
+            "pixelate" => Some(ObscureMode::Pixelate),
+            _ => None,
+        }
+    }
+}
+
+/// Final decision for a classified frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyDecision {
+    /// Whether the frame must be obscured
+    pub should_filter: bool,
+    /// Action to apply (always `Allow` when `should_filter` is false)
+    pub action: FilterAction,
+}
+
+/// Filtering policy: thresholds and effect strength per content group
+#[derive(Debug, Clone)]
+pub struct FilterPolicy {
+    /// Minimum confidence to filter adult content (porn, hentai) and other unsafe frames
+    pub unsafe_threshold: f32,
+    /// Minimum confidence to filter suggestive content (sexy)
+    pub suggestive_threshold: f32,
+    /// Effect family applied to filtered frames
+    pub mode: ObscureMode,
+    /// Suggestive frames with a lower skin-tone ratio are allowed (0 disables the check)
+    pub min_skin_ratio: f32,
+}
+
+impl Default for FilterPolicy {
+    fn default() -> Self {
+        FilterPolicy {
+            unsafe_threshold: 0.60,
+            suggestive_threshold: 0.80,
+            mode: ObscureMode::Blur,
+            min_skin_ratio: 0.0,
+        }
+    }
+}
+
+impl FilterPolicy {
+    /// Decide whether and how to obscure a frame from its classification
+    pub fn evaluate(&self, result: &ClassificationResult) -> PolicyDecision {
+        let group = result.safety_group();
+        let is_adult = group == SafetyGroup::Adult;
+        let is_suggestive = group == SafetyGroup::Suggestive;
+
+        let should_filter = if is_adult {
+            // Adult content (porn/hentai): filter if above threshold
+            result.confidence >= self.unsafe_threshold
+        } else if is_suggestive && result.confidence >= self.suggestive_threshold {
+            // Suggestive content (sexy): higher threshold required
+            true
+        } else {
+            // Catch-all: filter if unsafe score is high
+            !result.is_safe && result.confidence >= self.unsafe_threshold
+        };
+
+        if !should_filter {
+            return PolicyDecision {
+                should_filter,
+                action: FilterAction::Allow,
+            };
+        }
+
+        PolicyDecision {
+            should_filter,
+            action: self.action(Self::blur_radius(is_adult, is_suggestive, result.confidence)),
+        }
+    }
+
+    /// Decision for a frame the user always wants obscured: the strongest effect of the mode
+    pub fn block_decision(&self) -> PolicyDecision {
+        PolicyDecision {
+            should_filter: true,
+            action: self.action(Self::blur_radius(true, false, 1.0)),
+        }
+    }
+
+    /// Effect of the policy's mode for a blur radius
+    fn action(&self, radius: f32) -> FilterAction {
+        match self.mode {
+            ObscureMode::Blur => FilterAction::Blur { radius },
+            // Scale block size with blur strength so both modes obscure comparably
+            ObscureMode::Pixelate => FilterAction::Pixelate {
+                block_size: (radius * 2.0) as usize,
+            },
+        }
+    }
+
+    /// Whether `evaluate_with_skin_ratio` needs the frame's skin-tone ratio
+    pub fn uses_skin_ratio(&self) -> bool {
+        self.min_skin_ratio > 0.0
+    }
+
+    /// Skin-tone ratio of a frame (`analysis::skin_ratio`) if the policy uses it, else `None`
+    pub fn skin_ratio_input(&self, rgba_data: &[u8], width: usize, height: usize) -> Result<Option<f32>, Box<dyn Error>> {
+        if self.uses_skin_ratio() {
+            analysis::skin_ratio(rgba_data, width, height).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// `evaluate`, then allow suggestive frames showing too little skin for the model's verdict
+    /// The "sexy" class is the least reliable one, and without visible skin its verdict is
+    /// usually a false positive. Every path that decides frames goes through here with the
+    /// ratio from `skin_ratio_input`; `None` (no pixels at hand) skips the check.
+    pub fn evaluate_with_skin_ratio(&self, result: &ClassificationResult, skin_ratio: Option<f32>) -> PolicyDecision {
+        let decision = self.evaluate(result);
+        match skin_ratio {
+            Some(skin_ratio)
+                if decision.should_filter
+                    && result.safety_group() == SafetyGroup::Suggestive
+                    && skin_ratio < self.min_skin_ratio =>
+            {
+                PolicyDecision {
+                    should_filter: false,
+                    action: FilterAction::Allow,
+                }
+            }
+            _ => decision,
+        }
+    }
+
+    /// Parse a policy profile
+    ///
+    /// Profiles are plain text with one `key = value` per line and `#` comments:
+    /// `unsafe_threshold`, `suggestive_threshold`, `min_skin_ratio` (0..=1) and `mode`
+    /// (`blur` or `pixelate`).
+    /// Missing keys keep their default value.
+    pub fn from_profile(text: &str) -> Result<Self, Box<dyn Error>> {
+        let mut policy = FilterPolicy::default();
+
+        for (index, raw_line) in text.lines().enumerate() {
+            let line = raw_line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| format!("line {}: expected 'key = value', got '{}'", index + 1, line))?;
+
+            match key {
+                "unsafe_threshold" => policy.unsafe_threshold = parse_threshold(index, key, value)?,
+                "suggestive_threshold" => policy.suggestive_threshold = parse_threshold(index, key, value)?,
+                "min_skin_ratio" => policy.min_skin_ratio = parse_threshold(index, key, value)?,
+                "mode" => {
+                    policy.mode = ObscureMode::parse(value)
+                        .ok_or_else(|| format!("line {}: unknown mode '{}' (blur or pixelate)", index + 1, value))?
+                }
+                _ => return Err(format!("line {}: unknown key '{}'", index + 1, key).into()),
+            }
+        }
+
+        Ok(policy)
+    }
+
+    /// Load a policy profile from a file
+    pub fn load_profile(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Cannot read policy {}: {}", path.display(), e))?;
+        Self::from_profile(&text).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    /// Serialize as a policy profile readable by `from_profile`
+    pub fn to_profile(&self) -> String {
+        format!(
+            "unsafe_threshold = {}\nsuggestive_threshold = {}\nmode = {}\nmin_skin_ratio = {}\n",
+            self.unsafe_threshold,
+            self.suggestive_threshold,
+            self.mode.as_str(),
+            self.min_skin_ratio
+        )
+    }
+
+    /// Blur radius by content group and confidence
+    /// Adult content gets stronger blur than suggestive content
+    fn blur_radius(is_adult: bool, is_suggestive: bool, confidence: f32) -> f32 {
+        if is_adult {
+            if confidence > 0.95 {
+                25.0
+            } else if confidence > 0.85 {
+                20.0
+            } else if confidence > 0.75 {
+                15.0
+            } else {
+                12.0
+            }
+        } else if is_suggestive {
+            if confidence > 0.95 {
+                15.0
+            } else if confidence > 0.85 {
+                10.0
+            } else {
+                8.0
+            }
+        } else {
+            5.0
+        }
+    }
+}
+
+fn parse_threshold(index: usize, key: &str, value: &str) -> Result<f32, Box<dyn Error>> {
+    match value.parse::<f32>() {
+        Ok(threshold) if (0.0..=1.0).contains(&threshold) => Ok(threshold),
+        _ => Err(format!("line {}: {} must be a number between 0 and 1, got '{}'", index + 1, key, value).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CLASSES;
+
+    fn result(top_class_index: usize, is_safe: bool, confidence: f32) -> ClassificationResult {
+        ClassificationResult {
+            scores: [0.0; 5],
+            top_class_index,
+            top_class: CLASSES[top_class_index].to_string(),
+            is_safe,
+            confidence,
+        }
+    }
+
+    #[test]
+    fn test_safe_content_allowed() {
+        let decision = FilterPolicy::default().evaluate(&result(2, true, 0.9));
+        assert!(!decision.should_filter);
+        assert_eq!(decision.action, FilterAction::Allow);
+    }
+
+    #[test]
+    fn test_adult_content_blurred_by_confidence() {
+        let policy = FilterPolicy::default();
+        assert_eq!(policy.evaluate(&result(3, false, 0.97)).action, FilterAction::Blur { radius: 25.0 });
+        assert_eq!(policy.evaluate(&result(1, false, 0.65)).action, FilterAction::Blur { radius: 12.0 });
+        assert!(!policy.evaluate(&result(3, false, 0.55)).should_filter);
+    }
+
+    #[test]
+    fn test_suggestive_needs_higher_threshold() {
+        let policy = FilterPolicy::default();
+        assert!(!policy.evaluate(&result(4, true, 0.7)).should_filter);
+        assert_eq!(policy.evaluate(&result(4, false, 0.9)).action, FilterAction::Blur { radius: 10.0 });
+        // Below `suggestive_threshold`, but the catch-all applies `unsafe_threshold`
+        assert!(policy.evaluate(&result(4, false, 0.7)).should_filter);
+    }
+
+    #[test]
+    fn test_safe_top_class_outweighed_uses_unsafe_threshold() {
+        let policy = FilterPolicy::default();
+        assert_eq!(policy.evaluate(&result(2, false, 0.65)).action, FilterAction::Blur { radius: 5.0 });
+        assert!(!policy.evaluate(&result(0, false, 0.55)).should_filter);
+    }
+
+    #[test]
+    fn test_block_decision_uses_strongest_effect() {
+        assert_eq!(FilterPolicy::default().block_decision().action, FilterAction::Blur { radius: 25.0 });
+        let pixelate = FilterPolicy {
+            mode: ObscureMode::Pixelate,
+            ..FilterPolicy::default()
+        };
+        assert_eq!(pixelate.block_decision().action, FilterAction::Pixelate { block_size: 50 });
+    }
+
+    #[test]
+    fn test_pixelate_mode() {
+        let policy = FilterPolicy {
+            mode: ObscureMode::Pixelate,
+            ..FilterPolicy::default()
+        };
+        let decision = policy.evaluate(&result(3, false, 0.9));
+        assert_eq!(decision.action, FilterAction::Pixelate { block_size: 40 });
+        assert_eq!(decision.action.code(), 2);
+    }
+
+    #[test]
+    fn test_profile_round_trip() {
+        let policy = FilterPolicy {
+            unsafe_threshold: 0.42,
+            suggestive_threshold: 0.9,
+            mode: ObscureMode::Pixelate,
+            min_skin_ratio: 0.15,
+        };
+        let parsed = FilterPolicy::from_profile(&policy.to_profile()).unwrap();
+        assert_eq!(parsed.unsafe_threshold, 0.42);
+        assert_eq!(parsed.suggestive_threshold, 0.9);
+        assert_eq!(parsed.mode, ObscureMode::Pixelate);
+        assert_eq!(parsed.min_skin_ratio, 0.15);
+    }
+
+    #[test]
+    fn test_min_skin_ratio_allows_suggestive_frames() {
+        let policy = FilterPolicy {
+            min_skin_ratio: 0.1,
+            ..FilterPolicy::default()
+        };
+        assert!(policy.uses_skin_ratio());
+        assert!(!FilterPolicy::default().uses_skin_ratio());
+        assert_eq!(FilterPolicy::default().skin_ratio_input(&[0; 16], 2, 2).unwrap(), None);
+
+        assert!(!policy.evaluate_with_skin_ratio(&result(4, false, 0.9), Some(0.02)).should_filter);
+        assert!(policy.evaluate_with_skin_ratio(&result(4, false, 0.9), Some(0.3)).should_filter);
+        assert!(policy.evaluate_with_skin_ratio(&result(4, false, 0.9), None).should_filter);
+        // Adult content is never let through on skin ratio alone
+        assert!(policy.evaluate_with_skin_ratio(&result(3, false, 0.9), Some(0.0)).should_filter);
+    }
+
+    #[test]
+    fn test_profile_comments_and_defaults() {
+        let policy = FilterPolicy::from_profile("# tuned\n\nsuggestive_threshold = 0.7 # fpr 1%\n").unwrap();
+        assert_eq!(policy.unsafe_threshold, 0.60);
+        assert_eq!(policy.suggestive_threshold, 0.7);
+        assert_eq!(policy.mode, ObscureMode::Blur);
+    }
+
+    #[test]
+    fn test_profile_errors() {
+        assert!(FilterPolicy::from_profile("unsafe_threshold = 1.5").is_err());
+        assert!(FilterPolicy::from_profile("unsafe_threshold 0.5").is_err());
+        assert!(FilterPolicy::from_profile("mode = sepia").is_err());
+        assert!(FilterPolicy::from_profile("colour = red").is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Handle-based registry of shared engines.
+//!
+//! Foreign callers (JNI, C) hold an opaque `u64` handle instead of a pointer.
+//! Lookups clone an `Arc`, so the registry lock is only held for the map
+//! access: removing an engine never waits for in-flight work, which keeps
+//! running on its own reference until it finishes.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Handle value that never refers to a registered entry
+pub const INVALID_HANDLE: u64 = 0;
+
+/// Thread-safe map from opaque handles to shared values
+pub struct Registry<T> {
+    entries: RwLock<HashMap<u64, Arc<T>>>,
+    next_handle: AtomicU64,
+}
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Registry<T> {
+    pub fn new() -> Self {
+        Registry {
+            entries: RwLock::new(HashMap::new()),
+            next_handle: AtomicU64::new(INVALID_HANDLE + 1),
+        }
+    }
+
+    /// Register a value and return its handle
+    pub fn insert(&self, value: T) -> u64 {
+        let handle = self.next_handle.fetch_add(1, Ordering::Relaxed);
+        self.write().insert(handle, Arc::new(value));
+        handle
+    }
+
+    /// Shared reference to the value behind a handle
+    pub fn get(&self, handle: u64) -> Option<Arc<T>> {
+        self.read().get(&handle).cloned()
+    }
+
+    /// Unregister a handle; the value is dropped once the last in-flight user releases it
+    pub fn remove(&self, handle: u64) -> Option<Arc<T>> {
+        self.write().remove(&handle)
+    }
+
+    /// Number of registered values
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The map is never left half-updated, so a panic in another holder does not invalidate it
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<u64, Arc<T>>> {
+        self.entries.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<u64, Arc<T>>> {
+        self.entries.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_insert_get_remove() {
+        let registry = Registry::new();
+        let a = registry.insert("a");
+        let b = registry.insert("b");
+
+        assert_ne!(a, INVALID_HANDLE);
+        assert_ne!(a, b);
+        assert_eq!(*registry.get(a).unwrap(), "a");
+        assert_eq!(registry.len(), 2);
+
+        assert!(registry.remove(a).is_some());
+        assert!(registry.get(a).is_none());
+        assert!(registry.remove(a).is_none());
+        assert!(registry.get(INVALID_HANDLE).is_none());
+    }
+
+    #[test]
+    fn test_removed_value_outlives_in_flight_user() {
+        let registry = Registry::new();
+        let handle = registry.insert(vec![1u8, 2, 3]);
+
+        let in_flight = registry.get(handle).unwrap();
+        registry.remove(handle);
+        assert_eq!(*in_flight, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_concurrent_access() {
+        let registry = Arc::new(Registry::new());
+        let workers: Vec<_> = (0..8)
+            .map(|i| {
+                let registry = Arc::clone(&registry);
+                thread::spawn(move || {
+                    let handle = registry.insert(i);
+                    assert_eq!(*registry.get(handle).unwrap(), i);
+                    handle
+                })
+            })
+            .collect();
+
+        let mut handles: Vec<u64> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+        handles.sort();
+        handles.dedup();
+        assert_eq!(handles.len(), 8);
+        assert_eq!(registry.len(), 8);
+    }
+}
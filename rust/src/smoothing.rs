@@ -0,0 +1,196 @@
+//! Temporal smoothing of per-frame decisions.
+//!
+//! Single frames of a video can swing between classes, which makes the overlay
+//! flicker on and off. The smoother averages scores over time (an exponential
+//! moving average with a half-life, so irregular frame timing is handled) and
+//! keeps a filter in place for a hold period after the last frame that needed it.
+
+use std::time::Duration;
+
+use crate::config::NUM_CLASSES;
+use crate::inference::ClassificationResult;
+use crate::policy::{FilterAction, FilterPolicy, PolicyDecision};
+
+/// Smoothing parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SmoothingConfig {
+    /// Time for an old frame's weight in the score average to halve (zero disables averaging)
+    pub half_life: Duration,
+    /// How long a filter stays applied after the last frame that required it
+    pub hold: Duration,
+}
+
+impl Default for SmoothingConfig {
+    fn default() -> Self {
+        SmoothingConfig {
+            half_life: Duration::from_millis(300),
+            hold: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Decision for one frame after smoothing
+#[derive(Debug, Clone)]
+pub struct SmoothedFrame {
+    /// Classification rebuilt from the time-averaged scores
+    pub classification: ClassificationResult,
+    pub decision: PolicyDecision,
+    /// Whether the filter is only applied because of the hold period
+    pub held: bool,
+}
+
+/// Per-stream smoothing state; feed frames in timestamp order
+#[derive(Debug, Clone)]
+pub struct TemporalSmoother {
+    config: SmoothingConfig,
+    scores: Option<[f32; NUM_CLASSES]>,
+    last_timestamp: Duration,
+    /// Timestamp and action of the last frame the policy itself filtered
+    last_filter: Option<(Duration, FilterAction)>,
+}
+
+impl TemporalSmoother {
+    pub fn new(config: SmoothingConfig) -> Self {
+        TemporalSmoother {
+            config,
+            scores: None,
+            last_timestamp: Duration::ZERO,
+            last_filter: None,
+        }
+    }
+
+    /// Forget all history, e.g. when the stream restarts
+    pub fn reset(&mut self) {
+        self.scores = None;
+        self.last_filter = None;
+    }
+
+    /// Fold a frame's raw classification into the history and decide for this frame
+    /// `skin_ratio` is this frame's policy input (`FilterPolicy::skin_ratio_input`).
+    /// A timestamp earlier than the previous frame resets the history.
+    pub fn update(
+        &mut self,
+        timestamp: Duration,
+        raw: &ClassificationResult,
+        skin_ratio: Option<f32>,
+        policy: &FilterPolicy,
+    ) -> SmoothedFrame {
+        if timestamp < self.last_timestamp {
+            self.reset();
+        }
+        let elapsed = timestamp.saturating_sub(self.last_timestamp);
+        self.last_timestamp = timestamp;
+
+        let scores = match self.scores {
+            Some(previous) if !self.config.half_life.is_zero() => {
+                // Weight kept by the history after `elapsed`: 0.5 per half-life
+                let keep = 0.5f32.powf(elapsed.as_secs_f32() / self.config.half_life.as_secs_f32());
+                std::array::from_fn(|i| keep * previous[i] + (1.0 - keep) * raw.scores[i])
+            }
+            _ => raw.scores,
+        };
+        self.scores = Some(scores);
+
+        let classification = ClassificationResult::from_scores(scores);
+        let decision = policy.evaluate_with_skin_ratio(&classification, skin_ratio);
+
+        if decision.should_filter {
+            self.last_filter = Some((timestamp, decision.action));
+            return SmoothedFrame {
+                classification,
+                decision,
+                held: false,
+            };
+        }
+
+        match self.last_filter {
+            Some((filtered_at, action)) if timestamp - filtered_at < self.config.hold => SmoothedFrame {
+                classification,
+                decision: PolicyDecision {
+                    should_filter: true,
+                    action,
+                },
+                held: true,
+            },
+            _ => SmoothedFrame {
+                classification,
+                decision,
+                held: false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORN: [f32; NUM_CLASSES] = [0.0, 0.0, 0.02, 0.98, 0.0];
+    const NEUTRAL: [f32; NUM_CLASSES] = [0.0, 0.0, 1.0, 0.0, 0.0];
+
+    fn at(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_single_frame_spike_is_damped() {
+        let policy = FilterPolicy::default();
+        let mut smoother = TemporalSmoother::new(SmoothingConfig::default());
+
+        for t in [0, 33, 66] {
+            smoother.update(at(t), &ClassificationResult::from_scores(NEUTRAL), None, &policy);
+        }
+        let spike = smoother.update(at(100), &ClassificationResult::from_scores(PORN), None, &policy);
+        assert!(!spike.decision.should_filter);
+        assert!(spike.classification.scores[3] < 0.2);
+    }
+
+    #[test]
+    fn test_filter_held_after_content_disappears() {
+        let policy = FilterPolicy::default();
+        let config = SmoothingConfig {
+            half_life: Duration::ZERO,
+            hold: at(500),
+        };
+        let mut smoother = TemporalSmoother::new(config);
+
+        assert!(smoother.update(at(0), &ClassificationResult::from_scores(PORN), None, &policy).decision.should_filter);
+
+        let held = smoother.update(at(400), &ClassificationResult::from_scores(NEUTRAL), None, &policy);
+        assert!(held.held);
+        assert!(held.decision.should_filter);
+
+        let released = smoother.update(at(500), &ClassificationResult::from_scores(NEUTRAL), None, &policy);
+        assert!(!released.held);
+        assert_eq!(released.decision.action, FilterAction::Allow);
+    }
+
+    #[test]
+    fn test_skin_ratio_reaches_the_policy() {
+        const SEXY: [f32; NUM_CLASSES] = [0.0, 0.0, 0.05, 0.0, 0.95];
+        let policy = FilterPolicy {
+            min_skin_ratio: 0.2,
+            ..FilterPolicy::default()
+        };
+        let config = SmoothingConfig {
+            half_life: Duration::ZERO,
+            hold: Duration::ZERO,
+        };
+        let mut smoother = TemporalSmoother::new(config);
+
+        let sexy = ClassificationResult::from_scores(SEXY);
+        assert!(!smoother.update(at(0), &sexy, Some(0.05), &policy).decision.should_filter);
+        assert!(smoother.update(at(33), &sexy, Some(0.4), &policy).decision.should_filter);
+    }
+
+    #[test]
+    fn test_going_back_in_time_resets() {
+        let policy = FilterPolicy::default();
+        let mut smoother = TemporalSmoother::new(SmoothingConfig::default());
+
+        smoother.update(at(1000), &ClassificationResult::from_scores(PORN), None, &policy);
+        let restarted = smoother.update(at(0), &ClassificationResult::from_scores(NEUTRAL), None, &policy);
+        assert_eq!(restarted.classification.scores, NEUTRAL);
+        assert!(!restarted.decision.should_filter);
+    }
+}
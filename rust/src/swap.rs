@@ -0,0 +1,176 @@
+//! Hot-swappable engine.
+//!
+//! Replacing the model used to mean destroying the engine and creating a new one,
+//! with a window in which frames fell back to neutral. `SwappableEngine` loads the
+//! candidate model beside the live one, runs a self-test on it, and only then
+//! replaces the shared `Arc` in one step. Classifications already running keep
+//! their reference to the old engine and finish on it; a candidate that fails the
+//! self-test is dropped and the live engine stays in place.
+
+use std::error::Error;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::thread::{self, JoinHandle};
+
+use crate::config::{MODEL_INPUT_HEIGHT, MODEL_INPUT_WIDTH};
+use crate::inference::{ClassificationResult, MLEngine};
+
+/// Frame a candidate model must classify before it is swapped in
+#[derive(Debug, Clone)]
+pub struct SelfTestCase {
+    pub rgba: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+    /// Class the model must predict, or `None` to only check the output is well-formed
+    pub expected_class: Option<usize>,
+}
+
+/// Validation run on every candidate model
+/// Each case must classify without error into probabilities that are not all zero.
+#[derive(Debug, Clone)]
+pub struct SelfTest {
+    pub cases: Vec<SelfTestCase>,
+}
+
+impl Default for SelfTest {
+    /// A single mid-grey frame at the model input size, no expected class
+    fn default() -> Self {
+        SelfTest {
+            cases: vec![SelfTestCase {
+                rgba: [128, 128, 128, 255].repeat(MODEL_INPUT_WIDTH * MODEL_INPUT_HEIGHT),
+                width: MODEL_INPUT_WIDTH,
+                height: MODEL_INPUT_HEIGHT,
+                expected_class: None,
+            }],
+        }
+    }
+}
+
+impl SelfTest {
+    /// Run every case against `engine`, failing on the first bad output
+    pub fn run(&self, engine: &MLEngine) -> Result<(), Box<dyn Error>> {
+        for (index, case) in self.cases.iter().enumerate() {
+            let result = engine
+                .classify(&case.rgba, case.width, case.height)
+                .map_err(|e| format!("Self-test case {}: classification failed: {}", index, e))?;
+            check_probabilities(&result).map_err(|e| format!("Self-test case {}: {}", index, e))?;
+            if let Some(expected) = case.expected_class {
+                if result.top_class_index != expected {
+                    return Err(format!(
+                        "Self-test case {}: expected class {}, got {} ({})",
+                        index, expected, result.top_class_index, result.top_class
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_probabilities(result: &ClassificationResult) -> Result<(), String> {
+    // Non-finite outputs were already zeroed by `from_scores`, so they show up as all zeros.
+    // Sigmoid outputs need not sum to 1, so only the range is checked.
+    if result.scores.iter().any(|&s| !(0.0..=1.0).contains(&s)) || result.scores.iter().all(|&s| s == 0.0) {
+        return Err(format!("outputs are not probabilities: {:?}", result.scores));
+    }
+    Ok(())
+}
+
+/// Live engine plus the one it replaced, for rollback
+struct Slots {
+    current: Arc<MLEngine>,
+    previous: Option<Arc<MLEngine>>,
+    generation: u64,
+}
+
+/// Engine whose model can be replaced while classifications are running
+pub struct SwappableEngine {
+    slots: RwLock<Slots>,
+    self_test: SelfTest,
+}
+
+impl SwappableEngine {
+    /// Wrap an already loaded engine, validating future swaps with the default self-test
+    pub fn new(engine: MLEngine) -> Self {
+        Self::with_self_test(engine, SelfTest::default())
+    }
+
+    pub fn with_self_test(engine: MLEngine, self_test: SelfTest) -> Self {
+        SwappableEngine {
+            slots: RwLock::new(Slots {
+                current: Arc::new(engine),
+                previous: None,
+                generation: 0,
+            }),
+            self_test,
+        }
+    }
+
+    /// Engine to classify with; hold the `Arc` for the whole classification
+    pub fn current(&self) -> Arc<MLEngine> {
+        Arc::clone(&self.read().current)
+    }
+
+    /// Number of successful swaps and rollbacks so far
+    pub fn generation(&self) -> u64 {
+        self.read().generation
+    }
+
+    /// Self-test `candidate` and make it the live engine, returning the new generation
+    /// On failure the live engine is untouched and the candidate is dropped.
+    pub fn swap(&self, candidate: MLEngine) -> Result<u64, Box<dyn Error>> {
+        self.self_test.run(&candidate)?;
+
+        let mut slots = self.write();
+        let replaced = std::mem::replace(&mut slots.current, Arc::new(candidate));
+        slots.previous = Some(replaced);
+        slots.generation += 1;
+        log::info!(
+            "Swapped in model {} (generation {})",
+            slots.current.model_path(),
+            slots.generation
+        );
+        Ok(slots.generation)
+    }
+
+    /// Return to the engine replaced by the last swap; false if there is none
+    pub fn rollback(&self) -> bool {
+        let mut slots = self.write();
+        let Some(previous) = slots.previous.take() else {
+            return false;
+        };
+        slots.current = previous;
+        slots.generation += 1;
+        log::info!(
+            "Rolled back to model {} (generation {})",
+            slots.current.model_path(),
+            slots.generation
+        );
+        true
+    }
+
+    /// Load and swap in a model on a background thread
+    /// The join handle yields the new generation, or why loading or the self-test failed.
+    pub fn spawn_swap<F>(self: &Arc<Self>, load: F) -> JoinHandle<Result<u64, String>>
+    where
+        F: FnOnce() -> Result<MLEngine, Box<dyn Error>> + Send + 'static,
+    {
+        let engine = Arc::clone(self);
+        thread::spawn(move || {
+            let result = load().and_then(|candidate| engine.swap(candidate)).map_err(|e| e.to_string());
+            if let Err(message) = &result {
+                log::error!("Model swap failed, keeping the current model: {}", message);
+            }
+            result
+        })
+    }
+
+    // Slots are replaced with single assignments, so a poisoned lock still holds valid engines
+    fn read(&self) -> RwLockReadGuard<'_, Slots> {
+        self.slots.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Slots> {
+        self.slots.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
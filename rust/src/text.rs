@@ -0,0 +1,420 @@
+//! Text region detection.
+//!
+//! First step towards filtering on-screen text: an ONNX text detector run through tract
+//! locates text in a frame and its output maps are decoded into bounding boxes. The boxes
+//! can be obscured with the existing effects (`pipeline::apply_action_to_regions`) or
+//! cropped (`image::crop`) and handed to a recognizer.
+//!
+//! Two output conventions are supported, both channel-first:
+//! - DB (Differentiable Binarization): one text probability map `[1, 1, h, w]`.
+//! - EAST: a score map `[1, 1, h, w]` and a geometry map `[1, 5, h, w]` holding, per cell,
+//!   the distances to the top, right, bottom and left edges of its box (input pixels)
+//!   and the box angle (radians).
+
+use std::error::Error;
+use std::fs;
+
+use tract_onnx::prelude::*;
+
+use crate::image::{preprocess, PreprocessSpec, Rect};
+use crate::inference::RunnableModel;
+
+/// Model output: shape and values
+type Output = (Vec<usize>, Vec<f32>);
+
+/// Output convention of a text detection model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextDetectorKind {
+    Db,
+    East,
+}
+
+impl TextDetectorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TextDetectorKind::Db => "db",
+            TextDetectorKind::East => "east",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "db" => Some(TextDetectorKind::Db),
+            "east" => Some(TextDetectorKind::East),
+            _ => None,
+        }
+    }
+}
+
+/// Box decoding parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextDetectionConfig {
+    /// Map value above which a cell counts as text (DB probability, EAST score)
+    pub score_threshold: f32,
+    /// DB: minimum mean probability over a region for it to become a box
+    pub box_threshold: f32,
+    /// DB: each side grows by area × ratio / perimeter, undoing the shrinking of text
+    /// regions the model was trained with
+    pub unclip_ratio: f32,
+    /// EAST: overlap (intersection over union) above which the weaker of two boxes is dropped
+    pub nms_iou: f32,
+    /// Boxes narrower or shorter than this many frame pixels are dropped
+    pub min_size: usize,
+}
+
+impl TextDetectionConfig {
+    /// Usual values for each kind (PaddleOCR for DB, the OpenCV sample for EAST)
+    pub fn for_kind(kind: TextDetectorKind) -> Self {
+        match kind {
+            TextDetectorKind::Db => TextDetectionConfig {
+                score_threshold: 0.3,
+                box_threshold: 0.6,
+                unclip_ratio: 1.5,
+                nms_iou: 0.4,
+                min_size: 3,
+            },
+            TextDetectorKind::East => TextDetectionConfig {
+                score_threshold: 0.8,
+                box_threshold: 0.0,
+                unclip_ratio: 0.0,
+                nms_iou: 0.4,
+                min_size: 3,
+            },
+        }
+    }
+}
+
+/// Detected text region
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TextBox {
+    /// Axis-aligned bounds in frame pixels (rotated EAST boxes are enclosed)
+    pub rect: Rect,
+    pub score: f32,
+}
+
+/// Box in continuous coordinates, before scaling to the frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bounds {
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    score: f32,
+}
+
+impl Bounds {
+    fn area(&self) -> f32 {
+        (self.x1 - self.x0).max(0.0) * (self.y1 - self.y0).max(0.0)
+    }
+
+    fn iou(&self, other: &Bounds) -> f32 {
+        let intersection = Bounds {
+            x0: self.x0.max(other.x0),
+            y0: self.y0.max(other.y0),
+            x1: self.x1.min(other.x1),
+            y1: self.y1.min(other.y1),
+            score: 0.0,
+        }
+        .area();
+        let union = self.area() + other.area() - intersection;
+        if union > 0.0 {
+            intersection / union
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Text detection model
+pub struct TextDetector {
+    model: RunnableModel,
+    kind: TextDetectorKind,
+    input: PreprocessSpec,
+    config: TextDetectionConfig,
+}
+
+impl TextDetector {
+    /// Load an ONNX text detector from a file
+    pub fn new(model_path: &str, kind: TextDetectorKind, input: PreprocessSpec) -> Result<Self, Box<dyn Error>> {
+        let bytes = fs::read(model_path).map_err(|e| format!("Cannot read text detector {}: {}", model_path, e))?;
+        Self::from_bytes(&bytes, kind, input, model_path)
+    }
+
+    /// Load an ONNX text detector from memory
+    /// `input` is typically the `input` of the model's `ModelManifest`; detectors want far
+    /// larger inputs than the classifier's 224×224 (e.g. 640×640 for DB, 320×320 for EAST).
+    pub fn from_bytes(bytes: &[u8], kind: TextDetectorKind, input: PreprocessSpec, source: &str) -> Result<Self, Box<dyn Error>> {
+        if bytes.is_empty() {
+            return Err("Text detector model data is empty".into());
+        }
+        input.validate()?;
+        log::info!("Loading {} text detector: {} ({} bytes)", kind.as_str(), source, bytes.len());
+
+        let model = tract_onnx::onnx()
+            .model_for_read(&mut &bytes[..])?
+            .with_input_fact(0, f32::fact(input.shape()).into())?
+            .into_optimized()?
+            .into_runnable()?;
+        let detector = TextDetector {
+            model,
+            kind,
+            input,
+            config: TextDetectionConfig::for_kind(kind),
+        };
+
+        // Probe once so an incompatible model fails here rather than on the first frame
+        let probe = detector.run(&vec![0.0; detector.input.tensor_len()])?;
+        detector.decode(&probe)?;
+        Ok(detector)
+    }
+
+    pub fn with_config(mut self, config: TextDetectionConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn kind(&self) -> TextDetectorKind {
+        self.kind
+    }
+
+    pub fn config(&self) -> &TextDetectionConfig {
+        &self.config
+    }
+
+    pub fn preprocess_spec(&self) -> &PreprocessSpec {
+        &self.input
+    }
+
+    /// Text regions of an RGBA frame, highest score first
+    pub fn detect(&self, rgba_data: &[u8], width: usize, height: usize) -> Result<Vec<TextBox>, Box<dyn Error>> {
+        let outputs = self.run(&preprocess(rgba_data, width, height, &self.input)?)?;
+        let (bounds, scale_width, scale_height) = self.decode(&outputs)?;
+
+        let (scale_x, scale_y) = (width as f32 / scale_width as f32, height as f32 / scale_height as f32);
+        let mut boxes: Vec<TextBox> = bounds
+            .into_iter()
+            .filter_map(|b| {
+                let x0 = (b.x0 * scale_x).floor().clamp(0.0, width as f32) as usize;
+                let y0 = (b.y0 * scale_y).floor().clamp(0.0, height as f32) as usize;
+                let x1 = (b.x1 * scale_x).ceil().clamp(0.0, width as f32) as usize;
+                let y1 = (b.y1 * scale_y).ceil().clamp(0.0, height as f32) as usize;
+                let min_size = self.config.min_size.max(1);
+                (x1 >= x0 + min_size && y1 >= y0 + min_size).then(|| TextBox {
+                    rect: Rect::new(x0, y0, x1 - x0, y1 - y0),
+                    score: b.score,
+                })
+            })
+            .collect();
+        boxes.sort_by(|a, b| b.score.total_cmp(&a.score));
+        Ok(boxes)
+    }
+
+    /// Every model output with its shape
+    fn run(&self, input_tensor: &[f32]) -> Result<Vec<Output>, Box<dyn Error>> {
+        let [n, a, b, c] = self.input.shape();
+        let tensor: Tensor = tract_ndarray::Array4::from_shape_vec((n, a, b, c), input_tensor.to_vec())?.into();
+        let outputs = self.model.run(tvec!(tensor.into()))?;
+        outputs
+            .iter()
+            .map(|output| {
+                let view = output.to_array_view::<f32>()?;
+                Ok((view.shape().to_vec(), view.iter().copied().collect()))
+            })
+            .collect()
+    }
+
+    /// Boxes, plus the width and height of the space their coordinates are in
+    fn decode(&self, outputs: &[Output]) -> Result<(Vec<Bounds>, usize, usize), Box<dyn Error>> {
+        match self.kind {
+            TextDetectorKind::Db => {
+                let (map_width, map_height, probabilities) = find_map(outputs, 1)
+                    .ok_or("DB text detector must output a [1, 1, h, w] probability map")?;
+                Ok((decode_db(probabilities, map_width, map_height, &self.config), map_width, map_height))
+            }
+            TextDetectorKind::East => {
+                let (map_width, map_height, scores) =
+                    find_map(outputs, 1).ok_or("EAST text detector must output a [1, 1, h, w] score map")?;
+                let (_, _, geometry) = find_map(outputs, 5)
+                    .filter(|(w, h, _)| (*w, *h) == (map_width, map_height))
+                    .ok_or("EAST text detector must output a [1, 5, h, w] geometry map")?;
+                let stride = (
+                    self.input.width as f32 / map_width as f32,
+                    self.input.height as f32 / map_height as f32,
+                );
+                let bounds = decode_east(scores, geometry, map_width, map_height, stride, &self.config);
+                Ok((bounds, self.input.width, self.input.height))
+            }
+        }
+    }
+}
+
+/// Width, height and data of the first `[1, channels, h, w]` output
+fn find_map(outputs: &[Output], channels: usize) -> Option<(usize, usize, &[f32])> {
+    outputs.iter().find_map(|(shape, data)| match shape[..] {
+        [1, c, h, w] if c == channels && h > 0 && w > 0 => Some((w, h, &data[..])),
+        _ => None,
+    })
+}
+
+/// 4-connected regions above the score threshold, as unclipped bounding boxes in map cells
+fn decode_db(probabilities: &[f32], width: usize, height: usize, config: &TextDetectionConfig) -> Vec<Bounds> {
+    let mut visited = vec![false; probabilities.len()];
+    let mut stack = Vec::new();
+    let mut boxes = Vec::new();
+
+    for start in 0..probabilities.len() {
+        if visited[start] || probabilities[start] <= config.score_threshold {
+            continue;
+        }
+        visited[start] = true;
+        stack.push(start);
+        let (mut x0, mut y0, mut x1, mut y1) = (width, height, 0, 0);
+        let (mut sum, mut count) = (0.0f32, 0usize);
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % width, i / width);
+            (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x + 1), y1.max(y + 1));
+            sum += probabilities[i];
+            count += 1;
+            let neighbours = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < width).then_some(i + 1),
+                (y > 0).then(|| i - width),
+                (y + 1 < height).then_some(i + width),
+            ];
+            for n in neighbours.into_iter().flatten() {
+                if !visited[n] && probabilities[n] > config.score_threshold {
+                    visited[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+
+        let score = sum / count as f32;
+        if score < config.box_threshold {
+            continue;
+        }
+        let (w, h) = ((x1 - x0) as f32, (y1 - y0) as f32);
+        let grow = w * h * config.unclip_ratio / (2.0 * (w + h));
+        boxes.push(Bounds {
+            x0: x0 as f32 - grow,
+            y0: y0 as f32 - grow,
+            x1: x1 as f32 + grow,
+            y1: y1 as f32 + grow,
+            score,
+        });
+    }
+    boxes
+}
+
+/// Box per cell above the score threshold, enclosing its rotated rectangle, then
+/// non-maximum suppression; coordinates are input pixels
+fn decode_east(
+    scores: &[f32],
+    geometry: &[f32],
+    width: usize,
+    height: usize,
+    (stride_x, stride_y): (f32, f32),
+    config: &TextDetectionConfig,
+) -> Vec<Bounds> {
+    let plane = width * height;
+    let mut candidates = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let score = scores[i];
+            if score <= config.score_threshold {
+                continue;
+            }
+            let [top, right, bottom, left, angle] = [0, 1, 2, 3, 4].map(|channel| geometry[channel * plane + i]);
+            let (sin, cos) = angle.sin_cos();
+            let (box_width, box_height) = (left + right, top + bottom);
+
+            // As in the EAST reference decoder: the box's bottom-right corner, then its centre
+            let (origin_x, origin_y) = (x as f32 * stride_x, y as f32 * stride_y);
+            let end_x = origin_x + cos * right + sin * bottom;
+            let end_y = origin_y - sin * right + cos * bottom;
+            let centre_x = end_x - (cos * box_width + sin * box_height) / 2.0;
+            let centre_y = end_y + (sin * box_width - cos * box_height) / 2.0;
+
+            let half_x = (cos.abs() * box_width + sin.abs() * box_height) / 2.0;
+            let half_y = (sin.abs() * box_width + cos.abs() * box_height) / 2.0;
+            candidates.push(Bounds {
+                x0: centre_x - half_x,
+                y0: centre_y - half_y,
+                x1: centre_x + half_x,
+                y1: centre_y + half_y,
+                score,
+            });
+        }
+    }
+    non_maximum_suppression(candidates, config.nms_iou)
+}
+
+fn non_maximum_suppression(mut candidates: Vec<Bounds>, max_iou: f32) -> Vec<Bounds> {
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut kept: Vec<Bounds> = Vec::new();
+    for candidate in candidates {
+        if kept.iter().all(|k| k.iou(&candidate) <= max_iou) {
+            kept.push(candidate);
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_db_components() {
+        // 8×4 map: a strong 3×2 region, a weak single cell and background
+        let mut map = vec![0.0f32; 32];
+        for (x, y) in [(1, 1), (2, 1), (3, 1), (1, 2), (2, 2), (3, 2)] {
+            map[y * 8 + x] = 0.9;
+        }
+        map[6] = 0.4;
+        let config = TextDetectionConfig {
+            unclip_ratio: 0.0,
+            ..TextDetectionConfig::for_kind(TextDetectorKind::Db)
+        };
+
+        let boxes = decode_db(&map, 8, 4, &config);
+        assert_eq!(boxes.len(), 1);
+        assert_eq!((boxes[0].x0, boxes[0].y0, boxes[0].x1, boxes[0].y1), (1.0, 1.0, 4.0, 3.0));
+        assert!((boxes[0].score - 0.9).abs() < 1e-6);
+
+        // Unclipping grows each side by area * ratio / perimeter = 6 * 1.5 / 10
+        let unclipped = decode_db(&map, 8, 4, &TextDetectionConfig::for_kind(TextDetectorKind::Db));
+        assert!((unclipped[0].x0 - 0.1).abs() < 1e-6);
+        assert!((unclipped[0].y1 - 3.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_decode_east_geometry_and_suppression() {
+        // 2×1 map at stride 4: two overlapping boxes, the weaker one suppressed
+        let scores = [0.95, 0.9];
+        // Channels: top, right, bottom, left, angle
+        let geometry = [2.0, 2.0, 6.0, 6.0, 2.0, 2.0, 10.0, 8.0, 0.0, 0.0];
+        let config = TextDetectionConfig::for_kind(TextDetectorKind::East);
+
+        let boxes = decode_east(&scores, &geometry, 2, 1, (4.0, 4.0), &config);
+        assert_eq!(boxes.len(), 1);
+        let b = boxes[0];
+        assert_eq!((b.x0, b.y0, b.x1, b.y1, b.score), (-10.0, -2.0, 6.0, 2.0, 0.95));
+
+        // A quarter turn swaps the box's extent between the axes
+        let rotated = [2.0, 6.0, 2.0, 10.0, std::f32::consts::FRAC_PI_2];
+        let boxes = decode_east(&[0.9], &rotated, 1, 1, (4.0, 4.0), &config);
+        assert!((boxes[0].x1 - boxes[0].x0 - 4.0).abs() < 1e-4);
+        assert!((boxes[0].y1 - boxes[0].y0 - 16.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_iou() {
+        let a = Bounds { x0: 0.0, y0: 0.0, x1: 2.0, y1: 2.0, score: 1.0 };
+        let b = Bounds { x0: 1.0, y0: 0.0, x1: 3.0, y1: 2.0, score: 1.0 };
+        assert!((a.iou(&b) - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(a.iou(&Bounds { x0: 5.0, x1: 6.0, ..a }), 0.0);
+    }
+}
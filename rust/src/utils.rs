@@ -1,28 +1,52 @@
-/// Utility functions for Pavlova
+//! Utility functions for Pavlova
 
-/// Convert YUV420 to RGBA (for future use if needed)
+use std::error::Error;
+
+/// Convert planar YUV 4:2:0 to RGBA
+/// Chroma planes are subsampled 2×2 with odd sizes rounded up, i.e. `ceil(width / 2)`
+/// samples per row; planes may be longer than needed but not shorter.
 pub fn yuv420_to_rgba(
     y_plane: &[u8],
     u_plane: &[u8],
     v_plane: &[u8],
     width: usize,
     height: usize,
-) -> Vec<u8> {
-    let mut rgba = vec![0u8; width * height * 4];
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let luma_len = width
+        .checked_mul(height)
+        .filter(|pixels| pixels.checked_mul(4).is_some())
+        .ok_or_else(|| format!("Image dimensions {}x{} overflow", width, height))?;
+    let chroma_width = width.div_ceil(2);
+    let chroma_len = chroma_width * height.div_ceil(2);
+    if y_plane.len() < luma_len || u_plane.len() < chroma_len || v_plane.len() < chroma_len {
+        return Err(format!(
+            "YUV planes too short for {}x{}: need Y {} / U,V {}, got {} / {} / {}",
+            width,
+            height,
+            luma_len,
+            chroma_len,
+            y_plane.len(),
+            u_plane.len(),
+            v_plane.len()
+        )
+        .into());
+    }
+
+    let mut rgba = vec![0u8; luma_len * 4];
 
     for row in 0..height {
         for col in 0..width {
             let y_index = row * width + col;
-            let uv_index = (row / 2) * (width / 2) + (col / 2);
+            let uv_index = (row / 2) * chroma_width + (col / 2);
 
             let y = y_plane[y_index] as f32;
             let u = u_plane[uv_index] as f32 - 128.0;
             let v = v_plane[uv_index] as f32 - 128.0;
 
             // BT.709 conversion
-            let r = (y + 1.5748 * v).max(0.0).min(255.0) as u8;
-            let g = (y - 0.1873 * u - 0.4681 * v).max(0.0).min(255.0) as u8;
-            let b = (y + 1.8556 * u).max(0.0).min(255.0) as u8;
+            let r = (y + 1.5748 * v).clamp(0.0, 255.0) as u8;
+            let g = (y - 0.1873 * u - 0.4681 * v).clamp(0.0, 255.0) as u8;
+            let b = (y + 1.8556 * u).clamp(0.0, 255.0) as u8;
 
             let out_index = y_index * 4;
             rgba[out_index] = r;
@@ -32,7 +56,18 @@ pub fn yuv420_to_rgba(
         }
     }
 
-    rgba
+    Ok(rgba)
+}
+
+/// Extract the message of a caught panic payload
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
 }
 
 /// Performance timer
@@ -56,3 +91,21 @@ impl Drop for Timer {
         log::debug!("{} took {:?}", self.name, elapsed);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yuv420_odd_size_and_short_planes() {
+        // 3x3 luma has 2x2 chroma
+        let rgba = yuv420_to_rgba(&[128; 9], &[128; 4], &[128; 4], 3, 3).unwrap();
+        assert_eq!(rgba.len(), 36);
+        assert_eq!(&rgba[..4], &[128, 128, 128, 255]);
+
+        assert!(yuv420_to_rgba(&[128; 9], &[128; 3], &[128; 4], 3, 3).is_err());
+        assert!(yuv420_to_rgba(&[128; 8], &[128; 4], &[128; 4], 3, 3).is_err());
+        assert!(yuv420_to_rgba(&[], &[], &[], usize::MAX, 2).is_err());
+        assert!(yuv420_to_rgba(&[], &[], &[], 0, 0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+//! Builds tests/capi/test_capi.c against the cdylib and the generated header, then runs it.
+//! Requires a C compiler (`cc`, or `$CC`); run with `cargo test --features capi`.
+#![cfg(all(feature = "capi", target_os = "linux"))]
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Header generated by build.rs for this build
+fn generated_header() -> PathBuf {
+    PathBuf::from(env!("OUT_DIR")).join("include").join("pavlova.h")
+}
+
+/// The header shipped in `include/` must match the one generated from src/capi.rs
+/// Set `PAVLOVA_UPDATE_HEADER=1` to refresh it.
+#[test]
+fn test_shipped_header_is_current() {
+    let shipped = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("include").join("pavlova.h");
+    let generated = fs::read_to_string(generated_header()).unwrap();
+    if std::env::var_os("PAVLOVA_UPDATE_HEADER").is_some() {
+        fs::write(&shipped, &generated).unwrap();
+    }
+    assert!(
+        fs::read_to_string(&shipped).unwrap_or_default() == generated,
+        "include/pavlova.h is out of date; run `PAVLOVA_UPDATE_HEADER=1 cargo test --features capi --test capi`"
+    );
+}
+
+#[test]
+fn test_c_program() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // target/<profile>/deps/capi-<hash> -> target/<profile>, where libpavlova_core.so lives
+    let lib_dir = std::env::current_exe()
+        .unwrap()
+        .parent()
+        .and_then(|deps| deps.parent())
+        .unwrap()
+        .to_path_buf();
+    let scratch = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let exe = scratch.join("test_capi");
+
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(compiler)
+        .arg("-std=c99")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg(manifest_dir.join("tests/capi/test_capi.c"))
+        .arg("-I")
+        .arg(generated_header().parent().unwrap())
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-lpavlova_core")
+        .arg("-o")
+        .arg(&exe)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "C test program failed to compile");
+
+    let model = common::write_temp_model("capi_colour", &common::colour_model());
+    let output = Command::new(&exe)
+        .arg(&scratch)
+        .arg(&model)
+        .output()
+        .expect("failed to run the C test program");
+    assert!(
+        output.status.success(),
+        "C test program failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,117 @@
+/*
+ * Smoke test for the Pavlova C API.
+ * Built and run by tests/capi.rs with a scratch directory and the path of a synthetic
+ * colour model (red frames classify as porn) as arguments; exits non-zero if a check failed.
+ */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "pavlova.h"
+
+#define WIDTH 16
+#define HEIGHT 16
+#define LEN (WIDTH * HEIGHT * 4)
+
+static int failures = 0;
+
+#define CHECK(cond)                                                         \
+    do {                                                                    \
+        if (!(cond)) {                                                      \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+            failures++;                                                     \
+        }                                                                   \
+    } while (0)
+
+int main(int argc, char **argv) {
+    uint8_t image[LEN];
+    uint8_t out[LEN];
+
+    /* Left half black, right half white, opaque */
+    for (int y = 0; y < HEIGHT; y++) {
+        for (int x = 0; x < WIDTH; x++) {
+            uint8_t *px = &image[(y * WIDTH + x) * 4];
+            uint8_t v = x < WIDTH / 2 ? 0 : 255;
+            px[0] = px[1] = px[2] = v;
+            px[3] = 255;
+        }
+    }
+
+    /* Blur mixes the two halves at the edge and keeps alpha */
+    CHECK(pavlova_engine_blur(image, LEN, WIDTH, HEIGHT, 2.0f, out, LEN) == PAVLOVA_STATUS_OK);
+    CHECK(pavlova_last_error_message() == NULL);
+    CHECK(out[(WIDTH / 2) * 4] > 0 && out[(WIDTH / 2) * 4] < 255);
+    CHECK(out[3] == 255);
+
+    /* Pixelating with the full width averages to mid-gray */
+    CHECK(pavlova_engine_pixelate(image, LEN, WIDTH, HEIGHT, WIDTH, out, LEN) == PAVLOVA_STATUS_OK);
+    CHECK(out[0] == 127 && out[(LEN - 4)] == 127);
+
+    /* Argument errors are reported, not crashed on */
+    CHECK(pavlova_engine_blur(NULL, LEN, WIDTH, HEIGHT, 2.0f, out, LEN) == PAVLOVA_STATUS_NULL_POINTER);
+    CHECK(pavlova_engine_blur(image, LEN - 1, WIDTH, HEIGHT, 2.0f, out, LEN) == PAVLOVA_STATUS_INVALID_ARGUMENT);
+    CHECK(pavlova_engine_blur(image, LEN, WIDTH, HEIGHT, 1000.0f, out, LEN) == PAVLOVA_STATUS_INVALID_ARGUMENT);
+    CHECK(pavlova_last_error_message() != NULL);
+    CHECK(pavlova_engine_pixelate(image, LEN, WIDTH, HEIGHT, 0, out, LEN) == PAVLOVA_STATUS_INVALID_ARGUMENT);
+    CHECK(pavlova_engine_pixelate(image, LEN, WIDTH, HEIGHT, 4, out, LEN / 2) == PAVLOVA_STATUS_INVALID_ARGUMENT);
+
+    /* Classification with a real model */
+    CHECK(argc == 3);
+    uint64_t handle = 0;
+    uint8_t red[LEN];
+    for (int i = 0; i < LEN; i += 4) {
+        red[i] = 255;
+        red[i + 1] = red[i + 2] = 0;
+        red[i + 3] = 255;
+    }
+    PavlovaClassification result;
+    CHECK(pavlova_engine_new(argc == 3 ? argv[2] : "", &handle) == PAVLOVA_STATUS_OK);
+    CHECK(handle != 0);
+    CHECK(pavlova_engine_classify(handle, red, LEN, WIDTH, HEIGHT, &result) == PAVLOVA_STATUS_OK);
+    CHECK(result.top_class_index == 3);
+    CHECK(!result.is_safe);
+    float total = 0.0f;
+    for (int i = 0; i < PAVLOVA_NUM_CLASSES; i++) {
+        total += result.scores[i];
+    }
+    CHECK(total > 0.99f && total < 1.01f);
+    CHECK(pavlova_engine_classify(handle, red, LEN - 1, WIDTH, HEIGHT, &result) == PAVLOVA_STATUS_INVALID_ARGUMENT);
+    CHECK(pavlova_engine_free(handle) == PAVLOVA_STATUS_OK);
+    CHECK(pavlova_engine_classify(handle, red, LEN, WIDTH, HEIGHT, &result) == PAVLOVA_STATUS_INVALID_HANDLE);
+
+    /* Engine lifecycle errors */
+    CHECK(pavlova_engine_new("/nonexistent/model.onnx", &handle) == PAVLOVA_STATUS_MODEL_LOAD);
+    CHECK(strstr(pavlova_last_error_message(), "not found") != NULL);
+    CHECK(pavlova_engine_new(NULL, &handle) == PAVLOVA_STATUS_NULL_POINTER);
+
+    /* Integrity is checked before parsing, so any bytes serve as a tampered model */
+    char model_path[4096];
+    snprintf(model_path, sizeof model_path, "%s/capi_tampered.onnx", argc == 3 ? argv[1] : ".");
+    FILE *model = fopen(model_path, "wb");
+    CHECK(model != NULL);
+    if (model) {
+        fputs("not the pinned model", model);
+        fclose(model);
+    }
+    uint8_t digest[32] = {0};
+    CHECK(pavlova_engine_new_verified(model_path, digest, NULL, NULL, &handle) == PAVLOVA_STATUS_INTEGRITY);
+    CHECK(strstr(pavlova_last_error_message(), "integrity") != NULL);
+    CHECK(pavlova_engine_new_verified(model_path, NULL, NULL, NULL, &handle) == PAVLOVA_STATUS_NULL_POINTER);
+    /* A digest and a signature together are ambiguous, not a digest check */
+    uint8_t key[32] = {0};
+    uint8_t signature[64] = {0};
+    CHECK(pavlova_engine_new_verified(model_path, digest, key, signature, &handle) == PAVLOVA_STATUS_INVALID_ARGUMENT);
+    CHECK(strstr(pavlova_last_error_message(), "not both") != NULL);
+    CHECK(pavlova_engine_new_verified(model_path, digest, key, NULL, &handle) == PAVLOVA_STATUS_INVALID_ARGUMENT);
+
+    CHECK(pavlova_engine_classify(42, image, LEN, WIDTH, HEIGHT, &result) == PAVLOVA_STATUS_INVALID_HANDLE);
+    CHECK(pavlova_engine_free(42) == PAVLOVA_STATUS_INVALID_HANDLE);
+    CHECK(pavlova_engine_free(0) == PAVLOVA_STATUS_OK);
+
+    if (failures) {
+        fprintf(stderr, "%d check(s) failed\n", failures);
+        return EXIT_FAILURE;
+    }
+    printf("C API tests passed\n");
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,77 @@
+//! `CascadeEngine`: pre-filter decisions, escalation and stage counters.
+
+mod common;
+
+use pavlova_core::cascade::{CascadeEngine, CascadeStage, CascadeStats, PreFilter};
+use pavlova_core::inference::MLEngine;
+
+const RED: [u8; 3] = [255, 0, 0];
+const GREEN: [u8; 3] = [0, 255, 0];
+const SKIN: [u8; 3] = [224, 172, 150];
+const WHITE: [u8; 3] = [255, 255, 255];
+
+fn full() -> MLEngine {
+    MLEngine::from_bytes(&common::colour_model(), "full").unwrap()
+}
+
+/// Colour model taking a 64×64 channel-last input, standing in for a tiny gate model
+fn tiny() -> MLEngine {
+    let manifest = b"input_size = 64x64\ninput_layout = nhwc\ninput_mean = 0\ninput_std = 255\n";
+    MLEngine::from_bytes_with_manifest(&common::colour_model_with_input(64, 64, true), Some(manifest), "tiny").unwrap()
+}
+
+#[test]
+fn test_model_prefilter_escalates_unsafe_frames() {
+    let cascade = CascadeEngine::new(PreFilter::Model(Box::new(tiny())), full(), 0.3).unwrap();
+
+    let safe = cascade.classify(&common::solid_frame(32, 32, GREEN), 32, 32).unwrap();
+    assert_eq!(safe.stage, CascadeStage::PreFilter);
+    assert_eq!(safe.classification.unwrap().top_class, "neutral");
+    assert!(safe.prefilter_score.unwrap() < 0.3);
+
+    let unsafe_frame = cascade.classify(&common::solid_frame(32, 32, RED), 32, 32).unwrap();
+    assert_eq!(unsafe_frame.stage, CascadeStage::Full);
+    assert_eq!(unsafe_frame.classification.unwrap().top_class, "porn");
+    assert!(unsafe_frame.prefilter_score.unwrap() >= 0.3);
+
+    assert_eq!(
+        cascade.stats(),
+        CascadeStats { frames: 2, prefilter_decided: 1, escalated: 1, prefilter_errors: 0 }
+    );
+}
+
+#[test]
+fn test_skin_tone_prefilter_and_counters() {
+    let cascade = CascadeEngine::new(PreFilter::SkinTone, full(), 0.2).unwrap();
+
+    for _ in 0..3 {
+        let result = cascade.classify(&common::solid_frame(32, 32, WHITE), 32, 32).unwrap();
+        assert_eq!(result.stage, CascadeStage::PreFilter);
+        assert_eq!(result.prefilter_score, Some(0.0));
+        // No model ran, so there is no classification to report
+        assert!(result.classification.is_none());
+    }
+    let skin = cascade.classify(&common::solid_frame(32, 32, SKIN), 32, 32).unwrap();
+    assert_eq!(skin.stage, CascadeStage::Full);
+    assert_eq!(skin.prefilter_score, Some(1.0));
+    assert!(skin.classification.is_some());
+
+    let stats = cascade.stats();
+    assert_eq!((stats.frames, stats.prefilter_decided, stats.escalated), (4, 3, 1));
+    assert_eq!(stats.escalation_rate(), 0.25);
+
+    cascade.reset_stats();
+    assert_eq!(cascade.stats(), CascadeStats::default());
+}
+
+#[test]
+fn test_failed_prefilter_escalates() {
+    // The gate rejects the frame, so the full model must still see it (and fail on it too)
+    let cascade = CascadeEngine::new(PreFilter::SkinTone, full(), 0.5).unwrap();
+    assert!(cascade.classify(&[0; 12], 2, 2).is_err());
+    let stats = cascade.stats();
+    assert_eq!((stats.escalated, stats.prefilter_errors), (1, 1));
+
+    assert!(CascadeEngine::new(PreFilter::SkinTone, full(), 1.5).is_err());
+    assert!(CascadeEngine::new(PreFilter::SkinTone, full(), -0.1).is_err());
+}
@@ -0,0 +1,183 @@
+//! Shared test helpers: ONNX models built in code, so tests and benchmarks need no downloads.
+//!
+//! Included by integration tests as `mod common;` and by benchmarks via `#[path]`;
+//! not every includer uses every helper.
+#![allow(dead_code)]
+
+use prost::Message;
+use tract_onnx::pb::{
+    attribute_proto::AttributeType, tensor_proto::DataType, tensor_shape_proto, type_proto, AttributeProto,
+    GraphProto, ModelProto, NodeProto, OperatorSetIdProto, TensorProto, TensorShapeProto, TypeProto, ValueInfoProto,
+};
+
+fn value_info(name: &str, dims: &[i64]) -> ValueInfoProto {
+    ValueInfoProto {
+        name: name.to_string(),
+        r#type: Some(TypeProto {
+            value: Some(type_proto::Value::TensorType(type_proto::Tensor {
+                elem_type: DataType::Float as i32,
+                shape: Some(TensorShapeProto {
+                    dim: dims
+                        .iter()
+                        .map(|&d| tensor_shape_proto::Dimension {
+                            value: Some(tensor_shape_proto::dimension::Value::DimValue(d)),
+                            ..Default::default()
+                        })
+                        .collect(),
+                }),
+            })),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+fn initializer(name: &str, dims: &[i64], data: Vec<f32>) -> TensorProto {
+    TensorProto {
+        name: name.to_string(),
+        dims: dims.to_vec(),
+        data_type: DataType::Float as i32,
+        float_data: data,
+        ..Default::default()
+    }
+}
+
+fn node(op_type: &str, inputs: &[&str], output: &str, attributes: Vec<AttributeProto>) -> NodeProto {
+    NodeProto {
+        op_type: op_type.to_string(),
+        name: output.to_string(),
+        input: inputs.iter().map(|s| s.to_string()).collect(),
+        output: vec![output.to_string()],
+        attribute: attributes,
+        ..Default::default()
+    }
+}
+
+fn int_attribute(name: &str, value: i64) -> AttributeProto {
+    AttributeProto {
+        name: name.to_string(),
+        r#type: AttributeType::Int as i32,
+        i: value,
+        ..Default::default()
+    }
+}
+
+/// Average each input channel, then map the 3 channel means to 5 softmax probabilities
+pub fn channel_mean_model(weights: [[f32; 5]; 3], bias: [f32; 5]) -> Vec<u8> {
+    channel_linear_model(weights.iter().flatten().copied().collect(), bias.to_vec(), true, &[1, 3, 224, 224], false)
+}
+
+/// Like `channel_mean_model` but emitting raw logits, one per bias entry
+/// `weights` holds 3 rows of `bias.len()` values.
+pub fn channel_logit_model(weights: Vec<f32>, bias: Vec<f32>) -> Vec<u8> {
+    channel_linear_model(weights, bias, false, &[1, 3, 224, 224], false)
+}
+
+/// `colour_model` taking a `width`×`height` input, channel-last (`[1, H, W, 3]`) if `nhwc`
+pub fn colour_model_with_input(width: i64, height: i64, nhwc: bool) -> Vec<u8> {
+    let dims = if nhwc { [1, height, width, 3] } else { [1, 3, height, width] };
+    channel_linear_model(COLOUR_WEIGHTS.iter().flatten().copied().collect(), vec![0.0; 5], true, &dims, nhwc)
+}
+
+/// Channel means -> Gemm -> optional Softmax, transposing a channel-last input first if `nhwc`
+fn channel_linear_model(weights: Vec<f32>, bias: Vec<f32>, softmax: bool, input_dims: &[i64], nhwc: bool) -> Vec<u8> {
+    let outputs = bias.len() as i64;
+    let mut nodes = Vec::new();
+    if nhwc {
+        nodes.push(NodeProto {
+            attribute: vec![AttributeProto {
+                name: "perm".to_string(),
+                r#type: AttributeType::Ints as i32,
+                ints: vec![0, 3, 1, 2],
+                ..Default::default()
+            }],
+            ..node("Transpose", &["input"], "nchw", vec![])
+        });
+    }
+    nodes.extend([
+        node("GlobalAveragePool", &[if nhwc { "nchw" } else { "input" }], "pooled", vec![]),
+        node("Flatten", &["pooled"], "flat", vec![int_attribute("axis", 1)]),
+        node("Gemm", &["flat", "weights", "bias"], "logits", vec![]),
+    ]);
+    if softmax {
+        nodes.push(node("Softmax", &["logits"], "probs", vec![int_attribute("axis", 1)]));
+    }
+    let graph = GraphProto {
+        name: "channel_mean".to_string(),
+        node: nodes,
+        initializer: vec![
+            initializer("weights", &[3, outputs], weights),
+            initializer("bias", &[outputs], bias),
+        ],
+        input: vec![value_info("input", input_dims)],
+        output: vec![value_info(if softmax { "probs" } else { "logits" }, &[1, outputs])],
+        ..Default::default()
+    };
+    ModelProto {
+        ir_version: 7,
+        opset_import: vec![OperatorSetIdProto { domain: String::new(), version: 13 }],
+        producer_name: "pavlova-tests".to_string(),
+        graph: Some(graph),
+        ..Default::default()
+    }
+    .encode_to_vec()
+}
+
+/// DB-style text detector on a `width`×`height` NCHW input: per-pixel text probability
+/// `sigmoid(-4 × channel mean)`, so dark pixels (normalized near -1) read as text
+pub fn dark_text_model(width: i64, height: i64) -> Vec<u8> {
+    let graph = GraphProto {
+        name: "dark_text".to_string(),
+        node: vec![
+            node("Conv", &["input", "weights", "bias"], "logits", vec![]),
+            node("Sigmoid", &["logits"], "probs", vec![]),
+        ],
+        initializer: vec![
+            initializer("weights", &[1, 3, 1, 1], vec![-4.0 / 3.0; 3]),
+            initializer("bias", &[1], vec![0.0]),
+        ],
+        input: vec![value_info("input", &[1, 3, height, width])],
+        output: vec![value_info("probs", &[1, 1, height, width])],
+        ..Default::default()
+    };
+    ModelProto {
+        ir_version: 7,
+        opset_import: vec![OperatorSetIdProto { domain: String::new(), version: 13 }],
+        producer_name: "pavlova-tests".to_string(),
+        graph: Some(graph),
+        ..Default::default()
+    }
+    .encode_to_vec()
+}
+
+/// Channel-to-class weights of `colour_model`
+const COLOUR_WEIGHTS: [[f32; 5]; 3] = [[0.0, 0.0, 0.0, 4.0, 0.0], [0.0, 0.0, 4.0, 0.0, 0.0], [4.0, 0.0, 0.0, 0.0, 0.0]];
+
+/// Model whose scores depend only on average colour: red -> porn, green -> neutral, blue -> drawing
+pub fn colour_model() -> Vec<u8> {
+    channel_mean_model(COLOUR_WEIGHTS, [0.0; 5])
+}
+
+/// `colour_model` with red and blue exchanged: red -> drawing, blue -> porn
+pub fn inverted_colour_model() -> Vec<u8> {
+    channel_mean_model([COLOUR_WEIGHTS[2], COLOUR_WEIGHTS[1], COLOUR_WEIGHTS[0]], [0.0; 5])
+}
+
+/// Write a model under Cargo's per-target scratch directory and return its path
+/// `name` must be unique among the tests of one binary, which run in parallel.
+pub fn write_temp_model(name: &str, bytes: &[u8]) -> std::path::PathBuf {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.onnx", name));
+    std::fs::write(&path, bytes).expect("write temporary model");
+    path
+}
+
+/// Load `colour_model` into an engine
+pub fn colour_engine(name: &str) -> pavlova_core::inference::MLEngine {
+    let path = write_temp_model(name, &colour_model());
+    pavlova_core::inference::MLEngine::new(&path.to_string_lossy()).expect("load synthetic model")
+}
+
+/// Opaque RGBA frame of one colour
+pub fn solid_frame(width: usize, height: usize, rgb: [u8; 3]) -> Vec<u8> {
+    [rgb[0], rgb[1], rgb[2], 255].repeat(width * height)
+}
@@ -0,0 +1,100 @@
+//! `EnsembleEngine`: combination strategies, per-model results and mixed input specs.
+
+mod common;
+
+use pavlova_core::ensemble::{EnsembleEngine, EnsembleMember, EnsembleStrategy};
+use pavlova_core::inference::MLEngine;
+
+const RED: [u8; 3] = [255, 0, 0];
+const GREEN: [u8; 3] = [0, 255, 0];
+
+fn colour(name: &str) -> EnsembleMember {
+    EnsembleMember::new(name, MLEngine::from_bytes(&common::colour_model(), name).unwrap())
+}
+
+fn inverted(name: &str) -> EnsembleMember {
+    EnsembleMember::new(name, MLEngine::from_bytes(&common::inverted_colour_model(), name).unwrap())
+}
+
+/// Colour model taking a 96×96 channel-last input in [0, 1]
+fn small_nhwc(name: &str) -> EnsembleMember {
+    let manifest = b"input_size = 96x96\ninput_layout = nhwc\ninput_mean = 0\ninput_std = 255\n";
+    let bytes = common::colour_model_with_input(96, 96, true);
+    EnsembleMember::new(name, MLEngine::from_bytes_with_manifest(&bytes, Some(manifest), name).unwrap())
+}
+
+fn classify(ensemble: &EnsembleEngine, rgb: [u8; 3]) -> pavlova_core::ensemble::EnsembleResult {
+    ensemble.classify(&common::solid_frame(32, 32, rgb), 32, 32).unwrap()
+}
+
+#[test]
+fn test_weighted_average_with_mixed_inputs() {
+    let ensemble = EnsembleEngine::new(
+        vec![colour("full"), small_nhwc("small"), inverted("inverted").with_weight(0.5)],
+        EnsembleStrategy::WeightedAverage,
+    )
+    .unwrap();
+    let result = classify(&ensemble, RED);
+
+    let names: Vec<&str> = result.members.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, ["full", "small", "inverted"]);
+    assert_eq!(result.members[0].result.top_class, "porn");
+    assert_eq!(result.members[1].result.top_class, "porn");
+    assert_eq!(result.members[2].result.top_class, "drawing");
+
+    let expected: Vec<f32> = (0..5)
+        .map(|c| {
+            let m = &result.members;
+            (m[0].result.scores[c] + m[1].result.scores[c] + 0.5 * m[2].result.scores[c]) / 2.5
+        })
+        .collect();
+    for (combined, expected) in result.combined.scores.iter().zip(expected) {
+        assert!((combined - expected).abs() < 1e-5);
+    }
+    assert_eq!(result.combined.top_class, "porn");
+}
+
+#[test]
+fn test_max_unsafe_and_majority_vote() {
+    let max_unsafe = EnsembleEngine::new(vec![inverted("inverted"), colour("colour")], EnsembleStrategy::MaxUnsafe).unwrap();
+    let result = classify(&max_unsafe, RED);
+    assert_eq!(result.combined.top_class, "porn");
+    assert_eq!(result.combined.scores, result.members[1].result.scores);
+
+    let vote = EnsembleEngine::new(
+        vec![inverted("a"), colour("b"), small_nhwc("c")],
+        EnsembleStrategy::MajorityVote,
+    )
+    .unwrap();
+    let result = classify(&vote, RED);
+    assert_eq!(result.combined.top_class, "porn");
+    assert!((result.combined.scores[3] - 2.0 / 3.0).abs() < 1e-6);
+    assert!((result.combined.scores[0] - 1.0 / 3.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_cascade_only_escalates_unsafe_frames() {
+    let ensemble = EnsembleEngine::new(
+        vec![small_nhwc("gate"), colour("full")],
+        EnsembleStrategy::Cascade { escalate_at: 0.3 },
+    )
+    .unwrap();
+
+    let safe = classify(&ensemble, GREEN);
+    assert_eq!(safe.members.len(), 1);
+    assert_eq!(safe.members[0].name, "gate");
+    assert_eq!(safe.combined.top_class, "neutral");
+
+    let unsafe_frame = classify(&ensemble, RED);
+    assert_eq!(unsafe_frame.members.len(), 2);
+    // The gate only decides whether to escalate; the full model's result stands
+    assert_eq!(unsafe_frame.combined.scores, unsafe_frame.members[1].result.scores);
+}
+
+#[test]
+fn test_invalid_ensembles() {
+    assert!(EnsembleEngine::new(vec![], EnsembleStrategy::WeightedAverage).is_err());
+    assert!(EnsembleEngine::new(vec![colour("a").with_weight(0.0)], EnsembleStrategy::WeightedAverage).is_err());
+    assert!(EnsembleEngine::new(vec![colour("a")], EnsembleStrategy::Cascade { escalate_at: 0.5 }).is_err());
+    assert!(EnsembleEngine::new(vec![colour("a"), colour("b")], EnsembleStrategy::Cascade { escalate_at: 1.5 }).is_err());
+}
@@ -0,0 +1,186 @@
+//! Golden-image regression tests for the image operations.
+//!
+//! Each case runs an operation on a deterministic fixture and compares the result with
+//! `tests/golden/<case>.png`, allowing a small per-channel difference. After an intentional
+//! change to an operation, regenerate the goldens and review the new images:
+//!
+//! ```text
+//! PAVLOVA_UPDATE_GOLDENS=1 cargo test --test golden
+//! ```
+//!
+//! On a mismatch the actual output is written next to the build's scratch files
+//! (`target/tmp/golden-actual/`) for comparison.
+
+use std::path::{Path, PathBuf};
+
+use pavlova_core::config::{MODEL_INPUT_HEIGHT, MODEL_INPUT_WIDTH};
+use pavlova_core::image::{blur, pixelate, preprocess_for_model, resize_bilinear_rgba};
+use pavlova_core::utils::yuv420_to_rgba;
+
+/// Largest accepted per-channel difference, to absorb float rounding across platforms
+const TOLERANCE: u8 = 1;
+
+const FIXTURE_WIDTH: usize = 96;
+const FIXTURE_HEIGHT: usize = 64;
+
+/// Deterministic RGBA fixture: colour gradients, a hard-edged square, thin stripes and varying alpha
+fn fixture() -> Vec<u8> {
+    let mut data = Vec::with_capacity(FIXTURE_WIDTH * FIXTURE_HEIGHT * 4);
+    for y in 0..FIXTURE_HEIGHT {
+        for x in 0..FIXTURE_WIDTH {
+            let in_square = (20..44).contains(&x) && (16..40).contains(&y);
+            let stripe = x >= 64 && (y / 2) % 2 == 0;
+            let (r, g, b) = if in_square {
+                (250, 250, 250)
+            } else if stripe {
+                (10, 10, 10)
+            } else {
+                ((x * 255 / (FIXTURE_WIDTH - 1)) as u8, (y * 255 / (FIXTURE_HEIGHT - 1)) as u8, ((x * y) % 256) as u8)
+            };
+            let a = 255 - (y * 2) as u8;
+            data.extend_from_slice(&[r, g, b, a]);
+        }
+    }
+    data
+}
+
+/// Image compared against a golden file; `channels` is 3 (RGB) or 4 (RGBA)
+struct Output {
+    data: Vec<u8>,
+    width: usize,
+    height: usize,
+    channels: usize,
+}
+
+fn rgba(data: Vec<u8>, width: usize, height: usize) -> Output {
+    Output {
+        data,
+        width,
+        height,
+        channels: 4,
+    }
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.png", name))
+}
+
+fn color_type(channels: usize) -> image::ExtendedColorType {
+    if channels == 3 {
+        image::ExtendedColorType::Rgb8
+    } else {
+        image::ExtendedColorType::Rgba8
+    }
+}
+
+fn save(path: &Path, output: &Output) {
+    image::save_buffer(path, &output.data, output.width as u32, output.height as u32, color_type(output.channels))
+        .unwrap_or_else(|e| panic!("cannot write {}: {}", path.display(), e));
+}
+
+/// Compare with the golden image, or rewrite it when `PAVLOVA_UPDATE_GOLDENS` is set
+fn check_golden(name: &str, output: Output) {
+    assert_eq!(output.data.len(), output.width * output.height * output.channels, "{}: bad output size", name);
+    let path = golden_path(name);
+
+    if std::env::var_os("PAVLOVA_UPDATE_GOLDENS").is_some() {
+        save(&path, &output);
+        return;
+    }
+
+    let golden = image::open(&path)
+        .unwrap_or_else(|e| panic!("{}: cannot read {} ({}); run with PAVLOVA_UPDATE_GOLDENS=1", name, path.display(), e));
+    let expected = if output.channels == 3 {
+        golden.to_rgb8().into_raw()
+    } else {
+        golden.to_rgba8().into_raw()
+    };
+    assert_eq!(
+        (golden.width() as usize, golden.height() as usize),
+        (output.width, output.height),
+        "{}: size differs from golden",
+        name
+    );
+
+    let differing = expected.iter().zip(&output.data).filter(|(e, a)| e.abs_diff(**a) > TOLERANCE).count();
+    if differing > 0 {
+        let max_diff = expected.iter().zip(&output.data).map(|(e, a)| e.abs_diff(*a)).max().unwrap_or(0);
+        let actual_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden-actual");
+        std::fs::create_dir_all(&actual_dir).unwrap();
+        let actual_path = actual_dir.join(format!("{}.png", name));
+        save(&actual_path, &output);
+        panic!(
+            "{}: {} channel values differ by more than {} (max {}); actual output written to {}",
+            name,
+            differing,
+            TOLERANCE,
+            max_diff,
+            actual_path.display()
+        );
+    }
+}
+
+#[test]
+fn golden_fixture() {
+    // Guards the input itself, so a fixture change is not mistaken for an operation change
+    check_golden("fixture", rgba(fixture(), FIXTURE_WIDTH, FIXTURE_HEIGHT));
+}
+
+#[test]
+fn golden_blur() {
+    for radius in [2.0f32, 5.0, 15.0] {
+        let blurred = blur(&fixture(), FIXTURE_WIDTH, FIXTURE_HEIGHT, radius).unwrap();
+        check_golden(&format!("blur_r{}", radius), rgba(blurred, FIXTURE_WIDTH, FIXTURE_HEIGHT));
+    }
+}
+
+#[test]
+fn golden_pixelate() {
+    // 7 does not divide the fixture size, covering partial edge blocks
+    for block_size in [4usize, 7, 16] {
+        let pixelated = pixelate(&fixture(), FIXTURE_WIDTH, FIXTURE_HEIGHT, block_size).unwrap();
+        check_golden(&format!("pixelate_{}", block_size), rgba(pixelated, FIXTURE_WIDTH, FIXTURE_HEIGHT));
+    }
+}
+
+#[test]
+fn golden_resize() {
+    for (width, height) in [(40, 30), (150, 100)] {
+        let resized = resize_bilinear_rgba(&fixture(), FIXTURE_WIDTH, FIXTURE_HEIGHT, width, height).unwrap();
+        check_golden(&format!("resize_{}x{}", width, height), rgba(resized, width, height));
+    }
+}
+
+#[test]
+fn golden_preprocess() {
+    let tensor = preprocess_for_model(&fixture(), FIXTURE_WIDTH, FIXTURE_HEIGHT).unwrap();
+
+    // Undo the [-1, 1] normalization and NCHW layout; this is exact for u8-derived values
+    let pixel_count = MODEL_INPUT_WIDTH * MODEL_INPUT_HEIGHT;
+    let mut rgb = Vec::with_capacity(pixel_count * 3);
+    for i in 0..pixel_count {
+        for channel in 0..3 {
+            let value = tensor[channel * pixel_count + i] * 127.5 + 127.5;
+            rgb.push(value.round().clamp(0.0, 255.0) as u8);
+        }
+    }
+    check_golden(
+        "preprocess",
+        Output {
+            data: rgb,
+            width: MODEL_INPUT_WIDTH,
+            height: MODEL_INPUT_HEIGHT,
+            channels: 3,
+        },
+    );
+}
+
+#[test]
+fn golden_yuv420() {
+    let (width, height) = (64, 48);
+    let y: Vec<u8> = (0..width * height).map(|i| ((i % width) * 4) as u8).collect();
+    let u: Vec<u8> = (0..width * height / 4).map(|i| ((i / (width / 2)) * 10) as u8).collect();
+    let v: Vec<u8> = (0..width * height / 4).map(|i| (255 - (i % (width / 2)) * 8) as u8).collect();
+
+    check_golden("yuv420", rgba(yuv420_to_rgba(&y, &u, &v, width, height).unwrap(), width, height));
+}
@@ -0,0 +1,240 @@
+//! `MLEngine` against synthetic ONNX models: loading, classification and error paths.
+
+mod common;
+
+use std::sync::Arc;
+use std::thread;
+
+use pavlova_core::calibration::Calibration;
+use pavlova_core::config::{MODEL_INPUT_CHANNELS, MODEL_INPUT_HEIGHT, MODEL_INPUT_WIDTH};
+use pavlova_core::inference::{MLEngine, SafetyGroup};
+use pavlova_core::integrity::sha256_hex;
+
+const RED: [u8; 3] = [255, 0, 0];
+const GREEN: [u8; 3] = [0, 255, 0];
+const BLUE: [u8; 3] = [0, 0, 255];
+
+#[test]
+fn test_load_and_report_path() {
+    let path = common::write_temp_model("load", &common::colour_model());
+    let engine = MLEngine::new(&path.to_string_lossy()).unwrap();
+    assert_eq!(engine.model_path(), path.to_string_lossy());
+}
+
+#[test]
+fn test_load_from_memory() {
+    let model = common::colour_model();
+
+    let engine = MLEngine::from_bytes(&model, "assets/colour.onnx").unwrap();
+    assert_eq!(engine.model_path(), "assets/colour.onnx");
+    assert_eq!(engine.classify(&common::solid_frame(8, 8, GREEN), 8, 8).unwrap().top_class, "neutral");
+
+    let engine = MLEngine::from_reader(std::io::Cursor::new(&model), "stream").unwrap();
+    assert_eq!(engine.classify(&common::solid_frame(8, 8, BLUE), 8, 8).unwrap().top_class, "drawing");
+
+    assert!(MLEngine::from_bytes(&[], "empty").is_err());
+    assert!(MLEngine::from_bytes(&model[..model.len() / 2], "truncated").is_err());
+}
+
+#[test]
+fn test_model_info() {
+    let model = common::colour_model();
+    let engine = MLEngine::from_bytes(&model, "colour").unwrap();
+    let info = engine.info();
+
+    assert_eq!(info.source, "colour");
+    assert_eq!(info.sha256, sha256_hex(&model));
+    assert_eq!(info.file_size, model.len() as u64);
+    assert_eq!(info.inputs.len(), 1);
+    assert_eq!(info.inputs[0].dtype, "f32");
+    assert_eq!(info.inputs[0].shape, [Some(1), Some(3), Some(224), Some(224)]);
+    assert_eq!(info.outputs.len(), 1);
+    assert_eq!(info.outputs[0].element_count(), Some(5));
+    assert_eq!(info.labels, ["drawing", "hentai", "neutral", "porn", "sexy"]);
+    assert_eq!(info.activation, "none");
+    // 4 operators plus the input and 2 initializers
+    assert_eq!(info.source_node_count, 7);
+    assert_eq!(info.op_counts.iter().map(|(_, count)| count).sum::<usize>(), info.node_count);
+    assert!(info.to_string().contains("output: "));
+}
+
+#[test]
+fn test_logits_get_softmax() {
+    // colour_model without its Softmax node
+    let mut weights = vec![0.0; 15];
+    weights[3] = 4.0;
+    weights[5 + 2] = 4.0;
+    weights[10] = 4.0;
+    let engine = MLEngine::from_bytes(&common::channel_logit_model(weights, vec![0.0; 5]), "logits").unwrap();
+    assert_eq!(engine.info().activation, "softmax");
+
+    let result = engine.classify(&common::solid_frame(64, 64, [255, 0, 0]), 64, 64).unwrap();
+    assert_eq!(result.top_class, "porn");
+    assert!((result.scores.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+}
+
+#[test]
+fn test_output_count_must_match_labels() {
+    let two_outputs = common::channel_logit_model(vec![1.0; 6], vec![0.0; 2]);
+    let err = MLEngine::from_bytes(&two_outputs, "binary").err().unwrap().to_string();
+    assert!(err.contains("2 values but 5 labels"), "{}", err);
+
+    // An extra output is fine once the manifest names it
+    let six_outputs = common::channel_logit_model(vec![1.0; 18], vec![0.0; 6]);
+    assert!(MLEngine::from_bytes(&six_outputs, "six").is_err());
+    let manifest = b"labels = drawing, hentai, neutral, porn, sexy, other\n";
+    let engine = MLEngine::from_bytes_with_manifest(&six_outputs, Some(manifest), "six").unwrap();
+    assert_eq!(engine.info().labels.len(), 6);
+}
+
+#[test]
+fn test_manifest_reorders_outputs_and_applies_sigmoid() {
+    // Outputs in porn, neutral, sexy, drawing, hentai order; red drives porn, blue drawing
+    let mut weights = vec![0.0; 15];
+    weights[0] = 8.0;
+    weights[10 + 3] = 8.0;
+    let model = common::channel_logit_model(weights, vec![-4.0; 5]);
+    let path = common::write_temp_model("inference_sigmoid", &model);
+    std::fs::write(
+        pavlova_core::integrity::manifest_path(&path),
+        "labels = porn, neutral, sexy, drawing, hentai\nactivation = sigmoid\n",
+    )
+    .unwrap();
+
+    let engine = MLEngine::new(&path.to_string_lossy()).unwrap();
+    assert_eq!(engine.info().activation, "sigmoid");
+    assert_eq!(engine.info().labels[0], "porn");
+
+    let red = engine.classify(&common::solid_frame(64, 64, [255, 0, 0]), 64, 64).unwrap();
+    assert_eq!(red.top_class, "porn");
+    assert!(red.scores[3] > 0.95);
+    assert!(red.scores.iter().all(|s| (0.0..=1.0).contains(s)));
+
+    let blue = engine.classify(&common::solid_frame(64, 64, [0, 0, 255]), 64, 64).unwrap();
+    assert_eq!(blue.top_class, "drawing");
+}
+
+#[test]
+fn test_calibration_after_inference() {
+    let frame = common::solid_frame(64, 64, [255, 0, 0]);
+    let raw = common::colour_engine("inference_uncalibrated").classify(&frame, 64, 64).unwrap();
+
+    let calibration = Calibration::parse("temperature = 3\nisotonic.porn = 0:0, 1:0.5\n").unwrap();
+    let engine = common::colour_engine("inference_calibrated").with_calibration(calibration.clone());
+    let calibrated = engine.classify(&frame, 64, 64).unwrap();
+
+    assert_eq!(calibrated.scores, calibration.apply(raw.scores));
+    assert!(calibrated.scores[3] < raw.scores[3]);
+    assert!((calibrated.scores.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+    assert_eq!(engine.calibration(), Some(&calibration));
+}
+
+#[test]
+fn test_classify_by_colour() {
+    let engine = common::colour_engine("classify");
+
+    let porn = engine.classify(&common::solid_frame(64, 48, RED), 64, 48).unwrap();
+    assert_eq!(porn.top_class, "porn");
+    assert!(!porn.is_safe);
+    assert_eq!(porn.safety_group(), SafetyGroup::Adult);
+    assert!(porn.confidence > 0.9);
+
+    let neutral = engine.classify(&common::solid_frame(32, 32, GREEN), 32, 32).unwrap();
+    assert_eq!(neutral.top_class, "neutral");
+    assert!(neutral.is_safe);
+
+    let drawing = engine.classify(&common::solid_frame(300, 500, BLUE), 300, 500).unwrap();
+    assert_eq!(drawing.top_class_index, 0);
+    assert!(drawing.is_safe);
+}
+
+#[test]
+fn test_scores_are_probabilities() {
+    let engine = common::colour_engine("probabilities");
+    let result = engine.classify(&common::solid_frame(16, 16, [120, 80, 200]), 16, 16).unwrap();
+
+    let total: f32 = result.scores.iter().sum();
+    assert!((total - 1.0).abs() < 1e-4, "scores sum to {}", total);
+    assert!(result.scores.iter().all(|&s| (0.0..=1.0).contains(&s)));
+}
+
+#[test]
+fn test_classify_preprocessed_validates_size() {
+    let engine = common::colour_engine("preprocessed");
+    let expected = MODEL_INPUT_CHANNELS * MODEL_INPUT_HEIGHT * MODEL_INPUT_WIDTH;
+
+    assert!(engine.classify_preprocessed(&vec![0.0; expected]).is_ok());
+    assert!(engine.classify_preprocessed(&vec![0.0; expected - 1]).is_err());
+    assert!(engine.classify_preprocessed(&[]).is_err());
+}
+
+#[test]
+fn test_classify_validates_rgba_size() {
+    let engine = common::colour_engine("rgba-size");
+    let frame = common::solid_frame(10, 10, RED);
+
+    assert!(engine.classify(&frame, 10, 11).is_err());
+    assert!(engine.classify(&frame[..frame.len() - 4], 10, 10).is_err());
+}
+
+#[test]
+fn test_non_finite_outputs_fall_back_to_neutral() {
+    let path = common::write_temp_model("nan", &common::channel_mean_model([[0.0; 5]; 3], [f32::NAN; 5]));
+    let engine = MLEngine::new(&path.to_string_lossy()).unwrap();
+
+    let result = engine.classify(&common::solid_frame(8, 8, RED), 8, 8).unwrap();
+    assert_eq!(result.scores, [0.0; 5]);
+    assert_eq!(result.top_class, "neutral");
+}
+
+#[test]
+fn test_missing_model() {
+    let error = MLEngine::new("/nonexistent/model.onnx").err().unwrap();
+    assert!(error.to_string().contains("not found"));
+}
+
+#[test]
+fn test_unsupported_formats() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR"));
+
+    let tflite = dir.join("format.tflite");
+    std::fs::write(&tflite, b"TFL3").unwrap();
+    let error = MLEngine::new(&tflite.to_string_lossy()).err().unwrap();
+    assert!(error.to_string().contains("ONNX"));
+
+    let other = dir.join("format.pt");
+    std::fs::write(&other, b"pickle").unwrap();
+    let error = MLEngine::new(&other.to_string_lossy()).err().unwrap();
+    assert!(error.to_string().contains("Unsupported model format"));
+}
+
+#[test]
+fn test_corrupt_model() {
+    let path = common::write_temp_model("corrupt", b"\x08\x07garbage that is not protobuf");
+    assert!(MLEngine::new(&path.to_string_lossy()).is_err());
+
+    let truncated = common::colour_model();
+    let path = common::write_temp_model("truncated", &truncated[..truncated.len() / 2]);
+    assert!(MLEngine::new(&path.to_string_lossy()).is_err());
+}
+
+#[test]
+fn test_parallel_classification() {
+    let engine = Arc::new(common::colour_engine("parallel"));
+    let workers: Vec<_> = [RED, GREEN, BLUE, RED]
+        .into_iter()
+        .map(|colour| {
+            let engine = Arc::clone(&engine);
+            thread::spawn(move || {
+                (0..5)
+                    .map(|_| engine.classify(&common::solid_frame(40, 40, colour), 40, 40).unwrap().top_class)
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+
+    let expected = ["porn", "neutral", "drawing", "porn"];
+    for (worker, class) in workers.into_iter().zip(expected) {
+        assert!(worker.join().unwrap().iter().all(|c| c == class));
+    }
+}
@@ -0,0 +1,93 @@
+//! Verified model loading: pinned digests, publisher signatures and tampered files.
+
+mod common;
+
+use std::fs;
+
+use ed25519_dalek::{Signer, SigningKey};
+use pavlova_core::inference::MLEngine;
+use pavlova_core::integrity::{
+    manifest_path, pinned_sha256_hex, sha256_hex, signed_message, IntegrityError, ModelIntegrity,
+};
+
+#[test]
+fn test_pinned_digest() {
+    let model = common::colour_model();
+    let path = common::write_temp_model("integrity_digest", &model);
+    let path = path.to_string_lossy();
+
+    let pinned = ModelIntegrity::sha256_hex(&sha256_hex(&model)).unwrap();
+    let engine = MLEngine::new_verified(&path, &pinned).unwrap();
+    assert_eq!(engine.classify(&common::solid_frame(8, 8, [255, 0, 0]), 8, 8).unwrap().top_class, "porn");
+
+    let wrong = ModelIntegrity::sha256_hex(&sha256_hex(b"another model")).unwrap();
+    let error = MLEngine::new_verified(&path, &wrong).err().unwrap();
+    assert!(matches!(error.downcast_ref::<IntegrityError>(), Some(IntegrityError::DigestMismatch { .. })));
+}
+
+#[test]
+fn test_pinned_digest_covers_manifest() {
+    let model = common::colour_model();
+    let path = common::write_temp_model("integrity_pinned_manifest", &model);
+    let manifest = b"labels = drawing,hentai,neutral,porn,sexy\n";
+    fs::write(manifest_path(&path), manifest).unwrap();
+
+    // A model-only pin does not vouch for a manifest dropped next to the model
+    let model_only = ModelIntegrity::sha256_hex(&sha256_hex(&model)).unwrap();
+    let error = MLEngine::new_verified(&path.to_string_lossy(), &model_only).err().unwrap();
+    assert_eq!(error.downcast_ref::<IntegrityError>(), Some(&IntegrityError::ManifestNotPinned));
+
+    let pinned = ModelIntegrity::sha256_hex(&pinned_sha256_hex(&model, Some(manifest))).unwrap();
+    assert!(MLEngine::new_verified(&path.to_string_lossy(), &pinned).is_ok());
+
+    // Swapping porn and neutral must not pass the pin
+    fs::write(manifest_path(&path), b"labels = drawing,hentai,porn,neutral,sexy\n").unwrap();
+    let error = MLEngine::new_verified(&path.to_string_lossy(), &pinned).err().unwrap();
+    assert!(matches!(error.downcast_ref::<IntegrityError>(), Some(IntegrityError::DigestMismatch { .. })));
+}
+
+#[test]
+fn test_signature_over_model_and_manifest() {
+    let model = common::colour_model();
+    let path = common::write_temp_model("integrity_signed", &model);
+    let manifest = b"labels = drawing,hentai,neutral,porn,sexy\n";
+    fs::write(manifest_path(&path), manifest).unwrap();
+
+    let key = SigningKey::from_bytes(&[42u8; 32]);
+    let signature = key.sign(&signed_message(&model, Some(manifest)));
+    let integrity = ModelIntegrity::ed25519(key.verifying_key().as_bytes(), &signature.to_bytes()).unwrap();
+    assert!(MLEngine::new_verified(&path.to_string_lossy(), &integrity).is_ok());
+
+    // Editing the manifest invalidates the signature just like editing the model
+    fs::write(manifest_path(&path), b"labels = neutral\n").unwrap();
+    let error = MLEngine::new_verified(&path.to_string_lossy(), &integrity).err().unwrap();
+    assert_eq!(error.downcast_ref::<IntegrityError>(), Some(&IntegrityError::SignatureInvalid));
+}
+
+#[test]
+fn test_verified_load_from_memory() {
+    let model = common::colour_model();
+    let pinned = ModelIntegrity::sha256_hex(&sha256_hex(&model)).unwrap();
+    assert!(MLEngine::from_bytes_verified(&model, None, &pinned, "memory").is_ok());
+
+    let mut tampered = model.clone();
+    tampered[0] ^= 1;
+    let error = MLEngine::from_bytes_verified(&tampered, None, &pinned, "memory").err().unwrap();
+    assert!(error.is::<IntegrityError>());
+}
+
+#[test]
+fn test_tampered_model_is_rejected_before_parsing() {
+    let mut model = common::colour_model();
+    let pinned = ModelIntegrity::sha256_hex(&sha256_hex(&model)).unwrap();
+    // Garbage tract could not parse: the integrity error must win over a parse error
+    model.truncate(model.len() / 2);
+    let path = common::write_temp_model("integrity_tampered", &model);
+
+    let error = MLEngine::new_verified(&path.to_string_lossy(), &pinned).err().unwrap();
+    assert!(error.is::<IntegrityError>());
+
+    // Ordinary load failures are not integrity errors
+    let missing = MLEngine::new_verified("/nonexistent/model.onnx", &pinned).err().unwrap();
+    assert!(!missing.is::<IntegrityError>());
+}
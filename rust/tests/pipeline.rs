@@ -0,0 +1,86 @@
+//! Classification → policy → effect on a synthetic model.
+
+mod common;
+
+use pavlova_core::phash::{OverrideList, OverrideVerdict};
+use pavlova_core::pipeline::{process_frame, process_frame_with_overrides};
+use pavlova_core::policy::{FilterAction, FilterPolicy, ObscureMode};
+
+#[test]
+fn test_adult_frame_is_obscured() {
+    let engine = common::colour_engine("pipeline-adult");
+    let frame = common::solid_frame(32, 32, [255, 0, 0]);
+
+    let outcome = process_frame(&engine, &FilterPolicy::default(), &frame, 32, 32).unwrap();
+    assert!(outcome.decision.should_filter);
+    assert!(matches!(outcome.decision.action, FilterAction::Blur { .. }));
+    assert_eq!(outcome.output.unwrap().len(), frame.len());
+    assert!(outcome.timings.total >= outcome.timings.classify);
+}
+
+#[test]
+fn test_safe_frame_is_allowed() {
+    let engine = common::colour_engine("pipeline-safe");
+    let frame = common::solid_frame(32, 32, [0, 255, 0]);
+
+    let policy = FilterPolicy {
+        mode: ObscureMode::Pixelate,
+        ..FilterPolicy::default()
+    };
+    let outcome = process_frame(&engine, &policy, &frame, 32, 32).unwrap();
+    assert_eq!(outcome.decision.action, FilterAction::Allow);
+    assert!(outcome.output.is_none());
+}
+
+#[test]
+fn test_invalid_frame_is_an_error() {
+    let engine = common::colour_engine("pipeline-invalid");
+    assert!(process_frame(&engine, &FilterPolicy::default(), &[0u8; 7], 2, 2).is_err());
+}
+
+/// Frame whose `channel` brightens left to right (`horizontal`) or top to bottom
+fn gradient_frame(channel: usize, horizontal: bool) -> Vec<u8> {
+    let mut frame = common::solid_frame(32, 32, [0, 0, 0]);
+    for (i, pixel) in frame.chunks_mut(4).enumerate() {
+        let position = if horizontal { i % 32 } else { i / 32 };
+        pixel[channel] = 160 + 3 * position as u8;
+    }
+    frame
+}
+
+#[test]
+fn test_listed_images_override_the_model() {
+    let engine = common::colour_engine("pipeline-overrides");
+    let policy = FilterPolicy::default();
+    let (red, green) = (gradient_frame(0, true), gradient_frame(1, false));
+
+    let mut overrides = OverrideList::default();
+    overrides.add_image(&red, 32, 32, OverrideVerdict::Allow).unwrap();
+    overrides.add_image(&green, 32, 32, OverrideVerdict::Block).unwrap();
+
+    // Saved and reloaded, as the app does between sessions
+    let path = std::env::temp_dir().join(format!("pipeline-overrides-{}.txt", std::process::id()));
+    overrides.save(&path).unwrap();
+    let overrides = OverrideList::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // Slightly recompressed copy of an adult frame the user allowed
+    let mut recompressed = red.clone();
+    for (i, value) in recompressed.iter_mut().enumerate().filter(|(i, _)| i % 4 != 3) {
+        *value = value.saturating_add((i % 3) as u8);
+    }
+    let allowed = process_frame_with_overrides(&engine, &policy, &overrides, &recompressed, 32, 32).unwrap();
+    assert_eq!(allowed.classification.top_class, "porn");
+    assert_eq!(allowed.override_match.unwrap().verdict, OverrideVerdict::Allow);
+    assert_eq!(allowed.decision.action, FilterAction::Allow);
+
+    let blocked = process_frame_with_overrides(&engine, &policy, &overrides, &green, 32, 32).unwrap();
+    assert_eq!(blocked.classification.top_class, "neutral");
+    assert_eq!(blocked.decision, policy.block_decision());
+    assert!(blocked.output.is_some());
+
+    // Unlisted frames follow the policy
+    let unlisted = process_frame_with_overrides(&engine, &policy, &overrides, &gradient_frame(0, false), 32, 32).unwrap();
+    assert!(unlisted.override_match.is_none());
+    assert!(unlisted.decision.should_filter);
+}
@@ -0,0 +1,152 @@
+//! Property tests: public buffer-handling functions return errors on bad input instead of panicking.
+//!
+//! Run more cases with `PROPTEST_CASES=10000 cargo test --test properties`; the cargo-fuzz
+//! targets in `fuzz/` cover the same functions with coverage guidance.
+
+mod common;
+
+use proptest::prelude::*;
+
+use pavlova_core::config::{MODEL_INPUT_CHANNELS, MODEL_INPUT_HEIGHT, MODEL_INPUT_WIDTH};
+use pavlova_core::image::{blur, pixelate, preprocess_for_model, resize_bilinear_rgba, MAX_BLUR_RADIUS};
+use pavlova_core::inference::ClassificationResult;
+use pavlova_core::policy::FilterPolicy;
+use pavlova_core::utils::yuv420_to_rgba;
+
+/// Dimensions plus a buffer that is usually, but not always, the matching RGBA size
+fn image_input(max_side: usize) -> impl Strategy<Value = (Vec<u8>, usize, usize)> {
+    (0..=max_side, 0..=max_side, any::<bool>(), -8i64..8).prop_flat_map(|(width, height, exact, skew)| {
+        let len = if exact {
+            width * height * 4
+        } else {
+            (width as i64 * height as i64 * 4 + skew).max(0) as usize
+        };
+        (proptest::collection::vec(any::<u8>(), len), Just(width), Just(height))
+    })
+}
+
+/// Any f32 including NaN, infinities and out-of-range values
+fn any_radius() -> impl Strategy<Value = f32> {
+    prop_oneof![
+        -2.0f32..40.0,
+        Just(f32::NAN),
+        Just(f32::INFINITY),
+        Just(-0.0),
+        Just(MAX_BLUR_RADIUS),
+        Just(MAX_BLUR_RADIUS + 1.0),
+        any::<f32>(),
+    ]
+}
+
+fn valid_size(data: &[u8], width: usize, height: usize) -> bool {
+    data.len() == width * height * 4
+}
+
+proptest! {
+    #[test]
+    fn blur_never_panics((data, width, height) in image_input(24), radius in any_radius()) {
+        match blur(&data, width, height, radius) {
+            Ok(output) => {
+                prop_assert!(valid_size(&data, width, height));
+                prop_assert_eq!(output.len(), data.len());
+                // Alpha is never modified
+                prop_assert!(output.iter().skip(3).step_by(4).eq(data.iter().skip(3).step_by(4)));
+            }
+            Err(_) => prop_assert!(!valid_size(&data, width, height) || !(0.0..=MAX_BLUR_RADIUS).contains(&radius)),
+        }
+    }
+
+    #[test]
+    fn pixelate_never_panics((data, width, height) in image_input(32), block_size in prop_oneof![0usize..40, Just(usize::MAX)]) {
+        match pixelate(&data, width, height, block_size) {
+            Ok(output) => {
+                prop_assert!(valid_size(&data, width, height) && block_size > 0);
+                prop_assert_eq!(output.len(), data.len());
+                if block_size == 1 {
+                    prop_assert_eq!(output, data);
+                }
+            }
+            Err(_) => prop_assert!(!valid_size(&data, width, height) || block_size == 0),
+        }
+    }
+
+    #[test]
+    fn resize_never_panics((data, width, height) in image_input(24), dst_w in 0usize..64, dst_h in 0usize..64) {
+        match resize_bilinear_rgba(&data, width, height, dst_w, dst_h) {
+            Ok(output) => prop_assert_eq!(output.len(), dst_w * dst_h * 4),
+            Err(_) => prop_assert!(!valid_size(&data, width, height) || width * height * dst_w * dst_h == 0),
+        }
+    }
+
+    #[test]
+    fn resize_keeps_uniform_colour(width in 1usize..20, height in 1usize..20, dst_w in 1usize..50, dst_h in 1usize..50, pixel in any::<[u8; 4]>()) {
+        let data = pixel.repeat(width * height);
+        let output = resize_bilinear_rgba(&data, width, height, dst_w, dst_h).unwrap();
+        prop_assert!(output.chunks_exact(4).all(|p| p == pixel));
+    }
+
+    #[test]
+    fn preprocess_never_panics((data, width, height) in image_input(24)) {
+        match preprocess_for_model(&data, width, height) {
+            Ok(tensor) => {
+                prop_assert_eq!(tensor.len(), MODEL_INPUT_CHANNELS * MODEL_INPUT_WIDTH * MODEL_INPUT_HEIGHT);
+                prop_assert!(tensor.iter().all(|v| (-1.0..=1.0).contains(v)));
+            }
+            Err(_) => prop_assert!(!valid_size(&data, width, height) || width * height == 0),
+        }
+    }
+
+    #[test]
+    fn yuv420_never_panics(
+        width in 0usize..24,
+        height in 0usize..24,
+        y in proptest::collection::vec(any::<u8>(), 0..700),
+        u in proptest::collection::vec(any::<u8>(), 0..200),
+        v in proptest::collection::vec(any::<u8>(), 0..200),
+    ) {
+        let chroma = width.div_ceil(2) * height.div_ceil(2);
+        match yuv420_to_rgba(&y, &u, &v, width, height) {
+            Ok(rgba) => {
+                prop_assert_eq!(rgba.len(), width * height * 4);
+                prop_assert!(rgba.iter().skip(3).step_by(4).all(|&a| a == 255));
+            }
+            Err(_) => prop_assert!(y.len() < width * height || u.len() < chroma || v.len() < chroma),
+        }
+    }
+
+    #[test]
+    fn scores_always_yield_a_valid_result(scores in any::<[f32; 5]>()) {
+        let result = ClassificationResult::from_scores(scores);
+        prop_assert!(result.top_class_index < 5);
+        prop_assert!(result.scores.iter().all(|s| s.is_finite()));
+        let _ = FilterPolicy::default().evaluate(&result);
+    }
+
+    #[test]
+    fn policy_profile_parsing_never_panics(text in "\\PC*", line in "[a-z_]{0,24} ?=? ?[-0-9.a-z]{0,8}") {
+        let _ = FilterPolicy::from_profile(&text);
+        if let Ok(policy) = FilterPolicy::from_profile(&line) {
+            prop_assert!((0.0..=1.0).contains(&policy.unsafe_threshold));
+            prop_assert!((0.0..=1.0).contains(&policy.suggestive_threshold));
+        }
+    }
+}
+
+proptest! {
+    // Each case runs the model, so keep the count modest
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn classify_never_panics((data, width, height) in image_input(16)) {
+        let engine = common::colour_engine("properties-classify");
+        let result = engine.classify(&data, width, height);
+        prop_assert_eq!(result.is_ok(), valid_size(&data, width, height) && width * height > 0);
+    }
+
+    #[test]
+    fn classify_preprocessed_never_panics(len in 0usize..200_000, value in any::<f32>()) {
+        let engine = common::colour_engine("properties-preprocessed");
+        let result = engine.classify_preprocessed(&vec![value; len]);
+        prop_assert_eq!(result.is_ok(), len == MODEL_INPUT_CHANNELS * MODEL_INPUT_WIDTH * MODEL_INPUT_HEIGHT);
+    }
+}
@@ -0,0 +1,94 @@
+//! `SwappableEngine`: self-tested model replacement, rollback and swaps under load.
+
+mod common;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use pavlova_core::inference::MLEngine;
+use pavlova_core::swap::{SelfTest, SelfTestCase, SwappableEngine};
+
+const RED: [u8; 3] = [255, 0, 0];
+
+fn inverted_model() -> MLEngine {
+    MLEngine::from_bytes(&common::inverted_colour_model(), "inverted").unwrap()
+}
+
+fn nan_model() -> MLEngine {
+    MLEngine::from_bytes(&common::channel_mean_model([[0.0; 5]; 3], [f32::NAN; 5]), "nan").unwrap()
+}
+
+fn top_class(engine: &MLEngine) -> String {
+    engine.classify(&common::solid_frame(16, 16, RED), 16, 16).unwrap().top_class
+}
+
+#[test]
+fn test_swap_and_rollback() {
+    let engine = SwappableEngine::new(common::colour_engine("swap_initial"));
+    let before = engine.current();
+
+    assert_eq!(engine.swap(inverted_model()).unwrap(), 1);
+    assert_eq!(top_class(&engine.current()), "drawing");
+    // A reference taken before the swap still classifies with the old model
+    assert_eq!(top_class(&before), "porn");
+
+    assert!(engine.rollback());
+    assert_eq!(top_class(&engine.current()), "porn");
+    assert_eq!(engine.generation(), 2);
+    assert!(!engine.rollback());
+}
+
+#[test]
+fn test_failed_self_test_keeps_current_model() {
+    let engine = SwappableEngine::new(common::colour_engine("swap_self_test"));
+    let live_path = engine.current().model_path().to_string();
+
+    let error = engine.swap(nan_model()).err().unwrap();
+    assert!(error.to_string().contains("probabilities"));
+    assert_eq!(engine.generation(), 0);
+    assert_eq!(engine.current().model_path(), live_path);
+
+    let expect_porn = SelfTest {
+        cases: vec![SelfTestCase {
+            rgba: common::solid_frame(8, 8, RED),
+            width: 8,
+            height: 8,
+            expected_class: Some(3),
+        }],
+    };
+    let engine = SwappableEngine::with_self_test(common::colour_engine("swap_expected"), expect_porn);
+    assert!(engine.swap(inverted_model()).is_err());
+    assert_eq!(top_class(&engine.current()), "porn");
+}
+
+#[test]
+fn test_background_swap_under_load() {
+    let engine = Arc::new(SwappableEngine::new(common::colour_engine("swap_load")));
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let workers: Vec<_> = (0..3)
+        .map(|_| {
+            let engine = Arc::clone(&engine);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                let mut seen = Vec::new();
+                while !stop.load(Ordering::Relaxed) {
+                    seen.push(top_class(&engine.current()));
+                }
+                seen
+            })
+        })
+        .collect();
+
+    assert_eq!(engine.spawn_swap(|| Ok(nan_model())).join().unwrap().err().map(|e| e.contains("Self-test")), Some(true));
+    assert_eq!(engine.spawn_swap(|| Ok(inverted_model())).join().unwrap(), Ok(1));
+    assert!(engine.spawn_swap(|| Err("download failed".into())).join().unwrap().is_err());
+    stop.store(true, Ordering::Relaxed);
+
+    for worker in workers {
+        // Every frame was classified by one of the two valid models, never by the rejected one
+        assert!(worker.join().unwrap().iter().all(|class| class == "porn" || class == "drawing"));
+    }
+    assert_eq!(top_class(&engine.current()), "drawing");
+}
@@ -0,0 +1,80 @@
+//! `TextDetector`: loading, DB box decoding at frame scale and obscuring detected text.
+
+mod common;
+
+use pavlova_core::image::{PreprocessSpec, Rect};
+use pavlova_core::pipeline::apply_action_to_regions;
+use pavlova_core::policy::FilterAction;
+use pavlova_core::text::{TextDetectionConfig, TextDetector, TextDetectorKind};
+
+fn input(width: usize, height: usize) -> PreprocessSpec {
+    PreprocessSpec {
+        width,
+        height,
+        ..PreprocessSpec::default()
+    }
+}
+
+/// White 128×96 frame with black "lines of text" at the given rectangles
+fn page(lines: &[Rect]) -> Vec<u8> {
+    let mut frame = common::solid_frame(128, 96, [255, 255, 255]);
+    for line in lines {
+        for y in line.y..line.y + line.height {
+            for x in line.x..line.x + line.width {
+                frame[(y * 128 + x) * 4..(y * 128 + x) * 4 + 3].copy_from_slice(&[0, 0, 0]);
+            }
+        }
+    }
+    frame
+}
+
+#[test]
+fn test_db_boxes_in_frame_coordinates() {
+    // The model sees the frame at half resolution; boxes come back in frame pixels
+    let detector = TextDetector::from_bytes(&common::dark_text_model(64, 48), TextDetectorKind::Db, input(64, 48), "dark-text")
+        .unwrap()
+        .with_config(TextDetectionConfig {
+            unclip_ratio: 0.0,
+            ..TextDetectionConfig::for_kind(TextDetectorKind::Db)
+        });
+    let lines = [Rect::new(16, 16, 64, 8), Rect::new(16, 48, 96, 16)];
+
+    let mut boxes = detector.detect(&page(&lines), 128, 96).unwrap();
+    assert_eq!(boxes.len(), 2);
+    boxes.sort_by_key(|b| b.rect.y);
+    for (found, line) in boxes.iter().zip(&lines) {
+        assert!(found.score > 0.9);
+        // Within a map cell (2 frame pixels) plus bilinear bleed of each edge
+        for (got, want) in [(found.rect.x, line.x), (found.rect.y, line.y)] {
+            assert!(got.abs_diff(want) <= 2, "{:?} vs {:?}", found.rect, line);
+        }
+        for (got, want) in [(found.rect.width, line.width), (found.rect.height, line.height)] {
+            assert!(got.abs_diff(want) <= 4, "{:?} vs {:?}", found.rect, line);
+        }
+    }
+
+    assert!(detector.detect(&page(&[]), 128, 96).unwrap().is_empty());
+}
+
+#[test]
+fn test_detected_text_can_be_obscured() {
+    let detector = TextDetector::from_bytes(&common::dark_text_model(64, 48), TextDetectorKind::Db, input(64, 48), "dark-text-obscure").unwrap();
+    let frame = page(&[Rect::new(16, 16, 64, 8)]);
+
+    let regions: Vec<Rect> = detector.detect(&frame, 128, 96).unwrap().iter().map(|b| b.rect).collect();
+    assert_eq!(regions.len(), 1);
+    let obscured = apply_action_to_regions(&frame, 128, 96, &regions, FilterAction::Blur { radius: 4.0 }).unwrap().unwrap();
+    // Text is smeared, the far corner untouched
+    assert_ne!(obscured[(20 * 128 + 40) * 4], 0);
+    assert_eq!(obscured[(90 * 128 + 120) * 4..(90 * 128 + 121) * 4], frame[(90 * 128 + 120) * 4..(90 * 128 + 121) * 4]);
+}
+
+#[test]
+fn test_incompatible_models_are_rejected() {
+    // A DB probability map has no EAST geometry
+    let result = TextDetector::from_bytes(&common::dark_text_model(64, 48), TextDetectorKind::East, input(64, 48), "east");
+    assert!(result.err().unwrap().to_string().contains("geometry"));
+    // A classifier has no map at all
+    assert!(TextDetector::from_bytes(&common::colour_model(), TextDetectorKind::Db, PreprocessSpec::default(), "classifier").is_err());
+    assert!(TextDetector::from_bytes(&[], TextDetectorKind::Db, input(64, 48), "empty").is_err());
+}